@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE highlight DROP COLUMN highlight_color_id;
+
+DROP TABLE highlight_color;
\ No newline at end of file
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE highlight_color (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    sort_order INTEGER
+);
+
+ALTER TABLE highlight
+ADD COLUMN highlight_color_id TEXT REFERENCES highlight_color(id);
+
+INSERT INTO highlight_color (id, name, color, sort_order)
+SELECT lower(hex(randomblob(16))), color, color, ROW_NUMBER() OVER (ORDER BY color)
+FROM (SELECT DISTINCT color FROM highlight);
+
+UPDATE highlight
+SET highlight_color_id = (
+    SELECT highlight_color.id
+    FROM highlight_color
+    WHERE highlight_color.color = highlight.color
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE highlight_note_revision;
+
+DROP TABLE highlight_note;
\ No newline at end of file
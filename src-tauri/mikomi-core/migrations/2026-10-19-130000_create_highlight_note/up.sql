@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE highlight_note (
+    id TEXT PRIMARY KEY NOT NULL,
+    highlight_id TEXT NOT NULL,
+    parent_id TEXT,
+    content TEXT NOT NULL,
+    date_added INTEGER NOT NULL,
+    date_modified INTEGER,
+    FOREIGN KEY (highlight_id) REFERENCES highlight(id),
+    FOREIGN KEY (parent_id) REFERENCES highlight_note(id)
+);
+
+CREATE TABLE highlight_note_revision (
+    id TEXT PRIMARY KEY NOT NULL,
+    highlight_note_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    date_added INTEGER NOT NULL,
+    FOREIGN KEY (highlight_note_id) REFERENCES highlight_note(id)
+);
+
+INSERT INTO highlight_note (id, highlight_id, parent_id, content, date_added, date_modified)
+SELECT lower(hex(randomblob(16))), id, NULL, note, date_added, NULL
+FROM highlight
+WHERE note != '';
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE reading_session (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    start_percentage INTEGER,
+    end_percentage INTEGER,
+    pages_read INTEGER DEFAULT 0 NOT NULL,
+    characters_read INTEGER DEFAULT 0 NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES book(id)
+);
+
+CREATE INDEX reading_session_book_id_index ON reading_session(book_id);
+
+CREATE INDEX reading_session_start_time_index ON reading_session(start_time);
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE reading_goal (
+    id TEXT PRIMARY KEY NOT NULL,
+    goal_type TEXT NOT NULL,
+    target INTEGER NOT NULL,
+    year INTEGER,
+    CONSTRAINT unknown_reading_goal_type
+        CHECK (goal_type IN ('Books per year', 'Minutes per day'))
+);
\ No newline at end of file
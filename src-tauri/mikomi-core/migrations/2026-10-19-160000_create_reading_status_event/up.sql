@@ -0,0 +1,36 @@
+-- Your SQL goes here
+-- Older versions accepted any string, so the spellings of the three statuses are normalised.
+-- Any other value makes the migration fail instead of being overwritten.
+UPDATE book
+SET reading_status = CASE lower(trim(reading_status))
+    WHEN 'reading' THEN 'Reading'
+    WHEN 'plan to read' THEN 'Plan to read'
+    WHEN 'finished' THEN 'Finished'
+    ELSE reading_status
+END;
+
+CREATE TEMPORARY TABLE reading_status_check (
+    reading_status TEXT NOT NULL,
+    CONSTRAINT unknown_reading_status_in_book
+        CHECK (reading_status IN ('Reading', 'Plan to read', 'Finished'))
+);
+
+INSERT INTO reading_status_check (reading_status)
+SELECT reading_status FROM book;
+
+DROP TABLE reading_status_check;
+
+CREATE TABLE reading_status_event (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT NOT NULL,
+    reading_status TEXT NOT NULL,
+    date_added INTEGER NOT NULL,
+    previous_reading_status TEXT,
+    FOREIGN KEY (book_id) REFERENCES book(id)
+);
+
+CREATE INDEX reading_status_event_book_id_index ON reading_status_event(book_id);
+
+INSERT INTO reading_status_event (id, book_id, reading_status, date_added)
+SELECT lower(hex(randomblob(16))), id, reading_status, COALESCE(last_read, date_added)
+FROM book;
\ No newline at end of file
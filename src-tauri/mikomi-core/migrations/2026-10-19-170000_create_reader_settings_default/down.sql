@@ -0,0 +1,54 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE book_settings_old (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT NOT NULL UNIQUE,
+    width INTEGER,
+    height INTEGER,
+    percentage INTEGER,
+    last_element TEXT,
+    font_size INTEGER DEFAULT 16 NOT NULL,
+    line_height TEXT DEFAULT 'normal' NOT NULL,
+    margins INTEGER DEFAULT 0 NOT NULL,
+    text_align TEXT DEFAULT 'initial' NOT NULL,
+    column_count INTEGER DEFAULT 1 NOT NULL,
+    writing_mode TEXT NOT NULL,
+    font_family TEXT DEFAULT 'initial' NOT NULL,
+    background_color TEXT NOT NULL,
+    color TEXT NOT NULL,
+    link_color TEXT NOT NULL,
+    primary_color TEXT NOT NULL,
+    image_blend_mode TEXT NOT NULL,
+    last_page INTEGER,
+    FOREIGN KEY (book_id) REFERENCES book(id)
+);
+
+INSERT INTO book_settings_old
+SELECT
+    book_settings.id,
+    book_settings.book_id,
+    book_settings.width,
+    book_settings.height,
+    book_settings.percentage,
+    book_settings.last_element,
+    COALESCE(book_settings.font_size, defaults.font_size),
+    COALESCE(book_settings.line_height, defaults.line_height),
+    COALESCE(book_settings.margins, defaults.margins),
+    COALESCE(book_settings.text_align, defaults.text_align),
+    COALESCE(book_settings.column_count, defaults.column_count),
+    COALESCE(book_settings.writing_mode, defaults.writing_mode),
+    COALESCE(book_settings.font_family, defaults.font_family),
+    COALESCE(book_settings.background_color, defaults.background_color),
+    COALESCE(book_settings.color, defaults.color),
+    COALESCE(book_settings.link_color, defaults.link_color),
+    COALESCE(book_settings.primary_color, defaults.primary_color),
+    COALESCE(book_settings.image_blend_mode, defaults.image_blend_mode),
+    book_settings.last_page
+FROM book_settings
+CROSS JOIN reader_settings_default AS defaults
+WHERE defaults.id = 'global';
+
+DROP TABLE book_settings;
+
+ALTER TABLE book_settings_old RENAME TO book_settings;
+
+DROP TABLE reader_settings_default;
\ No newline at end of file
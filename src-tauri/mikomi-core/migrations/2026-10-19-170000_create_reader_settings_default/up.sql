@@ -0,0 +1,102 @@
+-- Your SQL goes here
+CREATE TABLE reader_settings_default (
+    id TEXT PRIMARY KEY NOT NULL,
+    language TEXT UNIQUE,
+    collection_id TEXT UNIQUE,
+    font_size INTEGER,
+    line_height TEXT,
+    margins INTEGER,
+    text_align TEXT,
+    column_count INTEGER,
+    writing_mode TEXT,
+    font_family TEXT,
+    background_color TEXT,
+    color TEXT,
+    link_color TEXT,
+    primary_color TEXT,
+    image_blend_mode TEXT,
+    FOREIGN KEY (language) REFERENCES language(name),
+    FOREIGN KEY (collection_id) REFERENCES collection(id),
+    CHECK (language IS NULL OR collection_id IS NULL)
+);
+
+INSERT INTO reader_settings_default (
+    id,
+    font_size,
+    line_height,
+    margins,
+    text_align,
+    column_count,
+    writing_mode,
+    font_family,
+    background_color,
+    color,
+    link_color,
+    primary_color,
+    image_blend_mode
+)
+VALUES (
+    'global',
+    16,
+    'normal',
+    0,
+    'initial',
+    1,
+    'horizontal',
+    'initial',
+    '#ffffff',
+    '#333333',
+    '#007acc',
+    '#4181e3',
+    'normal'
+);
+
+CREATE TABLE book_settings_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT NOT NULL UNIQUE,
+    width INTEGER,
+    height INTEGER,
+    percentage INTEGER,
+    last_element TEXT,
+    last_page INTEGER,
+    font_size INTEGER,
+    line_height TEXT,
+    margins INTEGER,
+    text_align TEXT,
+    column_count INTEGER,
+    writing_mode TEXT,
+    font_family TEXT,
+    background_color TEXT,
+    color TEXT,
+    link_color TEXT,
+    primary_color TEXT,
+    image_blend_mode TEXT,
+    FOREIGN KEY (book_id) REFERENCES book(id)
+);
+
+INSERT INTO book_settings_new
+SELECT
+    id,
+    book_id,
+    width,
+    height,
+    percentage,
+    last_element,
+    last_page,
+    NULLIF(font_size, 16),
+    NULLIF(line_height, 'normal'),
+    NULLIF(margins, 0),
+    NULLIF(text_align, 'initial'),
+    NULLIF(column_count, 1),
+    writing_mode,
+    NULLIF(font_family, 'initial'),
+    background_color,
+    color,
+    link_color,
+    primary_color,
+    image_blend_mode
+FROM book_settings;
+
+DROP TABLE book_settings;
+
+ALTER TABLE book_settings_new RENAME TO book_settings;
\ No newline at end of file
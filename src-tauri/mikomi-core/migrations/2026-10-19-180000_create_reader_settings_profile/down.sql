@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE reader_settings_profile;
\ No newline at end of file
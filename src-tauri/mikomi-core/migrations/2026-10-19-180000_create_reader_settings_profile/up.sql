@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE reader_settings_profile (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    font_size INTEGER NOT NULL,
+    line_height TEXT NOT NULL,
+    margins INTEGER NOT NULL,
+    text_align TEXT NOT NULL,
+    column_count INTEGER NOT NULL,
+    writing_mode TEXT NOT NULL,
+    font_family TEXT NOT NULL
+);
\ No newline at end of file
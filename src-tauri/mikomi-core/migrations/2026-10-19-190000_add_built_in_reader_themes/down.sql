@@ -0,0 +1,27 @@
+-- This file should undo anything in `up.sql`
+UPDATE reader_settings_default
+SET
+    background_color = COALESCE(background_color, (SELECT background_color FROM reader_theme WHERE reader_theme.id = reader_settings_default.reader_theme_id)),
+    color = COALESCE(color, (SELECT color FROM reader_theme WHERE reader_theme.id = reader_settings_default.reader_theme_id)),
+    link_color = COALESCE(link_color, (SELECT link_color FROM reader_theme WHERE reader_theme.id = reader_settings_default.reader_theme_id)),
+    primary_color = COALESCE(primary_color, (SELECT primary_color FROM reader_theme WHERE reader_theme.id = reader_settings_default.reader_theme_id)),
+    image_blend_mode = COALESCE(image_blend_mode, (SELECT image_blend_mode FROM reader_theme WHERE reader_theme.id = reader_settings_default.reader_theme_id))
+WHERE reader_theme_id IS NOT NULL;
+
+UPDATE book_settings
+SET
+    background_color = COALESCE(background_color, (SELECT background_color FROM reader_theme WHERE reader_theme.id = book_settings.reader_theme_id)),
+    color = COALESCE(color, (SELECT color FROM reader_theme WHERE reader_theme.id = book_settings.reader_theme_id)),
+    link_color = COALESCE(link_color, (SELECT link_color FROM reader_theme WHERE reader_theme.id = book_settings.reader_theme_id)),
+    primary_color = COALESCE(primary_color, (SELECT primary_color FROM reader_theme WHERE reader_theme.id = book_settings.reader_theme_id)),
+    image_blend_mode = COALESCE(image_blend_mode, (SELECT image_blend_mode FROM reader_theme WHERE reader_theme.id = book_settings.reader_theme_id))
+WHERE reader_theme_id IS NOT NULL;
+
+ALTER TABLE reader_settings_default DROP COLUMN reader_theme_id;
+
+ALTER TABLE book_settings DROP COLUMN reader_theme_id;
+
+DELETE FROM reader_theme
+WHERE built_in = 1;
+
+ALTER TABLE reader_theme DROP COLUMN built_in;
\ No newline at end of file
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+ALTER TABLE reader_theme
+ADD COLUMN built_in BOOLEAN NOT NULL DEFAULT 0;
+
+INSERT INTO reader_theme (id, name, background_color, color, link_color, image_blend_mode, primary_color, built_in)
+VALUES
+    ('light', 'Light', '#ffffff', '#333333', '#007acc', 'normal', '#4181e3', 1),
+    ('dark', 'Dark', '#202124', '#f8f9fa', '#4ca6ff', 'normal', '#6c73d5', 1),
+    ('dark-contrast', 'Dark contrast', '#121212', '#ffffff', '#4ca6ff', 'normal', '#6c73d5', 1),
+    ('sepia', 'Sepia', '#fbf0d9', '#5f4b32', '#9b674c', 'multiply', '#b77d7d', 1),
+    ('sepia-contrast', 'Sepia contrast', '#fbf0d9', '#000000', '#9b674c', 'multiply', '#b77d7d', 1),
+    ('green', 'Green', '#c5e7ce', '#3a4a43', '#19568f', 'normal', '#7880a9', 1)
+ON CONFLICT (id) DO NOTHING;
+
+ALTER TABLE book_settings
+ADD COLUMN reader_theme_id TEXT REFERENCES reader_theme(id);
+
+ALTER TABLE reader_settings_default
+ADD COLUMN reader_theme_id TEXT REFERENCES reader_theme(id);
+
+UPDATE reader_settings_default
+SET
+    reader_theme_id = 'light',
+    background_color = NULL,
+    color = NULL,
+    link_color = NULL,
+    primary_color = NULL,
+    image_blend_mode = NULL
+WHERE id = 'global';
\ No newline at end of file
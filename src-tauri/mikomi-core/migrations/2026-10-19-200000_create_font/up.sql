@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE font (
+    id TEXT PRIMARY KEY NOT NULL,
+    family TEXT NOT NULL,
+    style TEXT NOT NULL,
+    weight INTEGER NOT NULL,
+    format TEXT NOT NULL,
+    date_added INTEGER NOT NULL
+);
\ No newline at end of file
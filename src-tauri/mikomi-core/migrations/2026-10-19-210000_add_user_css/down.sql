@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE disabled_stylesheet;
+
+DROP TABLE app_setting;
+
+ALTER TABLE book_settings DROP COLUMN user_css;
\ No newline at end of file
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE book_settings ADD COLUMN user_css TEXT;
+
+CREATE TABLE app_setting (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE disabled_stylesheet (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES book(id),
+    UNIQUE (book_id, path)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE collection DROP COLUMN smart_filter;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE collection ADD COLUMN smart_filter TEXT;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE collection DROP COLUMN parent_id;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE collection ADD COLUMN parent_id TEXT REFERENCES collection(id);
\ No newline at end of file
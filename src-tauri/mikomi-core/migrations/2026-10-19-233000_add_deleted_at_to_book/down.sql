@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX book_deleted_at_index;
+
+ALTER TABLE book DROP COLUMN deleted_at;
\ No newline at end of file
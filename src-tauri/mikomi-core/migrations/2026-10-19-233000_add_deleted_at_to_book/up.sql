@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE book ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX book_deleted_at_index ON book(deleted_at);
\ No newline at end of file
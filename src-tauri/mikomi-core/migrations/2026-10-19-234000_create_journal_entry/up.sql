@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE journal_entry (
+    id TEXT PRIMARY KEY NOT NULL,
+    sequence INTEGER NOT NULL UNIQUE,
+    description TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    undone BOOLEAN NOT NULL DEFAULT 0,
+    date_added INTEGER NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE sync_record (
+    book_id TEXT NOT NULL,
+    record_key TEXT NOT NULL,
+    value TEXT,
+    modified INTEGER NOT NULL,
+    device_id TEXT NOT NULL,
+    PRIMARY KEY (book_id, record_key),
+    FOREIGN KEY (book_id) REFERENCES book(id)
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE opds_catalog (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    username TEXT,
+    password TEXT,
+    date_added INTEGER NOT NULL
+);
\ No newline at end of file
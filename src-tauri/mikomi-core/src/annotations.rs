@@ -0,0 +1,401 @@
+use crate::journal::{self, JournalChange};
+use crate::{current_timestamp, models, schema, Error, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Serialize, Type)]
+pub struct HighlightColorWithHighlights {
+    pub highlight_color: Option<models::HighlightColor>,
+    pub highlights: Vec<models::Highlight>,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+pub struct HighlightColorIdWithSortOrder {
+    pub id: String,
+    pub sort_order: i32,
+}
+
+pub fn add_bookmark(conn: &mut SqliteConnection, new_bookmark: models::Bookmark) -> Result<()> {
+    diesel::insert_into(schema::bookmark::table)
+        .values(&new_bookmark)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_bookmark(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let bookmark: Option<models::Bookmark> = schema::bookmark::table
+            .find(id)
+            .select(models::Bookmark::as_select())
+            .get_result(conn)
+            .optional()?;
+
+        diesel::delete(schema::bookmark::table.filter(schema::bookmark::id.eq(id)))
+            .execute(conn)?;
+
+        if let Some(bookmark) = bookmark {
+            journal::record_journal_entry(
+                conn,
+                "Remove bookmark",
+                vec![JournalChange::Bookmark {
+                    id: id.to_string(),
+                    before: Some(bookmark),
+                    after: None,
+                }],
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+pub fn update_bookmark(conn: &mut SqliteConnection, id: &str, display_text: &str) -> Result<()> {
+    diesel::update(schema::bookmark::table.filter(schema::bookmark::id.eq(id)))
+        .set(schema::bookmark::display_text.eq(display_text))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Highlights may only be labelled with a color of the palette
+fn check_highlight_color_exists(
+    conn: &mut SqliteConnection,
+    highlight_color_id: Option<&str>,
+) -> Result<()> {
+    let highlight_color_id = match highlight_color_id {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    let exists: bool = diesel::select(diesel::dsl::exists(
+        schema::highlight_color::table.find(highlight_color_id),
+    ))
+    .get_result(conn)?;
+    if !exists {
+        return Err(Error::Rejected(String::from("Unknown highlight color")));
+    }
+
+    Ok(())
+}
+
+pub fn add_highlight(conn: &mut SqliteConnection, new_highlight: models::Highlight) -> Result<()> {
+    check_highlight_color_exists(conn, new_highlight.highlight_color_id.as_deref())?;
+
+    diesel::insert_into(schema::highlight::table)
+        .values(&new_highlight)
+        .on_conflict(schema::highlight::id)
+        .do_update()
+        .set(&new_highlight)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_highlight(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let changes = journal::highlight_removal_changes(conn, &[id.to_string()])?;
+
+        delete_highlight_notes(conn, vec![id.to_string()])?;
+        diesel::delete(schema::highlight::table.filter(schema::highlight::id.eq(id)))
+            .execute(conn)?;
+
+        journal::record_journal_entry(conn, "Remove highlight", changes)
+    })
+}
+
+pub fn update_highlight(
+    conn: &mut SqliteConnection,
+    id: &str,
+    note: &str,
+    color: &str,
+    highlight_color_id: Option<&str>,
+) -> Result<()> {
+    check_highlight_color_exists(conn, highlight_color_id)?;
+
+    diesel::update(schema::highlight::table.filter(schema::highlight::id.eq(id)))
+        .set((
+            schema::highlight::note.eq(note),
+            schema::highlight::color.eq(color),
+            schema::highlight::highlight_color_id.eq(highlight_color_id),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn get_highlights(
+    conn: &mut SqliteConnection,
+    book_id: Option<&str>,
+    highlight_color_ids: Vec<String>,
+) -> Result<Vec<models::Highlight>> {
+    let mut query = schema::highlight::table
+        .select(models::Highlight::as_select())
+        .order(schema::highlight::date_added)
+        .into_boxed();
+
+    if let Some(book_id) = book_id {
+        query = query.filter(schema::highlight::book_id.eq(book_id));
+    }
+    if !highlight_color_ids.is_empty() {
+        query = query.filter(schema::highlight::highlight_color_id.eq_any(highlight_color_ids));
+    }
+
+    Ok(query.load(conn)?)
+}
+
+pub fn get_highlights_grouped_by_color(
+    conn: &mut SqliteConnection,
+    book_id: Option<&str>,
+) -> Result<Vec<HighlightColorWithHighlights>> {
+    conn.transaction(|conn| {
+        let highlight_colors: Vec<models::HighlightColor> = schema::highlight_color::table
+            .order(schema::highlight_color::sort_order)
+            .select(models::HighlightColor::as_select())
+            .load(conn)?;
+
+        let mut query = schema::highlight::table
+            .select(models::Highlight::as_select())
+            .order(schema::highlight::date_added)
+            .into_boxed();
+        if let Some(book_id) = book_id {
+            query = query.filter(schema::highlight::book_id.eq(book_id));
+        }
+        let highlights: Vec<models::Highlight> = query.load(conn)?;
+
+        // Highlights whose color is not in the palette, such as the ones synced from another
+        // device, are grouped with the unlabelled ones
+        let mut highlights_by_color: HashMap<String, Vec<models::Highlight>> = highlight_colors
+            .iter()
+            .map(|v| (v.id.clone(), vec![]))
+            .collect();
+        let mut unlabelled: Vec<models::Highlight> = vec![];
+        for highlight in highlights {
+            let group = highlight
+                .highlight_color_id
+                .as_ref()
+                .and_then(|id| highlights_by_color.get_mut(id));
+            match group {
+                Some(v) => v.push(highlight),
+                None => unlabelled.push(highlight),
+            }
+        }
+
+        let mut groups: Vec<HighlightColorWithHighlights> = highlight_colors
+            .into_iter()
+            .map(|highlight_color| HighlightColorWithHighlights {
+                highlights: highlights_by_color
+                    .remove(&highlight_color.id)
+                    .unwrap_or_default(),
+                highlight_color: Some(highlight_color),
+            })
+            .collect();
+
+        if !unlabelled.is_empty() {
+            groups.push(HighlightColorWithHighlights {
+                highlight_color: None,
+                highlights: unlabelled,
+            });
+        }
+
+        Ok(groups)
+    })
+}
+
+pub fn delete_highlight_notes(
+    conn: &mut SqliteConnection,
+    highlight_ids: Vec<String>,
+) -> Result<()> {
+    let note_ids: Vec<String> = schema::highlight_note::table
+        .filter(schema::highlight_note::highlight_id.eq_any(highlight_ids))
+        .select(schema::highlight_note::id)
+        .load(conn)?;
+
+    diesel::delete(
+        schema::highlight_note_revision::table
+            .filter(schema::highlight_note_revision::highlight_note_id.eq_any(&note_ids)),
+    )
+    .execute(conn)?;
+    diesel::update(
+        schema::highlight_note::table.filter(schema::highlight_note::id.eq_any(&note_ids)),
+    )
+    .set(schema::highlight_note::parent_id.eq(None::<String>))
+    .execute(conn)?;
+    diesel::delete(
+        schema::highlight_note::table.filter(schema::highlight_note::id.eq_any(&note_ids)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn get_highlight_notes(
+    conn: &mut SqliteConnection,
+    highlight_id: &str,
+) -> Result<Vec<models::HighlightNote>> {
+    let notes = schema::highlight_note::table
+        .filter(schema::highlight_note::highlight_id.eq(highlight_id))
+        .select(models::HighlightNote::as_select())
+        .order(schema::highlight_note::date_added)
+        .load(conn)?;
+
+    Ok(notes)
+}
+
+pub fn get_highlight_note_revisions(
+    conn: &mut SqliteConnection,
+    highlight_note_id: &str,
+) -> Result<Vec<models::HighlightNoteRevision>> {
+    let revisions = schema::highlight_note_revision::table
+        .filter(schema::highlight_note_revision::highlight_note_id.eq(highlight_note_id))
+        .select(models::HighlightNoteRevision::as_select())
+        .order(schema::highlight_note_revision::date_added.desc())
+        .load(conn)?;
+
+    Ok(revisions)
+}
+
+pub fn add_highlight_note(
+    conn: &mut SqliteConnection,
+    new_highlight_note: models::HighlightNote,
+) -> Result<()> {
+    diesel::insert_into(schema::highlight_note::table)
+        .values(&new_highlight_note)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Keeps the previous content of the note as a revision
+pub fn update_highlight_note(conn: &mut SqliteConnection, id: &str, content: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let note: models::HighlightNote = schema::highlight_note::table
+            .find(id)
+            .select(models::HighlightNote::as_select())
+            .get_result(conn)?;
+
+        if note.content == content {
+            return Ok(());
+        }
+
+        let revision = models::HighlightNoteRevision {
+            id: Uuid::new_v4().to_string(),
+            highlight_note_id: note.id,
+            content: note.content,
+            date_added: note.date_modified.unwrap_or(note.date_added),
+        };
+        diesel::insert_into(schema::highlight_note_revision::table)
+            .values(&revision)
+            .execute(conn)?;
+
+        diesel::update(schema::highlight_note::table.find(id))
+            .set((
+                schema::highlight_note::content.eq(content),
+                schema::highlight_note::date_modified.eq(current_timestamp()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+// The replies to the note are moved up to its parent
+pub fn remove_highlight_note(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let parent_id: Option<String> = schema::highlight_note::table
+            .find(id)
+            .select(schema::highlight_note::parent_id)
+            .get_result(conn)?;
+
+        diesel::update(
+            schema::highlight_note::table.filter(schema::highlight_note::parent_id.eq(id)),
+        )
+        .set(schema::highlight_note::parent_id.eq(parent_id))
+        .execute(conn)?;
+        diesel::delete(
+            schema::highlight_note_revision::table
+                .filter(schema::highlight_note_revision::highlight_note_id.eq(id)),
+        )
+        .execute(conn)?;
+        diesel::delete(schema::highlight_note::table.find(id)).execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn get_highlight_colors(conn: &mut SqliteConnection) -> Result<Vec<models::HighlightColor>> {
+    let highlight_colors = schema::highlight_color::table
+        .select(models::HighlightColor::as_select())
+        .order(schema::highlight_color::sort_order)
+        .get_results(conn)?;
+
+    Ok(highlight_colors)
+}
+
+pub fn add_highlight_color(
+    conn: &mut SqliteConnection,
+    new_highlight_color: models::HighlightColor,
+) -> Result<()> {
+    diesel::insert_into(schema::highlight_color::table)
+        .values(&new_highlight_color)
+        .on_conflict(schema::highlight_color::id)
+        .do_update()
+        .set(&new_highlight_color)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn update_highlight_color(
+    conn: &mut SqliteConnection,
+    id: &str,
+    name: &str,
+    color: &str,
+) -> Result<()> {
+    diesel::update(schema::highlight_color::table.filter(schema::highlight_color::id.eq(id)))
+        .set((
+            schema::highlight_color::name.eq(name),
+            schema::highlight_color::color.eq(color),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn reorder_highlight_colors(
+    conn: &mut SqliteConnection,
+    highlight_colors: Vec<HighlightColorIdWithSortOrder>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        for highlight_color in highlight_colors {
+            diesel::update(
+                schema::highlight_color::table
+                    .filter(schema::highlight_color::id.eq(highlight_color.id)),
+            )
+            .set(schema::highlight_color::sort_order.eq(highlight_color.sort_order))
+            .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+// The highlights of the color are kept without a color
+pub fn remove_highlight_color(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        diesel::update(
+            schema::highlight::table.filter(schema::highlight::highlight_color_id.eq(id)),
+        )
+        .set(schema::highlight::highlight_color_id.eq(None::<String>))
+        .execute(conn)?;
+
+        diesel::delete(schema::highlight_color::table.filter(schema::highlight_color::id.eq(id)))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
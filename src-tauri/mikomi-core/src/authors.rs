@@ -0,0 +1,46 @@
+use crate::{models, schema, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use uuid::Uuid;
+
+// Returns the id of the author with the given name, adding the author if there is none yet
+pub fn upsert_author(conn: &mut SqliteConnection, name: &str) -> Result<String> {
+    let ids: Vec<String> = schema::author::table
+        .filter(schema::author::name.eq(name))
+        .select(schema::author::id)
+        .load::<String>(conn)?;
+
+    if let Some(id) = ids.into_iter().next() {
+        return Ok(id);
+    }
+
+    let new_author = models::Author {
+        name: name.to_string(),
+        id: Uuid::new_v4().to_string(),
+    };
+
+    diesel::insert_into(schema::author::table)
+        .values(&new_author)
+        .execute(conn)?;
+
+    Ok(new_author.id)
+}
+
+pub fn insert_book_author_link(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    author_id: &str,
+    primary: bool,
+) -> Result<()> {
+    let new_book_author_link = models::BookAuthorLink {
+        book_id: book_id.to_string(),
+        author_id: author_id.to_string(),
+        primary_creator: primary,
+    };
+
+    diesel::insert_into(schema::book_author_link::table)
+        .values(&new_book_author_link)
+        .execute(conn)?;
+
+    Ok(())
+}
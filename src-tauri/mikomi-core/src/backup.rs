@@ -0,0 +1,265 @@
+use crate::{current_timestamp, run_migrations, Error, Result, MIGRATIONS};
+use diesel::migration::MigrationSource;
+use diesel::sqlite::Sqlite;
+use diesel::{Connection, SqliteConnection};
+use diesel_migrations::MigrationHarness;
+use libsqlite3_sys as ffi;
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// The name of the database inside a backup archive and a staging directory
+pub const DATABASE_FILE_NAME: &str = "db.sqlite";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const BACKUP_FORMAT_VERSION: i32 = 1;
+// Directories inside the data directory that are stored in a backup next to the database
+pub const BACKUP_DIRS: [&str; 3] = ["books", "covers", "fonts"];
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: i32,
+    pub app_version: String,
+    pub schema_version: String,
+    pub date_added: i32,
+}
+
+fn rejected(message: &str) -> Error {
+    Error::Rejected(String::from(message))
+}
+
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::Other, e))
+}
+
+struct RawConnection(*mut ffi::sqlite3);
+
+impl RawConnection {
+    fn open(path: &Path, flags: i32) -> Result<RawConnection> {
+        let path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| rejected("Invalid database path"))?;
+        let mut handle: *mut ffi::sqlite3 = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut handle, flags, ptr::null()) };
+        let connection = RawConnection(handle);
+        if rc != ffi::SQLITE_OK {
+            return Err(rejected("Cannot open database"));
+        }
+
+        Ok(connection)
+    }
+}
+
+impl Drop for RawConnection {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sqlite3_close(self.0);
+        }
+    }
+}
+
+// Copies the live database with SQLite's online backup API, which produces a consistent
+// snapshot even while the database is being written to in WAL mode
+pub fn backup_database(source: &Path, destination: &Path) -> Result<()> {
+    let source = RawConnection::open(source, ffi::SQLITE_OPEN_READONLY)?;
+    let destination = RawConnection::open(
+        destination,
+        ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+    )?;
+    let main = CString::new("main").unwrap();
+
+    unsafe {
+        let backup =
+            ffi::sqlite3_backup_init(destination.0, main.as_ptr(), source.0, main.as_ptr());
+        if backup.is_null() {
+            return Err(rejected("Cannot start database backup"));
+        }
+
+        let rc = loop {
+            match ffi::sqlite3_backup_step(backup, 100) {
+                ffi::SQLITE_OK | ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                rc => break rc,
+            }
+        };
+        ffi::sqlite3_backup_finish(backup);
+
+        if rc != ffi::SQLITE_DONE {
+            return Err(rejected("Cannot back up database"));
+        }
+    }
+
+    Ok(())
+}
+
+fn latest_applied_migration(conn: &mut SqliteConnection) -> Result<String> {
+    let versions = conn
+        .applied_migrations()
+        .map_err(|_| rejected("Cannot read schema version"))?;
+
+    versions
+        .into_iter()
+        .map(|v| v.to_string())
+        .max()
+        .ok_or(rejected("Database has no schema"))
+}
+
+fn add_file_to_archive<W: Write + io::Seek>(
+    archive: &mut ZipWriter<W>,
+    name: &str,
+    path: &Path,
+) -> Result<()> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    archive.start_file(name, options).map_err(zip_error)?;
+    let mut file = File::open(path)?;
+    io::copy(&mut file, archive)?;
+
+    Ok(())
+}
+
+// Writes the database at `database_path` and the data directories of `data_dir` to the archive
+// at `path`, recording `app_version` as the version that wrote it
+pub fn write_backup_archive(
+    database_path: &Path,
+    data_dir: &Path,
+    path: &Path,
+    app_version: &str,
+) -> Result<BackupManifest> {
+    let database_copy = PathBuf::from(format!("{}.backup", database_path.to_string_lossy()));
+    backup_database(database_path, &database_copy)?;
+
+    // The archive is written next to its destination first so that a failed backup never
+    // leaves a truncated archive behind
+    let partial_path = PathBuf::from(format!("{}.partial", path.to_string_lossy()));
+
+    let res = (|| {
+        let mut conn = SqliteConnection::establish(&database_copy.to_string_lossy())
+            .map_err(|_| rejected("Cannot open database backup"))?;
+        let manifest = BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION,
+            app_version: String::from(app_version),
+            schema_version: latest_applied_migration(&mut conn)?,
+            date_added: current_timestamp(),
+        };
+        drop(conn);
+
+        let file = File::create(&partial_path)?;
+        let mut archive = ZipWriter::new(file);
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        archive
+            .start_file(MANIFEST_FILE_NAME, options)
+            .map_err(zip_error)?;
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(|_| rejected("Cannot write backup manifest"))?;
+        archive.write_all(json.as_bytes())?;
+
+        add_file_to_archive(&mut archive, DATABASE_FILE_NAME, &database_copy)?;
+
+        for dir in BACKUP_DIRS {
+            let entries = match fs::read_dir(data_dir.join(dir)) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let entry = entry?;
+                if !entry.path().is_file() {
+                    continue;
+                }
+                let name = format!("{dir}/{}", entry.file_name().to_string_lossy());
+                add_file_to_archive(&mut archive, &name, &entry.path())?;
+            }
+        }
+
+        archive.finish().map_err(zip_error)?;
+        fs::rename(&partial_path, path)?;
+
+        Ok(manifest)
+    })();
+
+    let _ = fs::remove_file(&database_copy);
+    if res.is_err() {
+        let _ = fs::remove_file(&partial_path);
+    }
+
+    res
+}
+
+// Unpacks the database and data directories of the archive at `path` into `staging_dir`
+pub fn extract_backup_archive(path: &Path, staging_dir: &Path) -> Result<BackupManifest> {
+    let file = File::open(path).map_err(|_| rejected("Cannot open backup"))?;
+    let mut archive = ZipArchive::new(file).map_err(|_| rejected("Invalid backup archive"))?;
+
+    let manifest: BackupManifest = match archive.by_name(MANIFEST_FILE_NAME) {
+        Ok(v) => serde_json::from_reader(v).map_err(|_| rejected("Invalid backup manifest"))?,
+        Err(_) => return Err(rejected("Backup has no manifest")),
+    };
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(rejected("Backup was created by a newer version of the app"));
+    }
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(zip_error)?;
+        let name = match entry.enclosed_name() {
+            Some(v) => v.to_path_buf(),
+            None => return Err(rejected("Invalid file in backup archive")),
+        };
+
+        let is_database = name == Path::new(DATABASE_FILE_NAME);
+        let is_data_file = BACKUP_DIRS
+            .iter()
+            .any(|dir| name.parent() == Some(Path::new(dir)));
+        if !is_database && !is_data_file {
+            continue;
+        }
+
+        let destination = staging_dir.join(&name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&destination)?;
+        io::copy(&mut entry, &mut file)?;
+    }
+
+    if !staging_dir.join(DATABASE_FILE_NAME).exists() {
+        return Err(rejected("Backup has no database"));
+    }
+    // Directories missing from the archive were empty, so they replace the current ones as well
+    for dir in BACKUP_DIRS {
+        fs::create_dir_all(staging_dir.join(dir))?;
+    }
+
+    Ok(manifest)
+}
+
+// Brings the restored database up to the current schema, refusing databases that were
+// migrated by a newer version of the app
+pub fn migrate_restored_database(database_path: &Path) -> Result<()> {
+    let mut conn = SqliteConnection::establish(&database_path.to_string_lossy())
+        .map_err(|_| rejected("Cannot open restored database"))?;
+
+    let known_versions: Vec<String> = MigrationSource::<Sqlite>::migrations(&MIGRATIONS)
+        .map_err(|_| rejected("Cannot read migrations"))?
+        .iter()
+        .map(|m| m.name().version().to_string())
+        .collect();
+    let applied_versions = conn
+        .applied_migrations()
+        .map_err(|_| rejected("Invalid backup database"))?;
+    if applied_versions
+        .iter()
+        .any(|v| !known_versions.contains(&v.to_string()))
+    {
+        return Err(rejected("Backup was created by a newer version of the app"));
+    }
+
+    run_migrations(&mut conn).map_err(|_| rejected("Cannot migrate restored database"))?;
+
+    Ok(())
+}
@@ -0,0 +1,551 @@
+use crate::annotations::delete_highlight_notes;
+use crate::authors::{insert_book_author_link, upsert_author};
+use crate::journal::{self, JournalChange};
+use crate::settings::{get_app_setting, set_app_setting};
+use crate::{current_timestamp, models, schema, Error, Result, SECONDS_PER_DAY};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use uuid::Uuid;
+
+pub const TRASH_RETENTION_DAYS_SETTING_KEY: &str = "trash_retention_days";
+const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+pub const MAX_TRASH_RETENTION_DAYS: i32 = 100 * 365;
+
+struct BookWithAuthors {
+    book: models::Book,
+    authors: Vec<models::Author>,
+}
+
+#[derive(Serialize, Type)]
+pub struct BookWithAuthorsAndCoverAndSettingsAndCollections {
+    #[serde(flatten)]
+    pub book: models::Book,
+    pub authors: Vec<models::Author>,
+    pub cover: Option<String>,
+    pub settings: Option<models::BookSettings>,
+    pub collections: Vec<models::Collection>,
+}
+
+#[derive(Serialize, Type)]
+pub struct BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections {
+    #[serde(flatten)]
+    pub book: models::Book,
+    pub authors: Vec<models::Author>,
+    pub bookmarks: Vec<models::Bookmark>,
+    pub highlights: Vec<models::Highlight>,
+    pub collections: Vec<models::Collection>,
+    pub cover: Option<String>,
+    pub settings: Option<models::BookSettings>,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+pub struct BookWithCover {
+    #[serde(flatten)]
+    pub book: models::Book,
+    pub cover: Option<String>,
+}
+
+#[derive(Serialize, Type)]
+pub struct BulkOperationResult {
+    pub book_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+fn cover_path(covers_dir: &Path, book_id: &str) -> Option<String> {
+    Some(String::from(covers_dir.join(book_id).to_string_lossy()))
+}
+
+pub fn get_book(
+    conn: &mut SqliteConnection,
+    id: &str,
+    covers_dir: &Path,
+) -> Result<Option<BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections>> {
+    let books: Vec<models::Book> = schema::book::table
+        .filter(schema::book::id.eq(id))
+        .select(models::Book::as_select())
+        .get_results(conn)?;
+
+    let bookmarks: Vec<models::Bookmark> = models::Bookmark::belonging_to(&books)
+        .select(models::Bookmark::as_select())
+        .load(conn)?;
+
+    let highlights: Vec<models::Highlight> = models::Highlight::belonging_to(&books)
+        .select(models::Highlight::as_select())
+        .load(conn)?;
+
+    let settings: Vec<models::BookSettings> = models::BookSettings::belonging_to(&books)
+        .select(models::BookSettings::as_select())
+        .load(conn)?;
+
+    let settings = settings.into_iter().next();
+
+    let authors_with_book_link: Vec<(models::BookAuthorLink, models::Author)> =
+        models::BookAuthorLink::belonging_to(&books)
+            .inner_join(schema::author::table)
+            .select((
+                models::BookAuthorLink::as_select(),
+                models::Author::as_select(),
+            ))
+            .load::<(models::BookAuthorLink, models::Author)>(conn)?;
+
+    let collections_with_book_link: Vec<(models::BookCollectionLink, models::Collection)> =
+        models::BookCollectionLink::belonging_to(&books)
+            .inner_join(schema::collection::table)
+            .select((
+                models::BookCollectionLink::as_select(),
+                models::Collection::as_select(),
+            ))
+            .load::<(models::BookCollectionLink, models::Collection)>(conn)?;
+
+    let book = match books.into_iter().next() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let cover = cover_path(covers_dir, &book.id);
+
+    Ok(Some(
+        BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections {
+            book,
+            authors: authors_with_book_link.into_iter().map(|(_, a)| a).collect(),
+            bookmarks,
+            highlights,
+            cover,
+            settings,
+            collections: collections_with_book_link
+                .into_iter()
+                .map(|(_, c)| c)
+                .collect(),
+        },
+    ))
+}
+
+// Returns every book that is not in the trash
+pub fn get_books(
+    conn: &mut SqliteConnection,
+    covers_dir: &Path,
+) -> Result<Vec<BookWithAuthorsAndCoverAndSettingsAndCollections>> {
+    let all_books = schema::book::table
+        .filter(schema::book::deleted_at.is_null())
+        .select(models::Book::as_select())
+        .load(conn)?;
+
+    let mut settings: Vec<models::BookSettings> = models::BookSettings::belonging_to(&all_books)
+        .select(models::BookSettings::as_select())
+        .load(conn)?;
+
+    let mut collections_with_book_link: Vec<(models::BookCollectionLink, models::Collection)> =
+        models::BookCollectionLink::belonging_to(&all_books)
+            .inner_join(schema::collection::table)
+            .select((
+                models::BookCollectionLink::as_select(),
+                models::Collection::as_select(),
+            ))
+            .load::<(models::BookCollectionLink, models::Collection)>(conn)?;
+
+    let authors_with_book_link: Vec<(models::BookAuthorLink, models::Author)> =
+        models::BookAuthorLink::belonging_to(&all_books)
+            .inner_join(schema::author::table)
+            .select((
+                models::BookAuthorLink::as_select(),
+                models::Author::as_select(),
+            ))
+            .load::<(models::BookAuthorLink, models::Author)>(conn)?;
+
+    let books_with_authors: Vec<BookWithAuthors> = authors_with_book_link
+        .grouped_by(&all_books)
+        .into_iter()
+        .zip(all_books)
+        .map(|(book_author_link_with_author, book)| BookWithAuthors {
+            book,
+            authors: book_author_link_with_author
+                .into_iter()
+                .map(|(_, author)| author)
+                .collect(),
+        })
+        .collect();
+
+    let books_with_authors_and_cover: Vec<BookWithAuthorsAndCoverAndSettingsAndCollections> =
+        books_with_authors
+            .into_iter()
+            .map(|book| {
+                let cover = cover_path(covers_dir, &book.book.id);
+
+                let mut book_settings: Option<models::BookSettings> = None;
+                for setting in &mut settings {
+                    if setting.book_id == book.book.id {
+                        book_settings = Some(setting.clone());
+                        break;
+                    }
+                }
+
+                let mut book_collections: Vec<models::Collection> = vec![];
+                for book_collection_link_with_collection in &mut collections_with_book_link {
+                    if book_collection_link_with_collection.0.book_id == book.book.id {
+                        book_collections.push(book_collection_link_with_collection.1.clone());
+                    }
+                }
+
+                BookWithAuthorsAndCoverAndSettingsAndCollections {
+                    book: book.book,
+                    authors: book.authors,
+                    cover,
+                    settings: book_settings,
+                    collections: book_collections,
+                }
+            })
+            .collect();
+
+    Ok(books_with_authors_and_cover)
+}
+
+pub fn get_book_path(conn: &mut SqliteConnection, id: &str) -> Result<String> {
+    let path = schema::book::table
+        .find(id)
+        .select(schema::book::path)
+        .get_result(conn)?;
+
+    Ok(path)
+}
+
+pub fn get_languages(conn: &mut SqliteConnection) -> Result<Vec<models::Language>> {
+    let languages = schema::language::table
+        .select(models::Language::as_select())
+        .get_results(conn)?;
+
+    Ok(languages)
+}
+
+fn add_language(conn: &mut SqliteConnection, language: &str) -> Result<()> {
+    diesel::insert_into(schema::language::table)
+        .values(schema::language::name.eq(language))
+        .on_conflict(schema::language::name)
+        .do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Adds the book with its language and authors. The first author is the primary creator.
+pub fn add_book(
+    conn: &mut SqliteConnection,
+    new_book: &models::Book,
+    author_names: &[String],
+) -> Result<()> {
+    conn.transaction(|conn| {
+        if let Some(language) = &new_book.language {
+            add_language(conn, language)?;
+        }
+
+        diesel::insert_into(schema::book::table)
+            .values(new_book)
+            .execute(conn)?;
+
+        for (i, name) in author_names.iter().enumerate() {
+            let author_id = upsert_author(conn, name)?;
+            insert_book_author_link(conn, &new_book.id, &author_id, i == 0)?;
+        }
+
+        Ok(())
+    })
+}
+
+// Records a reading status event whenever the status actually changes
+pub fn set_reading_status(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    reading_status: models::ReadingStatus,
+) -> Result<()> {
+    let old_reading_status: models::ReadingStatus = schema::book::table
+        .find(book_id)
+        .select(schema::book::reading_status)
+        .get_result(conn)?;
+
+    if old_reading_status == reading_status {
+        return Ok(());
+    }
+
+    diesel::update(schema::book::table.find(book_id))
+        .set(schema::book::reading_status.eq(reading_status))
+        .execute(conn)?;
+
+    diesel::insert_into(schema::reading_status_event::table)
+        .values(models::ReadingStatusEvent {
+            id: Uuid::new_v4().to_string(),
+            book_id: book_id.to_string(),
+            reading_status,
+            previous_reading_status: Some(old_reading_status),
+            date_added: current_timestamp(),
+        })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn update_book(conn: &mut SqliteConnection, book: models::Book) -> Result<()> {
+    conn.transaction(|conn| {
+        set_reading_status(conn, &book.id, book.reading_status)?;
+        diesel::update(schema::book::table.filter(schema::book::id.eq(book.id.clone())))
+            .set(&book)
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn update_book_reading_status(
+    conn: &mut SqliteConnection,
+    id: &str,
+    reading_status: models::ReadingStatus,
+) -> Result<()> {
+    conn.transaction(|conn| set_reading_status(conn, id, reading_status))
+}
+
+// Deletes the book with every row that references it. The files of the book are left alone.
+pub fn delete_book(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    diesel::delete(
+        schema::book_collection_link::table.filter(schema::book_collection_link::book_id.eq(id)),
+    )
+    .execute(conn)?;
+    diesel::delete(
+        schema::book_author_link::table.filter(schema::book_author_link::book_id.eq(id)),
+    )
+    .execute(conn)?;
+    diesel::delete(schema::bookmark::table.filter(schema::bookmark::book_id.eq(id)))
+        .execute(conn)?;
+    let highlight_ids: Vec<String> = schema::highlight::table
+        .filter(schema::highlight::book_id.eq(id))
+        .select(schema::highlight::id)
+        .load(conn)?;
+    delete_highlight_notes(conn, highlight_ids)?;
+    diesel::delete(schema::reading_session::table.filter(schema::reading_session::book_id.eq(id)))
+        .execute(conn)?;
+    diesel::delete(
+        schema::reading_status_event::table.filter(schema::reading_status_event::book_id.eq(id)),
+    )
+    .execute(conn)?;
+    diesel::delete(schema::highlight::table.filter(schema::highlight::book_id.eq(id)))
+        .execute(conn)?;
+    diesel::delete(schema::book_settings::table.filter(schema::book_settings::book_id.eq(id)))
+        .execute(conn)?;
+    diesel::delete(
+        schema::disabled_stylesheet::table.filter(schema::disabled_stylesheet::book_id.eq(id)),
+    )
+    .execute(conn)?;
+    diesel::delete(schema::sync_record::table.filter(schema::sync_record::book_id.eq(id)))
+        .execute(conn)?;
+    diesel::delete(schema::book::table.filter(schema::book::id.eq(id))).execute(conn)?;
+
+    Ok(())
+}
+
+fn trash_book(conn: &mut SqliteConnection, id: &str) -> Result<Option<JournalChange>> {
+    let deleted_at = current_timestamp();
+    let updated = diesel::update(
+        schema::book::table
+            .filter(schema::book::id.eq(id))
+            .filter(schema::book::deleted_at.is_null()),
+    )
+    .set(schema::book::deleted_at.eq(deleted_at))
+    .execute(conn)?;
+
+    if updated == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(JournalChange::BookDeletedAt {
+        book_id: id.to_string(),
+        before: None,
+        after: Some(deleted_at),
+    }))
+}
+
+// Moves the book to the trash
+pub fn remove_book(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let changes = trash_book(conn, id)?.into_iter().collect();
+        journal::record_journal_entry(conn, "Remove book", changes)
+    })
+}
+
+pub fn bulk_remove_books(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+) -> Result<Vec<BulkOperationResult>> {
+    // The journal entry is part of the same transaction, so the books are never trashed without
+    // a way to undo it
+    conn.transaction(|conn| {
+        let mut changes: Vec<JournalChange> = vec![];
+        let results = run_bulk_operation(conn, book_ids, "Cannot delete book", |conn, id| {
+            changes.extend(trash_book(conn, id)?);
+            Ok(())
+        })?;
+
+        journal::record_journal_entry(conn, "Remove books", changes)?;
+
+        Ok(results)
+    })
+}
+
+pub fn get_trashed_books(
+    conn: &mut SqliteConnection,
+    covers_dir: &Path,
+) -> Result<Vec<BookWithCover>> {
+    let books: Vec<models::Book> = schema::book::table
+        .filter(schema::book::deleted_at.is_not_null())
+        .select(models::Book::as_select())
+        .order(schema::book::deleted_at.desc())
+        .load(conn)?;
+
+    Ok(books
+        .into_iter()
+        .map(|b| {
+            let cover = cover_path(covers_dir, &b.id);
+            BookWithCover { book: b, cover }
+        })
+        .collect())
+}
+
+pub fn restore_books(conn: &mut SqliteConnection, book_ids: Vec<String>) -> Result<()> {
+    diesel::update(schema::book::table.filter(schema::book::id.eq_any(book_ids)))
+        .set(schema::book::deleted_at.eq(None::<i32>))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Deletes the book and returns the path of its file
+pub fn remove_book_permanently(conn: &mut SqliteConnection, id: &str) -> Result<String> {
+    conn.transaction(|conn| {
+        let path = get_book_path(conn, id)?;
+        delete_book(conn, id)?;
+
+        Ok(path)
+    })
+}
+
+// Permanently deletes the trashed books that were removed before `deleted_before`, or every
+// trashed book when it is `None`, and returns the ids and paths of the deleted books
+pub fn purge_trashed_books(
+    conn: &mut SqliteConnection,
+    deleted_before: Option<i32>,
+) -> Result<Vec<(String, String)>> {
+    conn.transaction(|conn| {
+        let mut query = schema::book::table
+            .filter(schema::book::deleted_at.is_not_null())
+            .select((schema::book::id, schema::book::path))
+            .into_boxed();
+        if let Some(deleted_before) = deleted_before {
+            query = query.filter(schema::book::deleted_at.lt(deleted_before));
+        }
+        let books: Vec<(String, String)> = query.load(conn)?;
+
+        for (book_id, _) in &books {
+            delete_book(conn, book_id)?;
+        }
+
+        Ok(books)
+    })
+}
+
+pub fn get_trash_retention_days(conn: &mut SqliteConnection) -> Result<i32> {
+    let days = get_app_setting(conn, TRASH_RETENTION_DAYS_SETTING_KEY)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+
+    Ok(days)
+}
+
+pub fn set_trash_retention_days(conn: &mut SqliteConnection, days: i32) -> Result<()> {
+    if !(0..=MAX_TRASH_RETENTION_DAYS).contains(&days) {
+        return Err(Error::Rejected(format!(
+            "Trash retention must be between 0 and {MAX_TRASH_RETENTION_DAYS} days"
+        )));
+    }
+
+    set_app_setting(
+        conn,
+        TRASH_RETENTION_DAYS_SETTING_KEY,
+        Some(&days.to_string()),
+    )
+}
+
+// Permanently deletes the books that have been in the trash for longer than the retention
+// period, returning their ids and paths
+pub fn purge_expired_trash(conn: &mut SqliteConnection) -> Result<Vec<(String, String)>> {
+    let days = get_trash_retention_days(conn)?;
+    // A stored retention can still be out of range, so the cutoff is computed in i64 and
+    // a cutoff before the earliest timestamp keeps every book
+    let cutoff = i64::from(current_timestamp()) - i64::from(days) * i64::from(SECONDS_PER_DAY);
+    let cutoff = i32::try_from(cutoff).unwrap_or(i32::MIN);
+    purge_trashed_books(conn, Some(cutoff))
+}
+
+// Runs the operation for every book inside a single transaction. Each book gets its own
+// savepoint, so a failing book is rolled back and reported without aborting the others.
+pub fn run_bulk_operation<F>(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    error_message: &str,
+    mut operation: F,
+) -> Result<Vec<BulkOperationResult>>
+where
+    F: FnMut(&mut SqliteConnection, &str) -> Result<()>,
+{
+    conn.transaction(|conn| {
+        let mut results: Vec<BulkOperationResult> = vec![];
+        for book_id in book_ids {
+            let res = conn.transaction(|conn| {
+                schema::book::table
+                    .find(&book_id)
+                    .select(schema::book::id)
+                    .get_result::<String>(conn)?;
+                operation(conn, &book_id)
+            });
+
+            let error = match res {
+                Ok(_) => None,
+                Err(Error::NotFound) => Some(String::from("Book not found")),
+                Err(_) => Some(String::from(error_message)),
+            };
+            results.push(BulkOperationResult {
+                book_id,
+                success: error.is_none(),
+                error,
+            });
+        }
+
+        Ok(results)
+    })
+}
+
+pub fn bulk_update_reading_status(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    reading_status: models::ReadingStatus,
+) -> Result<Vec<BulkOperationResult>> {
+    run_bulk_operation(conn, book_ids, "Cannot update book", |conn, book_id| {
+        set_reading_status(conn, book_id, reading_status)
+    })
+}
+
+pub fn bulk_update_book_language(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    language: Option<String>,
+) -> Result<Vec<BulkOperationResult>> {
+    run_bulk_operation(conn, book_ids, "Cannot update book", |conn, book_id| {
+        if let Some(language) = &language {
+            add_language(conn, language)?;
+        }
+        diesel::update(schema::book::table.find(book_id))
+            .set(schema::book::language.eq(&language))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,747 @@
+use crate::books::{run_bulk_operation, BookWithCover, BulkOperationResult};
+use crate::journal::{self, JournalChange};
+use crate::{current_timestamp, models, schema, Error, Result, SECONDS_PER_DAY};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// How far back the "within days" smart collection rules can look
+pub const MAX_SMART_FILTER_DAYS: i32 = 100 * 365;
+
+#[derive(Serialize, Deserialize, Type)]
+pub struct CollectionIdWithSortOrder {
+    pub id: String,
+    pub sort_order: i32,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+pub enum RemoveCollectionPolicy {
+    Cascade,
+    Reparent,
+}
+
+#[derive(Serialize, Type)]
+pub struct CollectionTreeNode {
+    collection: models::Collection,
+    book_count: i32,
+    total_book_count: i32,
+    children: Vec<CollectionTreeNode>,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+pub struct CollectionWithBooks {
+    pub collection: models::Collection,
+    pub books: Vec<BookWithCover>,
+}
+
+pub fn get_collections(conn: &mut SqliteConnection) -> Result<Vec<models::Collection>> {
+    let collections = schema::collection::table
+        .select(models::Collection::as_select())
+        .order(schema::collection::sort_order)
+        .get_results(conn)?;
+
+    Ok(collections)
+}
+
+pub fn get_collection(conn: &mut SqliteConnection, id: &str) -> Result<models::Collection> {
+    let collection = schema::collection::table
+        .find(id)
+        .select(models::Collection::as_select())
+        .get_result(conn)?;
+
+    Ok(collection)
+}
+
+fn check_smart_filter(filter: &models::SmartCollectionFilter) -> Result<()> {
+    for rule in &filter.rules {
+        if let models::SmartCollectionRule::AddedWithinDays { days }
+        | models::SmartCollectionRule::LastReadWithinDays { days } = rule
+        {
+            if !(0..=MAX_SMART_FILTER_DAYS).contains(days) {
+                return Err(Error::Rejected(format!(
+                    "Number of days must be between 0 and {MAX_SMART_FILTER_DAYS}"
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Smart collections get their books from their filter, so books cannot be added to them
+fn check_not_smart_collections(
+    conn: &mut SqliteConnection,
+    collection_ids: &[String],
+) -> Result<()> {
+    let smart_collections: i64 = schema::collection::table
+        .filter(schema::collection::id.eq_any(collection_ids))
+        .filter(schema::collection::smart_filter.is_not_null())
+        .count()
+        .get_result(conn)?;
+    if smart_collections > 0 {
+        return Err(Error::Rejected(String::from(
+            "Cannot add books to a smart collection",
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn add_collection(
+    conn: &mut SqliteConnection,
+    new_collection: models::Collection,
+) -> Result<()> {
+    if let Some(filter) = &new_collection.smart_filter {
+        check_smart_filter(filter)?;
+    }
+
+    conn.transaction(|conn| {
+        if let Some(parent_id) = &new_collection.parent_id {
+            let all_collections: Vec<models::Collection> = schema::collection::table
+                .select(models::Collection::as_select())
+                .load(conn)?;
+            check_parent_collection(&all_collections, &new_collection.id, parent_id)?;
+        }
+
+        diesel::insert_into(schema::collection::table)
+            .values(&new_collection)
+            .on_conflict(schema::collection::id)
+            .do_update()
+            .set(&new_collection)
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn reorder_collections(
+    conn: &mut SqliteConnection,
+    collections: Vec<CollectionIdWithSortOrder>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        for col in collections {
+            diesel::update(schema::collection::table.filter(schema::collection::id.eq(col.id)))
+                .set(schema::collection::sort_order.eq(col.sort_order))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+pub fn update_collection_name(conn: &mut SqliteConnection, id: &str, name: &str) -> Result<()> {
+    diesel::update(schema::collection::table.filter(schema::collection::id.eq(id)))
+        .set(schema::collection::name.eq(name))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+fn get_descendant_collection_ids(collections: &[models::Collection], id: &str) -> Vec<String> {
+    let mut descendant_ids: Vec<String> = vec![];
+    let mut queue: Vec<String> = vec![String::from(id)];
+    while let Some(parent_id) = queue.pop() {
+        for c in collections {
+            if c.parent_id.as_deref() == Some(parent_id.as_str()) && !descendant_ids.contains(&c.id)
+            {
+                descendant_ids.push(c.id.clone());
+                queue.push(c.id.clone());
+            }
+        }
+    }
+
+    descendant_ids
+}
+
+pub fn remove_collection(
+    conn: &mut SqliteConnection,
+    id: &str,
+    policy: Option<RemoveCollectionPolicy>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        let all_collections: Vec<models::Collection> = schema::collection::table
+            .select(models::Collection::as_select())
+            .load(conn)?;
+
+        let parent_id: Option<String> = all_collections
+            .iter()
+            .find(|c| c.id == id)
+            .and_then(|c| c.parent_id.clone());
+
+        let mut changes: Vec<JournalChange> = vec![];
+
+        let mut ids_to_remove: Vec<String> = vec![id.to_string()];
+        match policy.unwrap_or(RemoveCollectionPolicy::Reparent) {
+            RemoveCollectionPolicy::Cascade => {
+                ids_to_remove.extend(get_descendant_collection_ids(&all_collections, id));
+            }
+            RemoveCollectionPolicy::Reparent => {
+                for child in all_collections
+                    .iter()
+                    .filter(|c| c.parent_id.as_deref() == Some(id))
+                {
+                    changes.push(JournalChange::Collection {
+                        id: child.id.clone(),
+                        before: Some(child.clone()),
+                        after: Some(models::Collection {
+                            parent_id: parent_id.clone(),
+                            ..child.clone()
+                        }),
+                    });
+                }
+
+                diesel::update(
+                    schema::collection::table.filter(schema::collection::parent_id.eq(id)),
+                )
+                .set(schema::collection::parent_id.eq(&parent_id))
+                .execute(conn)?;
+            }
+        }
+
+        // Children are removed before their parents so that no row references a deleted collection
+        for id in ids_to_remove.iter().rev() {
+            let links: Vec<models::BookCollectionLink> = schema::book_collection_link::table
+                .filter(schema::book_collection_link::collection_id.eq(id))
+                .select(models::BookCollectionLink::as_select())
+                .load(conn)?;
+            for link in links {
+                changes.push(JournalChange::BookCollectionLink {
+                    book_id: link.book_id.clone(),
+                    collection_id: link.collection_id.clone(),
+                    before: Some(link),
+                    after: None,
+                });
+            }
+
+            let defaults: Vec<models::ReaderSettingsDefault> =
+                schema::reader_settings_default::table
+                    .filter(schema::reader_settings_default::collection_id.eq(id))
+                    .select(models::ReaderSettingsDefault::as_select())
+                    .load(conn)?;
+            for default in defaults {
+                changes.push(JournalChange::ReaderSettingsDefault {
+                    id: default.id.clone(),
+                    before: Some(Box::new(default)),
+                    after: None,
+                });
+            }
+
+            if let Some(collection) = all_collections.iter().find(|c| c.id == *id) {
+                changes.push(JournalChange::Collection {
+                    id: id.clone(),
+                    before: Some(collection.clone()),
+                    after: None,
+                });
+            }
+
+            diesel::delete(
+                schema::book_collection_link::table
+                    .filter(schema::book_collection_link::collection_id.eq(id)),
+            )
+            .execute(conn)?;
+
+            diesel::delete(
+                schema::reader_settings_default::table
+                    .filter(schema::reader_settings_default::collection_id.eq(id)),
+            )
+            .execute(conn)?;
+
+            diesel::delete(schema::collection::table.filter(schema::collection::id.eq(id)))
+                .execute(conn)?;
+        }
+
+        journal::record_journal_entry(conn, "Remove collection", changes)
+    })
+}
+
+// A collection can only be nested under a collection that exists and is not the collection
+// itself or one of its descendants, which would cut the branch off from the tree
+fn check_parent_collection(
+    collections: &[models::Collection],
+    id: &str,
+    parent_id: &str,
+) -> Result<()> {
+    if !collections.iter().any(|c| c.id == parent_id) {
+        return Err(Error::Rejected(String::from(
+            "Parent collection does not exist",
+        )));
+    }
+    if parent_id == id
+        || get_descendant_collection_ids(collections, id)
+            .iter()
+            .any(|d| d == parent_id)
+    {
+        return Err(Error::Rejected(String::from(
+            "Cannot move collection into itself",
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn move_collection(
+    conn: &mut SqliteConnection,
+    id: &str,
+    parent_id: Option<&str>,
+    sort_order: Option<i32>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        let all_collections: Vec<models::Collection> = schema::collection::table
+            .select(models::Collection::as_select())
+            .load(conn)?;
+
+        if !all_collections.iter().any(|c| c.id == id) {
+            return Err(Error::NotFound);
+        }
+        if let Some(parent_id) = parent_id {
+            check_parent_collection(&all_collections, id, parent_id)?;
+        }
+
+        diesel::update(schema::collection::table.filter(schema::collection::id.eq(id)))
+            .set((
+                schema::collection::parent_id.eq(parent_id),
+                schema::collection::sort_order.eq(sort_order),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+fn build_collection_tree(
+    parent_id: Option<&str>,
+    collections: &[models::Collection],
+    book_ids_per_collection: &HashMap<String, Vec<String>>,
+) -> Vec<CollectionTreeNode> {
+    collections
+        .iter()
+        .filter(|c| c.parent_id.as_deref() == parent_id)
+        .map(|c| {
+            let book_ids = &book_ids_per_collection[&c.id];
+
+            let mut total_book_ids: HashSet<&String> = book_ids.iter().collect();
+            for descendant_id in get_descendant_collection_ids(collections, &c.id) {
+                total_book_ids.extend(book_ids_per_collection[&descendant_id].iter());
+            }
+
+            CollectionTreeNode {
+                collection: c.clone(),
+                book_count: book_ids.len() as i32,
+                total_book_count: total_book_ids.len() as i32,
+                children: build_collection_tree(Some(&c.id), collections, book_ids_per_collection),
+            }
+        })
+        .collect()
+}
+
+pub fn get_collection_tree(conn: &mut SqliteConnection) -> Result<Vec<CollectionTreeNode>> {
+    conn.transaction(|conn| {
+        let all_collections: Vec<models::Collection> = schema::collection::table
+            .select(models::Collection::as_select())
+            .order(schema::collection::sort_order)
+            .load(conn)?;
+
+        let mut book_ids_per_collection: HashMap<String, Vec<String>> = HashMap::new();
+        for c in &all_collections {
+            let book_ids = get_collection_books(conn, c)?
+                .into_iter()
+                .map(|b| b.id)
+                .collect();
+            book_ids_per_collection.insert(c.id.clone(), book_ids);
+        }
+
+        Ok(build_collection_tree(
+            None,
+            &all_collections,
+            &book_ids_per_collection,
+        ))
+    })
+}
+
+pub fn reorder_books_in_collection(
+    conn: &mut SqliteConnection,
+    book_collection_links: Vec<models::BookCollectionLink>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        for book_collection_link in book_collection_links {
+            diesel::update(
+                schema::book_collection_link::table
+                    .filter(schema::book_collection_link::book_id.eq(book_collection_link.book_id))
+                    .filter(
+                        schema::book_collection_link::collection_id
+                            .eq(book_collection_link.collection_id),
+                    ),
+            )
+            .set(schema::book_collection_link::sort_order.eq(book_collection_link.sort_order))
+            .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+// Appends the book to the end of the collection
+pub fn insert_book_collection_link(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    collection_id: &str,
+) -> Result<models::BookCollectionLink> {
+    let links: Vec<models::BookCollectionLink> = schema::book_collection_link::table
+        .filter(schema::book_collection_link::collection_id.eq(collection_id))
+        .select(models::BookCollectionLink::as_select())
+        .order(schema::book_collection_link::sort_order.desc())
+        .load(conn)?;
+    let first_link = links.first();
+
+    let count = match first_link {
+        Some(v) => v.sort_order,
+        None => Some(0),
+    };
+    let count = count.unwrap_or_default() + 1;
+
+    let link = models::BookCollectionLink {
+        book_id: book_id.to_string(),
+        collection_id: collection_id.to_string(),
+        sort_order: Some(count),
+    };
+    diesel::insert_into(schema::book_collection_link::table)
+        .values(&link)
+        .execute(conn)?;
+
+    Ok(link)
+}
+
+// Makes the book belong to exactly the given collections, keeping the position of the
+// links that already exist
+pub fn add_book_to_collections(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    collection_ids: Vec<String>,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        let book_collection_links: Vec<models::BookCollectionLink> =
+            schema::book_collection_link::table
+                .filter(schema::book_collection_link::book_id.eq(book_id))
+                .select(models::BookCollectionLink::as_select())
+                .load(conn)?;
+
+        let old_collection_ids: Vec<String> = book_collection_links
+            .iter()
+            .map(|v| v.collection_id.clone())
+            .collect();
+
+        let to_remove: Vec<models::BookCollectionLink> = book_collection_links
+            .into_iter()
+            .filter(|v| !collection_ids.contains(&v.collection_id))
+            .collect();
+
+        let to_add: Vec<String> = collection_ids
+            .into_iter()
+            .filter(|v| !old_collection_ids.contains(v))
+            .collect();
+        check_not_smart_collections(conn, &to_add)?;
+
+        let mut changes: Vec<JournalChange> = vec![];
+
+        for link in to_remove {
+            diesel::delete(
+                schema::book_collection_link::table
+                    .filter(schema::book_collection_link::book_id.eq(book_id))
+                    .filter(schema::book_collection_link::collection_id.eq(&link.collection_id)),
+            )
+            .execute(conn)?;
+
+            changes.push(JournalChange::BookCollectionLink {
+                book_id: link.book_id.clone(),
+                collection_id: link.collection_id.clone(),
+                before: Some(link),
+                after: None,
+            });
+        }
+
+        for collection_id in to_add {
+            let link = insert_book_collection_link(conn, book_id, &collection_id)?;
+
+            changes.push(JournalChange::BookCollectionLink {
+                book_id: link.book_id.clone(),
+                collection_id: link.collection_id.clone(),
+                before: None,
+                after: Some(link),
+            });
+        }
+
+        journal::record_journal_entry(conn, "Change book collections", changes)
+    })
+}
+
+pub fn remove_book_from_collection(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    collection_id: &str,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        let link: Option<models::BookCollectionLink> = schema::book_collection_link::table
+            .find((book_id, collection_id))
+            .select(models::BookCollectionLink::as_select())
+            .get_result(conn)
+            .optional()?;
+
+        diesel::delete(
+            schema::book_collection_link::table.filter(
+                schema::book_collection_link::collection_id
+                    .eq(collection_id)
+                    .and(schema::book_collection_link::book_id.eq(book_id)),
+            ),
+        )
+        .execute(conn)?;
+
+        if let Some(link) = link {
+            journal::record_journal_entry(
+                conn,
+                "Remove book from collection",
+                vec![JournalChange::BookCollectionLink {
+                    book_id: book_id.to_string(),
+                    collection_id: collection_id.to_string(),
+                    before: Some(link),
+                    after: None,
+                }],
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+pub fn get_smart_collection_books(
+    conn: &mut SqliteConnection,
+    filter: &models::SmartCollectionFilter,
+) -> Result<Vec<models::Book>> {
+    let books: Vec<models::Book> = schema::book::table
+        .filter(schema::book::deleted_at.is_null())
+        .select(models::Book::as_select())
+        .order(schema::book::title)
+        .load(conn)?;
+
+    let book_author_links: Vec<models::BookAuthorLink> = schema::book_author_link::table
+        .select(models::BookAuthorLink::as_select())
+        .load(conn)?;
+
+    let now = i64::from(current_timestamp());
+    let is_within_days = |timestamp: i32, days: i32| {
+        i64::from(timestamp) >= now - i64::from(days) * i64::from(SECONDS_PER_DAY)
+    };
+
+    let books = books
+        .into_iter()
+        .filter(|book| {
+            let mut results = filter.rules.iter().map(|rule| match rule {
+                models::SmartCollectionRule::Language { languages } => book
+                    .language
+                    .as_ref()
+                    .is_some_and(|l| languages.contains(l)),
+                models::SmartCollectionRule::ReadingStatus { reading_statuses } => {
+                    reading_statuses.contains(&book.reading_status)
+                }
+                models::SmartCollectionRule::Author { author_ids } => book_author_links
+                    .iter()
+                    .any(|l| l.book_id == book.id && author_ids.contains(&l.author_id)),
+                models::SmartCollectionRule::Publisher { publishers } => book
+                    .publisher
+                    .as_ref()
+                    .is_some_and(|p| publishers.contains(p)),
+                models::SmartCollectionRule::TitleContains { text } => {
+                    book.title.to_lowercase().contains(&text.to_lowercase())
+                }
+                models::SmartCollectionRule::AddedWithinDays { days } => {
+                    is_within_days(book.date_added, *days)
+                }
+                models::SmartCollectionRule::LastReadWithinDays { days } => {
+                    book.last_read.is_some_and(|t| is_within_days(t, *days))
+                }
+            });
+
+            if filter.match_all {
+                results.all(|r| r)
+            } else {
+                results.any(|r| r)
+            }
+        })
+        .collect();
+
+    Ok(books)
+}
+
+pub fn get_collection_books(
+    conn: &mut SqliteConnection,
+    collection: &models::Collection,
+) -> Result<Vec<models::Book>> {
+    match &collection.smart_filter {
+        Some(filter) => get_smart_collection_books(conn, filter),
+        None => Ok(models::BookCollectionLink::belonging_to(collection)
+            .inner_join(schema::book::table)
+            .filter(schema::book::deleted_at.is_null())
+            .select(models::Book::as_select())
+            .order(schema::book_collection_link::sort_order)
+            .load(conn)?),
+    }
+}
+
+fn with_covers(books: Vec<models::Book>, covers_dir: &Path) -> Vec<BookWithCover> {
+    books
+        .into_iter()
+        .map(|b| {
+            let path = covers_dir.join(b.id.clone());
+            BookWithCover {
+                book: b,
+                cover: Some(String::from(path.to_string_lossy())),
+            }
+        })
+        .collect()
+}
+
+pub fn get_books_belonging_to_collection(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    covers_dir: &Path,
+) -> Result<CollectionWithBooks> {
+    let collection = get_collection(conn, collection_id)?;
+    let books = get_collection_books(conn, &collection)?;
+
+    Ok(CollectionWithBooks {
+        collection,
+        books: with_covers(books, covers_dir),
+    })
+}
+
+// Returns the books of the collection followed by the books of its descendants
+pub fn get_books_belonging_to_collection_tree(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    covers_dir: &Path,
+) -> Result<CollectionWithBooks> {
+    let (collection, books) = conn.transaction(|conn| {
+        let all_collections: Vec<models::Collection> = schema::collection::table
+            .select(models::Collection::as_select())
+            .order(schema::collection::sort_order)
+            .load(conn)?;
+
+        let collection = match all_collections.iter().find(|c| c.id == collection_id) {
+            Some(c) => c.clone(),
+            None => return Err(Error::NotFound),
+        };
+
+        let descendant_ids = get_descendant_collection_ids(&all_collections, collection_id);
+
+        let mut books = get_collection_books(conn, &collection)?;
+        for c in all_collections
+            .iter()
+            .filter(|c| descendant_ids.contains(&c.id))
+        {
+            for b in get_collection_books(conn, c)? {
+                if !books.iter().any(|existing| existing.id == b.id) {
+                    books.push(b);
+                }
+            }
+        }
+
+        Ok((collection, books))
+    })?;
+
+    Ok(CollectionWithBooks {
+        collection,
+        books: with_covers(books, covers_dir),
+    })
+}
+
+pub fn get_collections_and_their_books(
+    conn: &mut SqliteConnection,
+    covers_dir: &Path,
+) -> Result<Vec<CollectionWithBooks>> {
+    let all_collections = schema::collection::table
+        .order(schema::collection::sort_order)
+        .select(models::Collection::as_select())
+        .load(conn)?;
+
+    let books_with_collection_link: Vec<(models::BookCollectionLink, models::Book)> =
+        models::BookCollectionLink::belonging_to(&all_collections)
+            .inner_join(schema::book::table)
+            .filter(schema::book::deleted_at.is_null())
+            .order(schema::book_collection_link::sort_order)
+            .select((
+                models::BookCollectionLink::as_select(),
+                models::Book::as_select(),
+            ))
+            .load::<(models::BookCollectionLink, models::Book)>(conn)?;
+
+    let mut books_per_collection: Vec<CollectionWithBooks> = vec![];
+    for (links, col) in books_with_collection_link
+        .grouped_by(&all_collections)
+        .into_iter()
+        .zip(all_collections)
+    {
+        let books = match &col.smart_filter {
+            Some(filter) => get_smart_collection_books(conn, filter)?,
+            None => links.into_iter().map(|(_, b)| b).collect(),
+        };
+
+        books_per_collection.push(CollectionWithBooks {
+            collection: col,
+            books: with_covers(books, covers_dir),
+        });
+    }
+
+    Ok(books_per_collection)
+}
+
+pub fn bulk_add_books_to_collections(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    collection_ids: Vec<String>,
+) -> Result<Vec<BulkOperationResult>> {
+    check_not_smart_collections(conn, &collection_ids)?;
+
+    run_bulk_operation(
+        conn,
+        book_ids,
+        "Cannot add book to collection",
+        |conn, book_id| {
+            for collection_id in &collection_ids {
+                let existing_links: i64 = schema::book_collection_link::table
+                    .filter(schema::book_collection_link::book_id.eq(book_id))
+                    .filter(schema::book_collection_link::collection_id.eq(collection_id))
+                    .count()
+                    .get_result(conn)?;
+                if existing_links == 0 {
+                    insert_book_collection_link(conn, book_id, collection_id)?;
+                }
+            }
+
+            Ok(())
+        },
+    )
+}
+
+pub fn bulk_remove_books_from_collections(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    collection_ids: Vec<String>,
+) -> Result<Vec<BulkOperationResult>> {
+    run_bulk_operation(
+        conn,
+        book_ids,
+        "Cannot remove book from collection",
+        |conn, book_id| {
+            diesel::delete(
+                schema::book_collection_link::table
+                    .filter(schema::book_collection_link::book_id.eq(book_id))
+                    .filter(schema::book_collection_link::collection_id.eq_any(&collection_ids)),
+            )
+            .execute(conn)?;
+
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+// `Rejected` is an operation that breaks a rule of the library, and its message can be shown
+// to the user as is. The other errors only tell the caller that the operation failed.
+#[derive(Debug)]
+pub enum Error {
+    NotFound,
+    Rejected(String),
+    Database(diesel::result::Error),
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "Not found"),
+            Error::Rejected(message) => write!(f, "{message}"),
+            Error::Database(e) => write!(f, "Database error: {e}"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<diesel::result::Error> for Error {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => Error::NotFound,
+            e => Error::Database(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+// Lets the repository functions be called inside transactions that use diesel's error type
+impl From<Error> for diesel::result::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::NotFound => diesel::result::Error::NotFound,
+            Error::Rejected(message) => diesel::result::Error::QueryBuilderError(message.into()),
+            Error::Database(e) => e,
+            Error::Io(e) => diesel::result::Error::QueryBuilderError(e.into()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{models, schema, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+pub fn get_fonts(conn: &mut SqliteConnection) -> Result<Vec<models::Font>> {
+    let fonts = schema::font::table
+        .select(models::Font::as_select())
+        .order((schema::font::family, schema::font::weight))
+        .get_results(conn)?;
+
+    Ok(fonts)
+}
+
+pub fn add_font(conn: &mut SqliteConnection, new_font: &models::Font) -> Result<()> {
+    diesel::insert_into(schema::font::table)
+        .values(new_font)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Returns the removed font, so that the caller can remove its file as well
+pub fn remove_font(conn: &mut SqliteConnection, id: &str) -> Result<models::Font> {
+    conn.transaction(|conn| {
+        let font = schema::font::table
+            .find(id)
+            .select(models::Font::as_select())
+            .get_result(conn)?;
+        diesel::delete(schema::font::table.find(id)).execute(conn)?;
+
+        Ok(font)
+    })
+}
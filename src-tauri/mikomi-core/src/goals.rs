@@ -0,0 +1,198 @@
+use crate::{models, schema, Error, Result};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use diesel::SqliteConnection;
+use serde::Serialize;
+use specta::Type;
+
+pub fn get_reading_goals(conn: &mut SqliteConnection) -> Result<Vec<models::ReadingGoal>> {
+    let goals = schema::reading_goal::table
+        .select(models::ReadingGoal::as_select())
+        .order((schema::reading_goal::goal_type, schema::reading_goal::year))
+        .get_results(conn)?;
+
+    Ok(goals)
+}
+
+// There is a single goal of each kind per year, so adding one replaces the goal it overlaps
+pub fn add_reading_goal(
+    conn: &mut SqliteConnection,
+    new_reading_goal: models::ReadingGoal,
+) -> Result<()> {
+    if new_reading_goal.target <= 0 {
+        return Err(Error::Rejected(String::from(
+            "Reading goal target must be positive",
+        )));
+    }
+    let year = match new_reading_goal.goal_type {
+        models::ReadingGoalType::BooksPerYear => match new_reading_goal.year {
+            Some(v) => Some(v),
+            None => {
+                return Err(Error::Rejected(String::from(
+                    "Books per year goal requires a year",
+                )))
+            }
+        },
+        models::ReadingGoalType::MinutesPerDay => None,
+    };
+
+    conn.transaction(|conn| {
+        let mut existing = diesel::delete(schema::reading_goal::table)
+            .filter(schema::reading_goal::goal_type.eq(&new_reading_goal.goal_type))
+            .filter(schema::reading_goal::id.ne(&new_reading_goal.id))
+            .into_boxed();
+        existing = match year {
+            Some(v) => existing.filter(schema::reading_goal::year.eq(v)),
+            None => existing.filter(schema::reading_goal::year.is_null()),
+        };
+        existing.execute(conn)?;
+
+        diesel::insert_into(schema::reading_goal::table)
+            .values(&new_reading_goal)
+            .on_conflict(schema::reading_goal::id)
+            .do_update()
+            .set((
+                schema::reading_goal::goal_type.eq(&new_reading_goal.goal_type),
+                schema::reading_goal::target.eq(new_reading_goal.target),
+                schema::reading_goal::year.eq(year),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn remove_reading_goal(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    diesel::delete(schema::reading_goal::table.filter(schema::reading_goal::id.eq(id)))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct DailyReadingTime {
+    #[diesel(sql_type = Integer)]
+    day: i32,
+    #[diesel(sql_type = Integer)]
+    seconds_read: i32,
+}
+
+#[derive(QueryableByName, Serialize, Type)]
+pub struct BooksCompletedInMonth {
+    #[diesel(sql_type = Text)]
+    pub month: String,
+    #[diesel(sql_type = Integer)]
+    pub books_completed: i32,
+}
+
+#[derive(Serialize, Type)]
+pub struct ReadingGoalProgress {
+    pub year: i32,
+    pub books_per_year_goal: Option<i32>,
+    pub books_completed: i32,
+    pub books_completed_per_month: Vec<BooksCompletedInMonth>,
+    pub minutes_per_day_goal: Option<i32>,
+    pub minutes_read_today: i32,
+    pub current_streak: i32,
+    pub longest_streak: i32,
+}
+
+pub fn get_reading_goal_progress(
+    conn: &mut SqliteConnection,
+    year: i32,
+) -> Result<ReadingGoalProgress> {
+    let (goals, daily_reading_times, today, books_completed_per_month) =
+        conn.transaction(|conn| {
+            let goals: Vec<models::ReadingGoal> = schema::reading_goal::table
+                .select(models::ReadingGoal::as_select())
+                .load(conn)?;
+
+            let daily_reading_times: Vec<DailyReadingTime> = diesel::sql_query(
+                "SELECT CAST(julianday(date(start_time, 'unixepoch', 'localtime')) AS INTEGER) AS day,
+                    CAST(SUM(end_time - start_time) AS INTEGER) AS seconds_read
+                FROM reading_session
+                GROUP BY day
+                ORDER BY day",
+            )
+            .load(conn)?;
+
+            let today: DailyReadingTime = diesel::sql_query(
+                "SELECT CAST(julianday(date('now', 'localtime')) AS INTEGER) AS day,
+                    0 AS seconds_read",
+            )
+            .get_result(conn)?;
+
+            let books_completed_per_month: Vec<BooksCompletedInMonth> = diesel::sql_query(
+                "SELECT strftime('%Y-%m', date_added, 'unixepoch', 'localtime') AS month,
+                    COUNT(*) AS books_completed
+                FROM reading_status_event
+                WHERE reading_status = 'Finished'
+                    AND strftime('%Y', date_added, 'unixepoch', 'localtime') = ?
+                GROUP BY month
+                ORDER BY month",
+            )
+            .bind::<Text, _>(format!("{year:04}"))
+            .load(conn)?;
+
+            diesel::result::QueryResult::Ok((
+                goals,
+                daily_reading_times,
+                today.day,
+                books_completed_per_month,
+            ))
+        })?;
+
+    let books_per_year_goal = goals
+        .iter()
+        .find(|g| g.goal_type == models::ReadingGoalType::BooksPerYear && g.year == Some(year))
+        .map(|g| g.target);
+    let minutes_per_day_goal = goals
+        .iter()
+        .find(|g| g.goal_type == models::ReadingGoalType::MinutesPerDay)
+        .map(|g| g.target);
+
+    let minutes_read_today = daily_reading_times
+        .iter()
+        .find(|d| d.day == today)
+        .map(|d| d.seconds_read / 60)
+        .unwrap_or(0);
+
+    let streak_days: Vec<i32> = daily_reading_times
+        .iter()
+        .filter(|d| match minutes_per_day_goal {
+            Some(goal) => d.seconds_read >= goal * 60,
+            None => d.seconds_read > 0,
+        })
+        .map(|d| d.day)
+        .collect();
+
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    let mut previous_day: Option<i32> = None;
+    for day in &streak_days {
+        streak = match previous_day {
+            Some(v) if v + 1 == *day => streak + 1,
+            _ => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        previous_day = Some(*day);
+    }
+    let current_streak = match previous_day {
+        Some(v) if v == today || v + 1 == today => streak,
+        _ => 0,
+    };
+
+    Ok(ReadingGoalProgress {
+        year,
+        books_per_year_goal,
+        books_completed: books_completed_per_month
+            .iter()
+            .map(|m| m.books_completed)
+            .sum(),
+        books_completed_per_month,
+        minutes_per_day_goal,
+        minutes_read_today,
+        current_streak,
+        longest_streak,
+    })
+}
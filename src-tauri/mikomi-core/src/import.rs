@@ -0,0 +1,54 @@
+use crate::{books, current_timestamp, models, Error, Result};
+use diesel::SqliteConnection;
+use epub::doc::EpubDoc;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+fn rejected(message: &str) -> Error {
+    Error::Rejected(String::from(message))
+}
+
+// Reads the epub at `path` into the library, keeping its cover in `covers_dir`
+pub fn import_book(
+    conn: &mut SqliteConnection,
+    path: String,
+    covers_dir: &Path,
+) -> Result<models::Book> {
+    let mut doc = EpubDoc::new(path.clone()).map_err(|_| rejected("Cannot read epub file"))?;
+
+    let uuid = Uuid::new_v4().to_string();
+
+    let (cover, _) = doc.get_cover().ok_or(rejected("No cover found in epub"))?;
+    fs::create_dir_all(covers_dir)
+        .and_then(|_| fs::write(covers_dir.join(&uuid), cover))
+        .map_err(|_| rejected("Error saving epub cover"))?;
+
+    let empty_vec = vec![];
+    let authors = doc.metadata.get("creator").unwrap_or(&empty_vec).clone();
+
+    let title = doc
+        .mdata("title")
+        .ok_or(rejected("Epub does not have a title"))?;
+
+    let new_book = models::Book {
+        title,
+        path,
+        id: uuid.clone(),
+        last_read: None,
+        date_added: current_timestamp(),
+        reading_status: models::ReadingStatus::PlanToRead,
+        language: doc.mdata("language"),
+        description: doc.mdata("description"),
+        identifier: doc.mdata("identifier"),
+        last_modified: doc.mdata("dcterms:modified"),
+        published_date: doc.mdata("date"),
+        publisher: doc.mdata("publisher"),
+        page_progression_direction: doc.page_progression_direction,
+        deleted_at: None,
+    };
+
+    books::add_book(conn, &new_book, &authors)?;
+
+    Ok(new_book)
+}
@@ -0,0 +1,378 @@
+use crate::{current_timestamp, models, schema, Error, Result};
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use uuid::Uuid;
+
+const JOURNAL_LIMIT: i32 = 100;
+
+// The state of a single row before and after a mutation. `None` means that the row does not
+// exist, so undoing restores every `before` state and redoing restores every `after` state.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum JournalChange {
+    Bookmark {
+        id: String,
+        before: Option<models::Bookmark>,
+        after: Option<models::Bookmark>,
+    },
+    Highlight {
+        id: String,
+        before: Option<models::Highlight>,
+        after: Option<models::Highlight>,
+    },
+    HighlightNote {
+        id: String,
+        before: Option<models::HighlightNote>,
+        after: Option<models::HighlightNote>,
+    },
+    HighlightNoteRevision {
+        id: String,
+        before: Option<models::HighlightNoteRevision>,
+        after: Option<models::HighlightNoteRevision>,
+    },
+    Collection {
+        id: String,
+        before: Option<models::Collection>,
+        after: Option<models::Collection>,
+    },
+    BookCollectionLink {
+        book_id: String,
+        collection_id: String,
+        before: Option<models::BookCollectionLink>,
+        after: Option<models::BookCollectionLink>,
+    },
+    ReaderSettingsDefault {
+        id: String,
+        before: Option<Box<models::ReaderSettingsDefault>>,
+        after: Option<Box<models::ReaderSettingsDefault>>,
+    },
+    BookDeletedAt {
+        book_id: String,
+        before: Option<i32>,
+        after: Option<i32>,
+    },
+}
+
+fn apply_journal_change(
+    conn: &mut SqliteConnection,
+    change: &JournalChange,
+    undo: bool,
+) -> Result<()> {
+    match change {
+        JournalChange::Bookmark { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::bookmark::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => diesel::delete(schema::bookmark::table.find(id)).execute(conn)?,
+            };
+        }
+        JournalChange::Highlight { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::highlight::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => diesel::delete(schema::highlight::table.find(id)).execute(conn)?,
+            };
+        }
+        JournalChange::HighlightNote { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::highlight_note::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => diesel::delete(schema::highlight_note::table.find(id)).execute(conn)?,
+            };
+        }
+        JournalChange::HighlightNoteRevision { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::highlight_note_revision::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => {
+                    diesel::delete(schema::highlight_note_revision::table.find(id)).execute(conn)?
+                }
+            };
+        }
+        JournalChange::Collection { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::collection::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => diesel::delete(schema::collection::table.find(id)).execute(conn)?,
+            };
+        }
+        JournalChange::BookCollectionLink {
+            book_id,
+            collection_id,
+            before,
+            after,
+        } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::book_collection_link::table)
+                    .values(v)
+                    .execute(conn)?,
+                None => diesel::delete(
+                    schema::book_collection_link::table.find((book_id, collection_id)),
+                )
+                .execute(conn)?,
+            };
+        }
+        JournalChange::ReaderSettingsDefault { id, before, after } => {
+            match if undo { before } else { after } {
+                Some(v) => diesel::replace_into(schema::reader_settings_default::table)
+                    .values(v.as_ref())
+                    .execute(conn)?,
+                None => {
+                    diesel::delete(schema::reader_settings_default::table.find(id)).execute(conn)?
+                }
+            };
+        }
+        JournalChange::BookDeletedAt {
+            book_id,
+            before,
+            after,
+        } => {
+            let updated = diesel::update(schema::book::table.find(book_id))
+                .set(schema::book::deleted_at.eq(if undo { before } else { after }))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(Error::NotFound);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Records the changes that the highlights' removal will make, in the order that
+// `annotations::delete_highlight_notes` and the highlight deletion make them
+pub fn highlight_removal_changes(
+    conn: &mut SqliteConnection,
+    highlight_ids: &[String],
+) -> Result<Vec<JournalChange>> {
+    let highlights: Vec<models::Highlight> = schema::highlight::table
+        .filter(schema::highlight::id.eq_any(highlight_ids))
+        .select(models::Highlight::as_select())
+        .load(conn)?;
+
+    let notes: Vec<models::HighlightNote> = schema::highlight_note::table
+        .filter(schema::highlight_note::highlight_id.eq_any(highlight_ids))
+        .select(models::HighlightNote::as_select())
+        .load(conn)?;
+
+    let revisions: Vec<models::HighlightNoteRevision> = schema::highlight_note_revision::table
+        .filter(
+            schema::highlight_note_revision::highlight_note_id
+                .eq_any(notes.iter().map(|n| n.id.clone())),
+        )
+        .select(models::HighlightNoteRevision::as_select())
+        .load(conn)?;
+
+    let mut changes: Vec<JournalChange> = vec![];
+    for revision in revisions {
+        changes.push(JournalChange::HighlightNoteRevision {
+            id: revision.id.clone(),
+            before: Some(revision),
+            after: None,
+        });
+    }
+    for note in notes.iter().filter(|n| n.parent_id.is_some()) {
+        changes.push(JournalChange::HighlightNote {
+            id: note.id.clone(),
+            before: Some(models::HighlightNote {
+                id: note.id.clone(),
+                highlight_id: note.highlight_id.clone(),
+                parent_id: note.parent_id.clone(),
+                content: note.content.clone(),
+                date_added: note.date_added,
+                date_modified: note.date_modified,
+            }),
+            after: Some(models::HighlightNote {
+                id: note.id.clone(),
+                highlight_id: note.highlight_id.clone(),
+                parent_id: None,
+                content: note.content.clone(),
+                date_added: note.date_added,
+                date_modified: note.date_modified,
+            }),
+        });
+    }
+    for note in notes {
+        changes.push(JournalChange::HighlightNote {
+            id: note.id.clone(),
+            before: Some(models::HighlightNote {
+                parent_id: None,
+                ..note
+            }),
+            after: None,
+        });
+    }
+    for highlight in highlights {
+        changes.push(JournalChange::Highlight {
+            id: highlight.id.clone(),
+            before: Some(highlight),
+            after: None,
+        });
+    }
+
+    Ok(changes)
+}
+
+pub fn record_journal_entry(
+    conn: &mut SqliteConnection,
+    description: &str,
+    changes: Vec<JournalChange>,
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    // A new mutation makes the undone entries impossible to redo
+    diesel::delete(schema::journal_entry::table.filter(schema::journal_entry::undone.eq(true)))
+        .execute(conn)?;
+
+    let last_sequence: Option<i32> = schema::journal_entry::table
+        .select(diesel::dsl::max(schema::journal_entry::sequence))
+        .get_result(conn)?;
+    let sequence = last_sequence.unwrap_or(0) + 1;
+
+    let changes = serde_json::to_string(&changes)
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+
+    diesel::insert_into(schema::journal_entry::table)
+        .values(models::JournalEntry {
+            id: Uuid::new_v4().to_string(),
+            sequence,
+            description: description.to_string(),
+            changes,
+            undone: false,
+            date_added: current_timestamp(),
+        })
+        .execute(conn)?;
+
+    diesel::delete(
+        schema::journal_entry::table
+            .filter(schema::journal_entry::sequence.le(sequence - JOURNAL_LIMIT)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+#[derive(Serialize, Type)]
+pub struct JournalEntrySummary {
+    pub id: String,
+    pub description: String,
+    pub undone: bool,
+    pub date_added: i32,
+}
+
+pub fn get_journal_entries(conn: &mut SqliteConnection) -> Result<Vec<JournalEntrySummary>> {
+    let entries: Vec<models::JournalEntry> = schema::journal_entry::table
+        .select(models::JournalEntry::as_select())
+        .order(schema::journal_entry::sequence.desc())
+        .load(conn)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|e| JournalEntrySummary {
+            id: e.id,
+            description: e.description,
+            undone: e.undone,
+            date_added: e.date_added,
+        })
+        .collect())
+}
+
+// Whether replaying failed because the rows the entry depends on are gone or clash with
+// newer ones, so that it can never succeed. Other errors, like a busy database, may go away
+// on the next try.
+fn is_stale_entry_error(e: &Error) -> bool {
+    match e {
+        Error::NotFound => true,
+        Error::Database(diesel::result::Error::DatabaseError(kind, _)) => matches!(
+            kind,
+            DatabaseErrorKind::UniqueViolation
+                | DatabaseErrorKind::ForeignKeyViolation
+                | DatabaseErrorKind::NotNullViolation
+                | DatabaseErrorKind::CheckViolation
+        ),
+        _ => false,
+    }
+}
+
+// Replays the latest entry that can be undone or redone and returns its description, or
+// `None` when there is nothing to replay
+fn replay_journal_entry(conn: &mut SqliteConnection, undo: bool) -> Result<Option<String>> {
+    let query = schema::journal_entry::table
+        .filter(schema::journal_entry::undone.eq(!undo))
+        .select(models::JournalEntry::as_select());
+    let entry = if undo {
+        query
+            .order(schema::journal_entry::sequence.desc())
+            .first(conn)
+    } else {
+        query.order(schema::journal_entry::sequence).first(conn)
+    };
+    let entry: models::JournalEntry = match entry.optional()? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let changes: Vec<JournalChange> = match serde_json::from_str(&entry.changes) {
+        Ok(v) => v,
+        Err(_) => {
+            // Left in place, the entry would be picked again and block every older entry
+            diesel::delete(schema::journal_entry::table.find(&entry.id)).execute(conn)?;
+            return Err(Error::Rejected(String::from("Invalid journal entry")));
+        }
+    };
+
+    let res = conn.transaction(|conn| {
+        if undo {
+            for change in changes.iter().rev() {
+                apply_journal_change(conn, change, true)?;
+            }
+        } else {
+            for change in &changes {
+                apply_journal_change(conn, change, false)?;
+            }
+        }
+
+        diesel::update(schema::journal_entry::table.find(&entry.id))
+            .set(schema::journal_entry::undone.eq(undo))
+            .execute(conn)?;
+
+        Result::Ok(())
+    });
+
+    match res {
+        Ok(_) => Ok(Some(entry.description)),
+        Err(e) if !is_stale_entry_error(&e) => Err(e),
+        Err(_) => {
+            diesel::delete(schema::journal_entry::table.find(&entry.id)).execute(conn)?;
+            if undo {
+                Err(Error::Rejected(format!(
+                    "Cannot undo \"{}\"",
+                    entry.description
+                )))
+            } else {
+                Err(Error::Rejected(format!(
+                    "Cannot redo \"{}\"",
+                    entry.description
+                )))
+            }
+        }
+    }
+}
+
+pub fn undo_last(conn: &mut SqliteConnection) -> Result<Option<String>> {
+    replay_journal_entry(conn, true)
+}
+
+pub fn redo(conn: &mut SqliteConnection) -> Result<Option<String>> {
+    replay_journal_entry(conn, false)
+}
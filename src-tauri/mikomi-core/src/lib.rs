@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod annotations;
+pub mod authors;
+pub mod backup;
+pub mod books;
+pub mod collections;
+mod error;
+pub mod fonts;
+pub mod goals;
+pub mod import;
+pub mod journal;
+pub mod models;
+pub mod schema;
+pub mod sessions;
+pub mod settings;
+pub mod themes;
+
+pub use error::{Error, Result};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+// Moves the library database elsewhere, such as to `:memory:` or a temporary folder in tests
+pub const DATABASE_URL_VAR: &str = "MIKOMI_DATABASE_URL";
+
+const DEFAULT_DATABASE_URL: &str = "mikomi-data/db.sqlite";
+
+// The app and its tools keep the library in `mikomi-data` inside their working directory
+pub const COVERS_DIR: &str = "mikomi-data/covers";
+
+pub const SECONDS_PER_DAY: i32 = 60 * 60 * 24;
+
+pub fn current_timestamp() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32
+}
+
+pub fn database_url() -> String {
+    env::var(DATABASE_URL_VAR).unwrap_or_else(|_| String::from(DEFAULT_DATABASE_URL))
+}
+
+// Opens the library database at `database_url`, which is either a file path or `:memory:`
+pub fn establish_connection(database_url: &str) -> ConnectionResult<SqliteConnection> {
+    SqliteConnection::establish(database_url)
+}
+
+pub fn run_migrations(
+    conn: &mut SqliteConnection,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    conn.run_pending_migrations(MIGRATIONS)?;
+
+    Ok(())
+}
@@ -0,0 +1,648 @@
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::{Sqlite, SqliteValue};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+#[derive(
+    AsExpression, FromSqlRow, Deserialize, Serialize, Type, PartialEq, Eq, Debug, Clone, Copy,
+)]
+#[diesel(sql_type = Text)]
+pub enum ReadingStatus {
+    Reading,
+    #[serde(rename = "Plan to read")]
+    PlanToRead,
+    Finished,
+}
+
+impl ReadingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingStatus::Reading => "Reading",
+            ReadingStatus::PlanToRead => "Plan to read",
+            ReadingStatus::Finished => "Finished",
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for ReadingStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.as_str());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for ReadingStatus {
+    fn from_sql(bytes: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        match value.as_str() {
+            "Reading" => Ok(ReadingStatus::Reading),
+            "Plan to read" => Ok(ReadingStatus::PlanToRead),
+            "Finished" => Ok(ReadingStatus::Finished),
+            _ => Err(format!("Unknown reading status: {value}").into()),
+        }
+    }
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Identifiable,
+    QueryableByName,
+    Serialize,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(table_name = crate::schema::book)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Book {
+    pub id: String,
+    pub title: String,
+    pub path: String,
+    pub last_read: Option<i32>,
+    pub date_added: i32,
+    pub reading_status: ReadingStatus,
+    pub language: Option<String>,
+    pub last_modified: Option<String>,
+    pub identifier: Option<String>,
+    pub published_date: Option<String>,
+    pub description: Option<String>,
+    pub publisher: Option<String>,
+    pub page_progression_direction: Option<String>,
+    pub deleted_at: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Identifiable, Type, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::app_setting)]
+#[diesel(primary_key(key))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AppSetting {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Identifiable, Type, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::author)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Author {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::journal_entry)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JournalEntry {
+    pub id: String,
+    pub sequence: i32,
+    pub description: String,
+    pub changes: String,
+    pub undone: bool,
+    pub date_added: i32,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Identifiable, Type, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::opds_catalog)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct OpdsCatalog {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub username: Option<String>,
+    #[serde(skip)]
+    pub password: Option<String>,
+    pub date_added: i32,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::sync_record)]
+#[diesel(primary_key(book_id, record_key))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SyncRecord {
+    pub book_id: String,
+    pub record_key: String,
+    pub value: Option<String>,
+    pub modified: i32,
+    pub device_id: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Identifiable, Type, PartialEq, Debug)]
+#[diesel(table_name = crate::schema::language)]
+#[diesel(primary_key(name))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Language {
+    pub name: String,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(table_name = crate::schema::bookmark)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Bookmark {
+    pub id: String,
+    pub book_id: String,
+    pub display_text: String,
+    pub date_added: i32,
+    pub css_selector: String,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(belongs_to(HighlightColor))]
+#[diesel(table_name = crate::schema::highlight)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Highlight {
+    pub id: String,
+    pub book_id: String,
+    pub date_added: i32,
+    pub note: String,
+    pub start_container: String,
+    pub start_offset: i32,
+    pub end_container: String,
+    pub end_offset: i32,
+    pub color: String,
+    pub highlight_color_id: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+    Clone,
+)]
+#[diesel(table_name = crate::schema::highlight_color)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HighlightColor {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Highlight))]
+#[diesel(table_name = crate::schema::highlight_note)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HighlightNote {
+    pub id: String,
+    pub highlight_id: String,
+    pub parent_id: Option<String>,
+    pub content: String,
+    pub date_added: i32,
+    pub date_modified: Option<i32>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+)]
+#[diesel(belongs_to(HighlightNote))]
+#[diesel(table_name = crate::schema::highlight_note_revision)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HighlightNoteRevision {
+    pub id: String,
+    pub highlight_note_id: String,
+    pub content: String,
+    pub date_added: i32,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+    Clone,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(belongs_to(ReaderTheme))]
+#[diesel(table_name = crate::schema::book_settings)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookSettings {
+    pub id: String,
+    pub book_id: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub percentage: Option<i32>,
+    pub last_element: Option<String>,
+    pub last_page: Option<i32>,
+    pub font_size: Option<i32>,
+    pub line_height: Option<String>,
+    pub margins: Option<i32>,
+    pub text_align: Option<String>,
+    pub column_count: Option<i32>,
+    pub writing_mode: Option<String>,
+    pub font_family: Option<String>,
+    pub background_color: Option<String>,
+    pub color: Option<String>,
+    pub link_color: Option<String>,
+    pub primary_color: Option<String>,
+    pub image_blend_mode: Option<String>,
+    pub reader_theme_id: Option<String>,
+    pub user_css: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+    Clone,
+)]
+#[diesel(table_name = crate::schema::reader_settings_default)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReaderSettingsDefault {
+    pub id: String,
+    pub language: Option<String>,
+    pub collection_id: Option<String>,
+    pub font_size: Option<i32>,
+    pub line_height: Option<String>,
+    pub margins: Option<i32>,
+    pub text_align: Option<String>,
+    pub column_count: Option<i32>,
+    pub writing_mode: Option<String>,
+    pub font_family: Option<String>,
+    pub background_color: Option<String>,
+    pub color: Option<String>,
+    pub link_color: Option<String>,
+    pub primary_color: Option<String>,
+    pub image_blend_mode: Option<String>,
+    pub reader_theme_id: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(table_name = crate::schema::reader_settings_profile)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReaderSettingsProfile {
+    pub id: String,
+    pub name: String,
+    pub font_size: i32,
+    pub line_height: String,
+    pub margins: i32,
+    pub text_align: String,
+    pub column_count: i32,
+    pub writing_mode: String,
+    pub font_family: String,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(table_name = crate::schema::disabled_stylesheet)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DisabledStylesheet {
+    pub id: String,
+    pub book_id: String,
+    pub path: String,
+}
+
+#[derive(
+    Queryable, Selectable, Insertable, Deserialize, Serialize, Identifiable, Type, PartialEq, Debug,
+)]
+#[diesel(table_name = crate::schema::font)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Font {
+    pub id: String,
+    pub family: String,
+    pub style: String,
+    pub weight: i32,
+    pub format: String,
+    pub date_added: i32,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    Clone,
+    AsChangeset,
+)]
+#[diesel(table_name = crate::schema::reader_theme)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReaderTheme {
+    pub id: String,
+    pub name: String,
+    pub background_color: String,
+    pub color: String,
+    pub link_color: String,
+    pub primary_color: String,
+    pub image_blend_mode: String,
+    #[serde(default)]
+    pub built_in: bool,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(table_name = crate::schema::reading_session)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReadingSession {
+    pub id: String,
+    pub book_id: String,
+    pub start_time: i32,
+    pub end_time: i32,
+    pub start_percentage: Option<i32>,
+    pub end_percentage: Option<i32>,
+    pub pages_read: i32,
+    pub characters_read: i32,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(table_name = crate::schema::reading_status_event)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReadingStatusEvent {
+    pub id: String,
+    pub book_id: String,
+    pub reading_status: ReadingStatus,
+    pub previous_reading_status: Option<ReadingStatus>,
+    pub date_added: i32,
+}
+
+#[derive(
+    AsExpression, FromSqlRow, Deserialize, Serialize, Type, PartialEq, Eq, Debug, Clone, Copy,
+)]
+#[diesel(sql_type = Text)]
+pub enum ReadingGoalType {
+    #[serde(rename = "Books per year")]
+    BooksPerYear,
+    #[serde(rename = "Minutes per day")]
+    MinutesPerDay,
+}
+
+impl ReadingGoalType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingGoalType::BooksPerYear => "Books per year",
+            ReadingGoalType::MinutesPerDay => "Minutes per day",
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for ReadingGoalType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.as_str());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for ReadingGoalType {
+    fn from_sql(bytes: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        match value.as_str() {
+            "Books per year" => Ok(ReadingGoalType::BooksPerYear),
+            "Minutes per day" => Ok(ReadingGoalType::MinutesPerDay),
+            _ => Err(format!("Unknown reading goal type: {value}").into()),
+        }
+    }
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Serialize,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+)]
+#[diesel(table_name = crate::schema::reading_goal)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReadingGoal {
+    pub id: String,
+    pub goal_type: ReadingGoalType,
+    pub target: i32,
+    pub year: Option<i32>,
+}
+
+#[derive(
+    Queryable, Selectable, Insertable, Serialize, Associations, Identifiable, Type, PartialEq, Debug,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(belongs_to(Author))]
+#[diesel(table_name = crate::schema::book_author_link)]
+#[diesel(primary_key(book_id, author_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookAuthorLink {
+    pub book_id: String,
+    pub author_id: String,
+    pub primary_creator: bool,
+}
+
+#[derive(Deserialize, Serialize, Type, PartialEq, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SmartCollectionRule {
+    Language {
+        languages: Vec<String>,
+    },
+    ReadingStatus {
+        reading_statuses: Vec<ReadingStatus>,
+    },
+    Author {
+        author_ids: Vec<String>,
+    },
+    Publisher {
+        publishers: Vec<String>,
+    },
+    TitleContains {
+        text: String,
+    },
+    AddedWithinDays {
+        days: i32,
+    },
+    LastReadWithinDays {
+        days: i32,
+    },
+}
+
+#[derive(AsExpression, FromSqlRow, Deserialize, Serialize, Type, PartialEq, Debug, Clone)]
+#[diesel(sql_type = Text)]
+pub struct SmartCollectionFilter {
+    pub match_all: bool,
+    pub rules: Vec<SmartCollectionRule>,
+}
+
+impl ToSql<Text, Sqlite> for SmartCollectionFilter {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(serde_json::to_string(self)?);
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for SmartCollectionFilter {
+    fn from_sql(bytes: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    Deserialize,
+    Identifiable,
+    QueryableByName,
+    Serialize,
+    Type,
+    PartialEq,
+    Debug,
+    AsChangeset,
+    Clone,
+)]
+#[diesel(table_name = crate::schema::collection)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub sort_order: Option<i32>,
+    pub smart_filter: Option<SmartCollectionFilter>,
+    pub parent_id: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Selectable,
+    Deserialize,
+    Insertable,
+    Serialize,
+    Associations,
+    Identifiable,
+    Type,
+    PartialEq,
+    Debug,
+)]
+#[diesel(belongs_to(Book))]
+#[diesel(belongs_to(Collection))]
+#[diesel(table_name = crate::schema::book_collection_link)]
+#[diesel(primary_key(book_id, collection_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookCollectionLink {
+    pub book_id: String,
+    pub collection_id: String,
+    pub sort_order: Option<i32>,
+}
@@ -0,0 +1,299 @@
+use crate::{current_timestamp, models, schema, Result};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Nullable, Text};
+use diesel::SqliteConnection;
+use serde::Serialize;
+use specta::Type;
+use uuid::Uuid;
+
+pub fn start_reading_session(
+    conn: &mut SqliteConnection,
+    book_id: String,
+    percentage: Option<i32>,
+) -> Result<models::ReadingSession> {
+    let now = current_timestamp();
+    let new_reading_session = models::ReadingSession {
+        id: Uuid::new_v4().to_string(),
+        book_id,
+        start_time: now,
+        end_time: now,
+        start_percentage: percentage,
+        end_percentage: percentage,
+        pages_read: 0,
+        characters_read: 0,
+    };
+
+    conn.transaction(|conn| {
+        diesel::insert_into(schema::reading_session::table)
+            .values(&new_reading_session)
+            .execute(conn)?;
+        diesel::update(schema::book::table.find(&new_reading_session.book_id))
+            .set(schema::book::last_read.eq(now))
+            .execute(conn)?;
+
+        Ok(new_reading_session)
+    })
+}
+
+pub fn heartbeat_reading_session(
+    conn: &mut SqliteConnection,
+    id: &str,
+    percentage: Option<i32>,
+    pages_read: i32,
+    characters_read: i32,
+) -> Result<()> {
+    diesel::update(schema::reading_session::table.find(id))
+        .set((
+            schema::reading_session::end_time.eq(current_timestamp()),
+            schema::reading_session::end_percentage.eq(percentage),
+            schema::reading_session::pages_read.eq(pages_read),
+            schema::reading_session::characters_read.eq(characters_read),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Sessions that ended as soon as they started are dropped, so that opening a book to look at
+// it does not count as reading it
+pub fn end_reading_session(
+    conn: &mut SqliteConnection,
+    id: &str,
+    percentage: Option<i32>,
+    pages_read: i32,
+    characters_read: i32,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        heartbeat_reading_session(conn, id, percentage, pages_read, characters_read)?;
+        diesel::delete(
+            schema::reading_session::table
+                .filter(schema::reading_session::id.eq(id))
+                .filter(schema::reading_session::end_time.eq(schema::reading_session::start_time)),
+        )
+        .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+#[derive(QueryableByName, Serialize, Type)]
+pub struct ReadingTimePerDay {
+    #[diesel(sql_type = Text)]
+    pub day: String,
+    #[diesel(sql_type = Integer)]
+    pub seconds_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub pages_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub characters_read: i32,
+}
+
+pub fn get_reading_time_per_day(
+    conn: &mut SqliteConnection,
+    from: i32,
+    to: i32,
+) -> Result<Vec<ReadingTimePerDay>> {
+    let days = diesel::sql_query(
+        "SELECT date(start_time, 'unixepoch', 'localtime') AS day,
+            CAST(SUM(end_time - start_time) AS INTEGER) AS seconds_read,
+            CAST(SUM(pages_read) AS INTEGER) AS pages_read,
+            CAST(SUM(characters_read) AS INTEGER) AS characters_read
+        FROM reading_session
+        WHERE start_time >= ? AND start_time < ?
+        GROUP BY day
+        ORDER BY day",
+    )
+    .bind::<Integer, _>(from)
+    .bind::<Integer, _>(to)
+    .load(conn)?;
+
+    Ok(days)
+}
+
+#[derive(QueryableByName, Serialize, Type)]
+pub struct ReadingTimePerBook {
+    #[diesel(sql_type = Text)]
+    pub book_id: String,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Integer)]
+    pub seconds_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub pages_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub characters_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub session_count: i32,
+}
+
+pub fn get_reading_time_per_book(conn: &mut SqliteConnection) -> Result<Vec<ReadingTimePerBook>> {
+    let books = diesel::sql_query(
+        "SELECT book.id AS book_id,
+            book.title AS title,
+            CAST(SUM(reading_session.end_time - reading_session.start_time) AS INTEGER) AS seconds_read,
+            CAST(SUM(reading_session.pages_read) AS INTEGER) AS pages_read,
+            CAST(SUM(reading_session.characters_read) AS INTEGER) AS characters_read,
+            COUNT(reading_session.id) AS session_count
+        FROM reading_session
+        INNER JOIN book ON book.id = reading_session.book_id
+        GROUP BY book.id
+        ORDER BY seconds_read DESC",
+    )
+    .load(conn)?;
+
+    Ok(books)
+}
+
+#[derive(QueryableByName, Serialize, Type)]
+pub struct ReadingTimePerAuthor {
+    #[diesel(sql_type = Text)]
+    pub author_id: String,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Integer)]
+    pub seconds_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub pages_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub characters_read: i32,
+    #[diesel(sql_type = Integer)]
+    pub book_count: i32,
+}
+
+pub fn get_reading_time_per_author(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<ReadingTimePerAuthor>> {
+    let authors = diesel::sql_query(
+        "SELECT author.id AS author_id,
+            author.name AS name,
+            CAST(SUM(reading_session.end_time - reading_session.start_time) AS INTEGER) AS seconds_read,
+            CAST(SUM(reading_session.pages_read) AS INTEGER) AS pages_read,
+            CAST(SUM(reading_session.characters_read) AS INTEGER) AS characters_read,
+            COUNT(DISTINCT reading_session.book_id) AS book_count
+        FROM reading_session
+        INNER JOIN book_author_link ON book_author_link.book_id = reading_session.book_id
+        INNER JOIN author ON author.id = book_author_link.author_id
+        GROUP BY author.id
+        ORDER BY seconds_read DESC",
+    )
+    .load(conn)?;
+
+    Ok(authors)
+}
+
+#[derive(Serialize, Type)]
+pub struct BookReadingStatistics {
+    pub book_id: String,
+    pub seconds_read: i32,
+    pub pages_read: i32,
+    pub characters_read: i32,
+    pub session_count: i32,
+    pub percentage: Option<i32>,
+    pub percentage_per_hour: Option<f64>,
+    pub pages_per_hour: Option<f64>,
+    pub characters_per_minute: Option<f64>,
+    pub estimated_seconds_left: Option<i32>,
+}
+
+pub fn get_book_reading_statistics(
+    conn: &mut SqliteConnection,
+    book_id: String,
+) -> Result<BookReadingStatistics> {
+    let (sessions, settings_percentage) = conn.transaction(|conn| {
+        let sessions: Vec<models::ReadingSession> = schema::reading_session::table
+            .filter(schema::reading_session::book_id.eq(&book_id))
+            .order(schema::reading_session::start_time)
+            .select(models::ReadingSession::as_select())
+            .load(conn)?;
+
+        let settings_percentage: Option<Option<i32>> = schema::book_settings::table
+            .filter(schema::book_settings::book_id.eq(&book_id))
+            .select(schema::book_settings::percentage)
+            .first(conn)
+            .optional()?;
+
+        diesel::result::QueryResult::Ok((sessions, settings_percentage.flatten()))
+    })?;
+
+    let seconds_read: i32 = sessions.iter().map(|s| s.end_time - s.start_time).sum();
+    let pages_read: i32 = sessions.iter().map(|s| s.pages_read).sum();
+    let characters_read: i32 = sessions.iter().map(|s| s.characters_read).sum();
+
+    let mut tracked_seconds = 0;
+    let mut tracked_percentage = 0;
+    for session in &sessions {
+        if let (Some(start), Some(end)) = (session.start_percentage, session.end_percentage) {
+            tracked_seconds += session.end_time - session.start_time;
+            tracked_percentage += end - start;
+        }
+    }
+
+    let percentage = settings_percentage.or(sessions.last().and_then(|s| s.end_percentage));
+
+    let per_hour = |amount: i32, seconds: i32| {
+        if amount > 0 && seconds > 0 {
+            Some(amount as f64 * 3600.0 / seconds as f64)
+        } else {
+            None
+        }
+    };
+
+    let percentage_per_hour = per_hour(tracked_percentage, tracked_seconds);
+    let estimated_seconds_left = match (percentage, percentage_per_hour) {
+        (Some(p), Some(rate)) => Some((((100 - p).max(0)) as f64 / rate * 3600.0).round() as i32),
+        _ => None,
+    };
+
+    Ok(BookReadingStatistics {
+        book_id,
+        seconds_read,
+        pages_read,
+        characters_read,
+        session_count: sessions.len() as i32,
+        percentage,
+        percentage_per_hour,
+        pages_per_hour: per_hour(pages_read, seconds_read),
+        characters_per_minute: per_hour(characters_read, seconds_read).map(|v| v / 60.0),
+        estimated_seconds_left,
+    })
+}
+
+pub fn get_reading_status_events(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+) -> Result<Vec<models::ReadingStatusEvent>> {
+    let events = schema::reading_status_event::table
+        .filter(schema::reading_status_event::book_id.eq(book_id))
+        .select(models::ReadingStatusEvent::as_select())
+        .order(schema::reading_status_event::date_added)
+        .load(conn)?;
+
+    Ok(events)
+}
+
+#[derive(QueryableByName, Serialize, Type)]
+pub struct ReadingHistory {
+    #[diesel(sql_type = Text)]
+    pub book_id: String,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub date_started: Option<i32>,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub date_finished: Option<i32>,
+    #[diesel(sql_type = Integer)]
+    pub reread_count: i32,
+}
+
+pub fn get_reading_histories(conn: &mut SqliteConnection) -> Result<Vec<ReadingHistory>> {
+    let histories = diesel::sql_query(
+        "SELECT book_id,
+            MIN(CASE WHEN reading_status = 'Reading' THEN date_added END) AS date_started,
+            MAX(CASE WHEN reading_status = 'Finished' THEN date_added END) AS date_finished,
+            COUNT(CASE WHEN reading_status = 'Reading'
+                AND previous_reading_status = 'Finished' THEN 1 END) AS reread_count
+        FROM reading_status_event
+        GROUP BY book_id",
+    )
+    .load(conn)?;
+
+    Ok(histories)
+}
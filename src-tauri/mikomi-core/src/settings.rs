@@ -0,0 +1,651 @@
+use crate::books::{run_bulk_operation, BulkOperationResult};
+use crate::collections::get_collection_books;
+use crate::{models, schema, Error, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use uuid::Uuid;
+
+pub const GLOBAL_READER_SETTINGS_ID: &str = "global";
+
+pub const USER_CSS_SETTING_KEY: &str = "user_css";
+
+pub fn get_app_setting(conn: &mut SqliteConnection, key: &str) -> Result<Option<String>> {
+    let value = schema::app_setting::table
+        .find(key)
+        .select(schema::app_setting::value)
+        .get_result(conn)
+        .optional()?;
+
+    Ok(value)
+}
+
+pub fn set_app_setting(conn: &mut SqliteConnection, key: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(value) => diesel::insert_into(schema::app_setting::table)
+            .values((
+                schema::app_setting::key.eq(key),
+                schema::app_setting::value.eq(value),
+            ))
+            .on_conflict(schema::app_setting::key)
+            .do_update()
+            .set(schema::app_setting::value.eq(value))
+            .execute(conn)?,
+        None => diesel::delete(schema::app_setting::table.filter(schema::app_setting::key.eq(key)))
+            .execute(conn)?,
+    };
+
+    Ok(())
+}
+
+// Saves the settings of the reader. The values that are not given keep what the book already
+// has, so only `update_book_settings` can clear an override.
+pub fn add_book_settings(
+    conn: &mut SqliteConnection,
+    new_book_settings: models::BookSettings,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        let old: Option<models::BookSettings> = schema::book_settings::table
+            .filter(schema::book_settings::book_id.eq(&new_book_settings.book_id))
+            .select(models::BookSettings::as_select())
+            .first(conn)
+            .optional()?;
+
+        let new_book_settings = match old {
+            Some(old) => models::BookSettings {
+                id: old.id,
+                book_id: old.book_id,
+                width: new_book_settings.width.or(old.width),
+                height: new_book_settings.height.or(old.height),
+                percentage: new_book_settings.percentage.or(old.percentage),
+                last_element: new_book_settings.last_element.or(old.last_element),
+                last_page: new_book_settings.last_page.or(old.last_page),
+                font_size: new_book_settings.font_size.or(old.font_size),
+                line_height: new_book_settings.line_height.or(old.line_height),
+                margins: new_book_settings.margins.or(old.margins),
+                text_align: new_book_settings.text_align.or(old.text_align),
+                column_count: new_book_settings.column_count.or(old.column_count),
+                writing_mode: new_book_settings.writing_mode.or(old.writing_mode),
+                font_family: new_book_settings.font_family.or(old.font_family),
+                background_color: new_book_settings.background_color.or(old.background_color),
+                color: new_book_settings.color.or(old.color),
+                link_color: new_book_settings.link_color.or(old.link_color),
+                primary_color: new_book_settings.primary_color.or(old.primary_color),
+                image_blend_mode: new_book_settings.image_blend_mode.or(old.image_blend_mode),
+                reader_theme_id: new_book_settings.reader_theme_id.or(old.reader_theme_id),
+                user_css: new_book_settings.user_css.or(old.user_css),
+            },
+            None => new_book_settings,
+        };
+
+        diesel::insert_into(schema::book_settings::table)
+            .values(&new_book_settings)
+            .on_conflict(schema::book_settings::book_id)
+            .do_update()
+            .set(&new_book_settings)
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn remove_book_settings(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    diesel::delete(schema::book_settings::table.filter(schema::book_settings::id.eq(id)))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn update_book_settings(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    new_book_settings: models::BookSettings,
+) -> Result<()> {
+    diesel::update(schema::book_settings::table.filter(schema::book_settings::book_id.eq(book_id)))
+        .set(&new_book_settings)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn reset_book_settings_to_defaults(conn: &mut SqliteConnection, book_id: &str) -> Result<()> {
+    diesel::update(schema::book_settings::table.filter(schema::book_settings::book_id.eq(book_id)))
+        .set((
+            schema::book_settings::font_size.eq(None::<i32>),
+            schema::book_settings::line_height.eq(None::<String>),
+            schema::book_settings::margins.eq(None::<i32>),
+            schema::book_settings::text_align.eq(None::<String>),
+            schema::book_settings::column_count.eq(None::<i32>),
+            schema::book_settings::writing_mode.eq(None::<String>),
+            schema::book_settings::font_family.eq(None::<String>),
+            schema::book_settings::background_color.eq(None::<String>),
+            schema::book_settings::color.eq(None::<String>),
+            schema::book_settings::link_color.eq(None::<String>),
+            schema::book_settings::primary_color.eq(None::<String>),
+            schema::book_settings::image_blend_mode.eq(None::<String>),
+            schema::book_settings::reader_theme_id.eq(None::<String>),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Updates the reading position of a book, creating its settings if it does not have any yet
+pub fn set_book_progress(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    percentage: Option<i32>,
+    last_element: Option<&str>,
+    last_page: Option<i32>,
+) -> Result<()> {
+    let updated = diesel::update(
+        schema::book_settings::table.filter(schema::book_settings::book_id.eq(book_id)),
+    )
+    .set((
+        schema::book_settings::percentage.eq(percentage),
+        schema::book_settings::last_element.eq(last_element),
+        schema::book_settings::last_page.eq(last_page),
+    ))
+    .execute(conn)?;
+
+    if updated == 0 {
+        diesel::insert_into(schema::book_settings::table)
+            .values((
+                schema::book_settings::id.eq(Uuid::new_v4().to_string()),
+                schema::book_settings::book_id.eq(book_id),
+                schema::book_settings::percentage.eq(percentage),
+                schema::book_settings::last_element.eq(last_element),
+                schema::book_settings::last_page.eq(last_page),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_book_user_css(conn: &mut SqliteConnection, book_id: &str) -> Result<Option<String>> {
+    let user_css = schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq(book_id))
+        .select(schema::book_settings::user_css)
+        .get_result::<Option<String>>(conn)
+        .optional()?;
+
+    Ok(user_css.flatten())
+}
+
+pub fn update_book_user_css(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    user_css: Option<&str>,
+) -> Result<()> {
+    diesel::insert_into(schema::book_settings::table)
+        .values((
+            schema::book_settings::id.eq(Uuid::new_v4().to_string()),
+            schema::book_settings::book_id.eq(book_id),
+            schema::book_settings::user_css.eq(user_css),
+        ))
+        .on_conflict(schema::book_settings::book_id)
+        .do_update()
+        .set(schema::book_settings::user_css.eq(user_css))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn get_disabled_stylesheets(conn: &mut SqliteConnection, book_id: &str) -> Result<Vec<String>> {
+    let paths = schema::disabled_stylesheet::table
+        .filter(schema::disabled_stylesheet::book_id.eq(book_id))
+        .select(schema::disabled_stylesheet::path)
+        .load(conn)?;
+
+    Ok(paths)
+}
+
+pub fn set_book_stylesheet_disabled(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    path: &str,
+    disabled: bool,
+) -> Result<()> {
+    if disabled {
+        diesel::insert_into(schema::disabled_stylesheet::table)
+            .values(models::DisabledStylesheet {
+                id: Uuid::new_v4().to_string(),
+                book_id: book_id.to_string(),
+                path: path.to_string(),
+            })
+            .on_conflict((
+                schema::disabled_stylesheet::book_id,
+                schema::disabled_stylesheet::path,
+            ))
+            .do_nothing()
+            .execute(conn)?;
+    } else {
+        diesel::delete(
+            schema::disabled_stylesheet::table
+                .filter(schema::disabled_stylesheet::book_id.eq(book_id))
+                .filter(schema::disabled_stylesheet::path.eq(path)),
+        )
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_reader_settings_defaults(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<models::ReaderSettingsDefault>> {
+    let defaults = schema::reader_settings_default::table
+        .select(models::ReaderSettingsDefault::as_select())
+        .get_results(conn)?;
+
+    Ok(defaults)
+}
+
+pub fn add_reader_settings_default(
+    conn: &mut SqliteConnection,
+    new_reader_settings_default: models::ReaderSettingsDefault,
+) -> Result<()> {
+    let is_global = new_reader_settings_default.id == GLOBAL_READER_SETTINGS_ID;
+    let has_scope = new_reader_settings_default.language.is_some()
+        || new_reader_settings_default.collection_id.is_some();
+    if is_global == has_scope {
+        return Err(Error::Rejected(String::from(
+            "Reader settings defaults must apply globally, to a language or to a collection",
+        )));
+    }
+
+    diesel::insert_into(schema::reader_settings_default::table)
+        .values(&new_reader_settings_default)
+        .on_conflict(schema::reader_settings_default::id)
+        .do_update()
+        .set(&new_reader_settings_default)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_reader_settings_default(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    if id == GLOBAL_READER_SETTINGS_ID {
+        return Err(Error::Rejected(String::from(
+            "Cannot delete global reader settings",
+        )));
+    }
+
+    diesel::delete(
+        schema::reader_settings_default::table.filter(schema::reader_settings_default::id.eq(id)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+#[derive(Serialize, Type)]
+pub struct EffectiveBookSettings {
+    pub book_id: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub percentage: Option<i32>,
+    pub last_element: Option<String>,
+    pub last_page: Option<i32>,
+    pub font_size: i32,
+    pub line_height: String,
+    pub margins: i32,
+    pub text_align: String,
+    pub column_count: i32,
+    pub writing_mode: String,
+    pub font_family: String,
+    pub background_color: String,
+    pub color: String,
+    pub link_color: String,
+    pub primary_color: String,
+    pub image_blend_mode: String,
+    pub reader_theme_id: Option<String>,
+}
+
+fn merge_reader_settings(
+    layers: Vec<models::ReaderSettingsDefault>,
+) -> Option<models::ReaderSettingsDefault> {
+    layers
+        .into_iter()
+        .reduce(|acc, layer| models::ReaderSettingsDefault {
+            id: acc.id,
+            language: acc.language,
+            collection_id: acc.collection_id,
+            font_size: acc.font_size.or(layer.font_size),
+            line_height: acc.line_height.or(layer.line_height),
+            margins: acc.margins.or(layer.margins),
+            text_align: acc.text_align.or(layer.text_align),
+            column_count: acc.column_count.or(layer.column_count),
+            writing_mode: acc.writing_mode.or(layer.writing_mode),
+            font_family: acc.font_family.or(layer.font_family),
+            background_color: acc.background_color.or(layer.background_color),
+            color: acc.color.or(layer.color),
+            link_color: acc.link_color.or(layer.link_color),
+            primary_color: acc.primary_color.or(layer.primary_color),
+            image_blend_mode: acc.image_blend_mode.or(layer.image_blend_mode),
+            reader_theme_id: acc.reader_theme_id.or(layer.reader_theme_id),
+        })
+}
+
+pub fn get_effective_book_settings(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+) -> Result<EffectiveBookSettings> {
+    let book_settings: Option<models::BookSettings> = schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq(book_id))
+        .select(models::BookSettings::as_select())
+        .first(conn)
+        .optional()?;
+
+    let language: Option<String> = schema::book::table
+        .find(book_id)
+        .select(schema::book::language)
+        .get_result(conn)?;
+
+    let collection_ids: Vec<String> = schema::book_collection_link::table
+        .inner_join(schema::collection::table)
+        .filter(schema::book_collection_link::book_id.eq(book_id))
+        .order(schema::collection::sort_order)
+        .select(schema::collection::id)
+        .load(conn)?;
+
+    let scoped_defaults: Vec<models::ReaderSettingsDefault> =
+        schema::reader_settings_default::table
+            .filter(
+                schema::reader_settings_default::collection_id
+                    .eq_any(&collection_ids)
+                    .or(schema::reader_settings_default::language.eq(&language))
+                    .or(schema::reader_settings_default::id.eq(GLOBAL_READER_SETTINGS_ID)),
+            )
+            .select(models::ReaderSettingsDefault::as_select())
+            .load(conn)?;
+
+    let reader_themes: Vec<models::ReaderTheme> = schema::reader_theme::table
+        .select(models::ReaderTheme::as_select())
+        .load(conn)?;
+
+    let mut layers: Vec<models::ReaderSettingsDefault> = vec![];
+    if let Some(s) = &book_settings {
+        layers.push(models::ReaderSettingsDefault {
+            id: s.id.clone(),
+            language: None,
+            collection_id: None,
+            font_size: s.font_size,
+            line_height: s.line_height.clone(),
+            margins: s.margins,
+            text_align: s.text_align.clone(),
+            column_count: s.column_count,
+            writing_mode: s.writing_mode.clone(),
+            font_family: s.font_family.clone(),
+            background_color: s.background_color.clone(),
+            color: s.color.clone(),
+            link_color: s.link_color.clone(),
+            primary_color: s.primary_color.clone(),
+            image_blend_mode: s.image_blend_mode.clone(),
+            reader_theme_id: s.reader_theme_id.clone(),
+        });
+    }
+    for collection_id in &collection_ids {
+        if let Some(d) = scoped_defaults
+            .iter()
+            .find(|d| d.collection_id.as_ref() == Some(collection_id))
+        {
+            layers.push(d.clone());
+        }
+    }
+    if let Some(d) = scoped_defaults
+        .iter()
+        .find(|d| d.language.is_some() && d.language == language)
+    {
+        layers.push(d.clone());
+    }
+    if let Some(d) = scoped_defaults
+        .iter()
+        .find(|d| d.id == GLOBAL_READER_SETTINGS_ID)
+    {
+        layers.push(d.clone());
+    }
+
+    let layers = layers
+        .into_iter()
+        .map(|layer| {
+            let theme = reader_themes
+                .iter()
+                .find(|t| Some(&t.id) == layer.reader_theme_id.as_ref());
+            match theme {
+                Some(t) => models::ReaderSettingsDefault {
+                    background_color: layer.background_color.or(Some(t.background_color.clone())),
+                    color: layer.color.or(Some(t.color.clone())),
+                    link_color: layer.link_color.or(Some(t.link_color.clone())),
+                    primary_color: layer.primary_color.or(Some(t.primary_color.clone())),
+                    image_blend_mode: layer.image_blend_mode.or(Some(t.image_blend_mode.clone())),
+                    ..layer
+                },
+                None => layer,
+            }
+        })
+        .collect();
+
+    let merged = merge_reader_settings(layers);
+    let merged = merged.as_ref();
+
+    Ok(EffectiveBookSettings {
+        book_id: book_id.to_string(),
+        width: book_settings.as_ref().and_then(|s| s.width),
+        height: book_settings.as_ref().and_then(|s| s.height),
+        percentage: book_settings.as_ref().and_then(|s| s.percentage),
+        last_element: book_settings.as_ref().and_then(|s| s.last_element.clone()),
+        last_page: book_settings.as_ref().and_then(|s| s.last_page),
+        font_size: merged.and_then(|m| m.font_size).unwrap_or(16),
+        line_height: merged
+            .and_then(|m| m.line_height.clone())
+            .unwrap_or(String::from("normal")),
+        margins: merged.and_then(|m| m.margins).unwrap_or(0),
+        text_align: merged
+            .and_then(|m| m.text_align.clone())
+            .unwrap_or(String::from("initial")),
+        column_count: merged.and_then(|m| m.column_count).unwrap_or(1),
+        writing_mode: merged
+            .and_then(|m| m.writing_mode.clone())
+            .unwrap_or(String::from("horizontal")),
+        font_family: merged
+            .and_then(|m| m.font_family.clone())
+            .unwrap_or(String::from("initial")),
+        background_color: merged
+            .and_then(|m| m.background_color.clone())
+            .unwrap_or(String::from("#ffffff")),
+        color: merged
+            .and_then(|m| m.color.clone())
+            .unwrap_or(String::from("#333333")),
+        link_color: merged
+            .and_then(|m| m.link_color.clone())
+            .unwrap_or(String::from("#007acc")),
+        primary_color: merged
+            .and_then(|m| m.primary_color.clone())
+            .unwrap_or(String::from("#4181e3")),
+        image_blend_mode: merged
+            .and_then(|m| m.image_blend_mode.clone())
+            .unwrap_or(String::from("normal")),
+        reader_theme_id: merged.and_then(|m| m.reader_theme_id.clone()),
+    })
+}
+
+pub fn get_reader_settings_profiles(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<models::ReaderSettingsProfile>> {
+    let profiles = schema::reader_settings_profile::table
+        .select(models::ReaderSettingsProfile::as_select())
+        .order(schema::reader_settings_profile::name)
+        .get_results(conn)?;
+
+    Ok(profiles)
+}
+
+pub fn add_reader_settings_profile(
+    conn: &mut SqliteConnection,
+    new_reader_settings_profile: models::ReaderSettingsProfile,
+) -> Result<()> {
+    diesel::insert_into(schema::reader_settings_profile::table)
+        .values(&new_reader_settings_profile)
+        .on_conflict(schema::reader_settings_profile::id)
+        .do_update()
+        .set(&new_reader_settings_profile)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn update_reader_settings_profile(
+    conn: &mut SqliteConnection,
+    reader_settings_profile: models::ReaderSettingsProfile,
+) -> Result<()> {
+    let updated = diesel::update(
+        schema::reader_settings_profile::table
+            .filter(schema::reader_settings_profile::id.eq(&reader_settings_profile.id)),
+    )
+    .set(&reader_settings_profile)
+    .execute(conn)?;
+
+    if updated == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
+
+pub fn remove_reader_settings_profile(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    diesel::delete(
+        schema::reader_settings_profile::table.filter(schema::reader_settings_profile::id.eq(id)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Type)]
+pub enum ReaderSettingsProfileTarget {
+    Book(String),
+    Collection(String),
+    Books(Vec<String>),
+}
+
+// Applies the profile to every book of the target. Each book is reported on its own, so an
+// unknown book id does not stop the profile from reaching the others.
+pub fn apply_reader_settings_profile(
+    conn: &mut SqliteConnection,
+    id: &str,
+    target: ReaderSettingsProfileTarget,
+) -> Result<Vec<BulkOperationResult>> {
+    conn.transaction(|conn| {
+        let profile: models::ReaderSettingsProfile = schema::reader_settings_profile::table
+            .find(id)
+            .select(models::ReaderSettingsProfile::as_select())
+            .get_result(conn)?;
+
+        let book_ids: Vec<String> = match target {
+            ReaderSettingsProfileTarget::Book(book_id) => vec![book_id],
+            ReaderSettingsProfileTarget::Books(book_ids) => book_ids,
+            ReaderSettingsProfileTarget::Collection(collection_id) => {
+                let collection: models::Collection = schema::collection::table
+                    .find(collection_id)
+                    .select(models::Collection::as_select())
+                    .get_result(conn)?;
+                get_collection_books(conn, &collection)?
+                    .into_iter()
+                    .map(|b| b.id)
+                    .collect()
+            }
+        };
+
+        run_bulk_operation(
+            conn,
+            book_ids,
+            "Cannot apply reader settings profile",
+            |conn, book_id| {
+                diesel::insert_into(schema::book_settings::table)
+                    .values((
+                        schema::book_settings::id.eq(Uuid::new_v4().to_string()),
+                        schema::book_settings::book_id.eq(book_id),
+                        schema::book_settings::font_size.eq(profile.font_size),
+                        schema::book_settings::line_height.eq(&profile.line_height),
+                        schema::book_settings::margins.eq(profile.margins),
+                        schema::book_settings::text_align.eq(&profile.text_align),
+                        schema::book_settings::column_count.eq(profile.column_count),
+                        schema::book_settings::writing_mode.eq(&profile.writing_mode),
+                        schema::book_settings::font_family.eq(&profile.font_family),
+                    ))
+                    .on_conflict(schema::book_settings::book_id)
+                    .do_update()
+                    .set((
+                        schema::book_settings::font_size.eq(profile.font_size),
+                        schema::book_settings::line_height.eq(&profile.line_height),
+                        schema::book_settings::margins.eq(profile.margins),
+                        schema::book_settings::text_align.eq(&profile.text_align),
+                        schema::book_settings::column_count.eq(profile.column_count),
+                        schema::book_settings::writing_mode.eq(&profile.writing_mode),
+                        schema::book_settings::font_family.eq(&profile.font_family),
+                    ))
+                    .execute(conn)?;
+
+                Ok(())
+            },
+        )
+    })
+}
+
+#[derive(Deserialize, Type, AsChangeset)]
+#[diesel(table_name = schema::book_settings)]
+pub struct BulkBookSettings {
+    pub font_size: Option<i32>,
+    pub line_height: Option<String>,
+    pub margins: Option<i32>,
+    pub text_align: Option<String>,
+    pub column_count: Option<i32>,
+    pub writing_mode: Option<String>,
+    pub font_family: Option<String>,
+    pub reader_theme_id: Option<String>,
+}
+
+pub fn bulk_update_book_settings(
+    conn: &mut SqliteConnection,
+    book_ids: Vec<String>,
+    settings: BulkBookSettings,
+) -> Result<Vec<BulkOperationResult>> {
+    // Diesel cannot build an update without any column, which would fail for every book
+    let is_empty = settings.font_size.is_none()
+        && settings.line_height.is_none()
+        && settings.margins.is_none()
+        && settings.text_align.is_none()
+        && settings.column_count.is_none()
+        && settings.writing_mode.is_none()
+        && settings.font_family.is_none()
+        && settings.reader_theme_id.is_none();
+    if is_empty {
+        return Err(Error::Rejected(String::from("No book settings to update")));
+    }
+
+    run_bulk_operation(
+        conn,
+        book_ids,
+        "Cannot update book settings",
+        |conn, book_id| {
+            diesel::insert_into(schema::book_settings::table)
+                .values((
+                    schema::book_settings::id.eq(Uuid::new_v4().to_string()),
+                    schema::book_settings::book_id.eq(book_id),
+                ))
+                .on_conflict(schema::book_settings::book_id)
+                .do_nothing()
+                .execute(conn)?;
+            diesel::update(
+                schema::book_settings::table.filter(schema::book_settings::book_id.eq(book_id)),
+            )
+            .set(&settings)
+            .execute(conn)?;
+
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,174 @@
+use crate::{models, schema, Error, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use uuid::Uuid;
+
+pub fn get_reader_themes(conn: &mut SqliteConnection) -> Result<Vec<models::ReaderTheme>> {
+    let themes = schema::reader_theme::table
+        .select(models::ReaderTheme::as_select())
+        .get_results(conn)?;
+
+    Ok(themes)
+}
+
+pub fn get_reader_themes_by_ids(
+    conn: &mut SqliteConnection,
+    ids: Vec<String>,
+) -> Result<Vec<models::ReaderTheme>> {
+    let themes = schema::reader_theme::table
+        .filter(schema::reader_theme::id.eq_any(ids))
+        .select(models::ReaderTheme::as_select())
+        .load(conn)?;
+
+    Ok(themes)
+}
+
+// Built-in themes ship with the app, so they can be picked and copied but not changed
+fn is_built_in(conn: &mut SqliteConnection, id: &str) -> Result<Option<bool>> {
+    let built_in = schema::reader_theme::table
+        .find(id)
+        .select(schema::reader_theme::built_in)
+        .get_result(conn)
+        .optional()?;
+
+    Ok(built_in)
+}
+
+fn built_in_theme_error() -> Error {
+    Error::Rejected(String::from("Cannot change built-in reader theme"))
+}
+
+pub fn add_reader_theme(
+    conn: &mut SqliteConnection,
+    new_reader_theme: models::ReaderTheme,
+) -> Result<()> {
+    let new_reader_theme = models::ReaderTheme {
+        built_in: false,
+        ..new_reader_theme
+    };
+
+    conn.transaction(|conn| {
+        if is_built_in(conn, &new_reader_theme.id)? == Some(true) {
+            return Err(built_in_theme_error());
+        }
+
+        diesel::insert_into(schema::reader_theme::table)
+            .values(&new_reader_theme)
+            .on_conflict(schema::reader_theme::id)
+            .do_update()
+            .set((
+                schema::reader_theme::name.eq(&new_reader_theme.name),
+                schema::reader_theme::background_color.eq(&new_reader_theme.background_color),
+                schema::reader_theme::color.eq(&new_reader_theme.color),
+                schema::reader_theme::link_color.eq(&new_reader_theme.link_color),
+                schema::reader_theme::primary_color.eq(&new_reader_theme.primary_color),
+                schema::reader_theme::image_blend_mode.eq(&new_reader_theme.image_blend_mode),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn add_reader_themes(
+    conn: &mut SqliteConnection,
+    themes: &[models::ReaderTheme],
+) -> Result<()> {
+    let themes: Vec<_> = themes
+        .iter()
+        .map(|theme| models::ReaderTheme {
+            built_in: false,
+            ..theme.clone()
+        })
+        .collect();
+
+    diesel::insert_into(schema::reader_theme::table)
+        .values(&themes)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_reader_theme(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    conn.transaction(|conn| {
+        let built_in: bool = schema::reader_theme::table
+            .find(id)
+            .select(schema::reader_theme::built_in)
+            .get_result(conn)?;
+        if built_in {
+            return Err(Error::Rejected(String::from(
+                "Cannot delete built-in reader theme",
+            )));
+        }
+
+        diesel::update(
+            schema::book_settings::table.filter(schema::book_settings::reader_theme_id.eq(id)),
+        )
+        .set(schema::book_settings::reader_theme_id.eq(None::<String>))
+        .execute(conn)?;
+        diesel::update(
+            schema::reader_settings_default::table
+                .filter(schema::reader_settings_default::reader_theme_id.eq(id)),
+        )
+        .set(schema::reader_settings_default::reader_theme_id.eq(None::<String>))
+        .execute(conn)?;
+        diesel::delete(schema::reader_theme::table.filter(schema::reader_theme::id.eq(id)))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn update_reader_theme(
+    conn: &mut SqliteConnection,
+    reader_theme: models::ReaderTheme,
+) -> Result<()> {
+    conn.transaction(|conn| {
+        match is_built_in(conn, &reader_theme.id)? {
+            Some(true) => return Err(built_in_theme_error()),
+            Some(false) => {}
+            None => return Err(Error::NotFound),
+        }
+
+        diesel::update(
+            schema::reader_theme::table.filter(schema::reader_theme::id.eq(&reader_theme.id)),
+        )
+        .set((
+            schema::reader_theme::name.eq(&reader_theme.name),
+            schema::reader_theme::background_color.eq(&reader_theme.background_color),
+            schema::reader_theme::color.eq(&reader_theme.color),
+            schema::reader_theme::link_color.eq(&reader_theme.link_color),
+            schema::reader_theme::primary_color.eq(&reader_theme.primary_color),
+            schema::reader_theme::image_blend_mode.eq(&reader_theme.image_blend_mode),
+        ))
+        .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn set_book_reader_theme(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    reader_theme_id: Option<&str>,
+) -> Result<()> {
+    diesel::insert_into(schema::book_settings::table)
+        .values((
+            schema::book_settings::id.eq(Uuid::new_v4().to_string()),
+            schema::book_settings::book_id.eq(book_id),
+            schema::book_settings::reader_theme_id.eq(reader_theme_id),
+        ))
+        .on_conflict(schema::book_settings::book_id)
+        .do_update()
+        .set((
+            schema::book_settings::reader_theme_id.eq(reader_theme_id),
+            schema::book_settings::background_color.eq(None::<String>),
+            schema::book_settings::color.eq(None::<String>),
+            schema::book_settings::link_color.eq(None::<String>),
+            schema::book_settings::primary_color.eq(None::<String>),
+            schema::book_settings::image_blend_mode.eq(None::<String>),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
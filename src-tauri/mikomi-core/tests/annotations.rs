@@ -0,0 +1,79 @@
+mod common;
+
+use common::{add_book, memory_connection};
+use diesel::prelude::*;
+use mikomi_core::{annotations, models, schema, Error};
+
+fn new_highlight(id: &str, highlight_color_id: Option<&str>) -> models::Highlight {
+    models::Highlight {
+        id: id.to_string(),
+        book_id: String::from("a"),
+        date_added: 0,
+        note: String::new(),
+        start_container: String::from("p"),
+        start_offset: 0,
+        end_container: String::from("p"),
+        end_offset: 4,
+        color: String::from("#ffff00"),
+        highlight_color_id: highlight_color_id.map(|v| v.to_string()),
+    }
+}
+
+fn add_highlight_color(conn: &mut SqliteConnection, id: &str) {
+    annotations::add_highlight_color(
+        conn,
+        models::HighlightColor {
+            id: id.to_string(),
+            name: format!("Color {id}"),
+            color: String::from("#ffff00"),
+            sort_order: Some(1),
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn it_rejects_highlights_with_an_unknown_color() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    add_highlight_color(&mut conn, "yellow");
+
+    let res = annotations::add_highlight(&mut conn, new_highlight("h", Some("missing")));
+    assert!(matches!(res, Err(Error::Rejected(_))));
+
+    annotations::add_highlight(&mut conn, new_highlight("h", Some("yellow"))).unwrap();
+    let res = annotations::update_highlight(&mut conn, "h", "", "#ffff00", Some("missing"));
+    assert!(matches!(res, Err(Error::Rejected(_))));
+}
+
+#[test]
+fn it_groups_highlights_of_an_unknown_color_with_the_unlabelled_ones() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    add_highlight_color(&mut conn, "yellow");
+    annotations::add_highlight(&mut conn, new_highlight("labelled", Some("yellow"))).unwrap();
+    annotations::add_highlight(&mut conn, new_highlight("unlabelled", None)).unwrap();
+    // A row written without the foreign key checks, such as by an older version of the app
+    diesel::sql_query("PRAGMA foreign_keys = OFF")
+        .execute(&mut conn)
+        .unwrap();
+    diesel::insert_into(schema::highlight::table)
+        .values(new_highlight("orphan", Some("missing")))
+        .execute(&mut conn)
+        .unwrap();
+
+    let groups = annotations::get_highlights_grouped_by_color(&mut conn, Some("a")).unwrap();
+
+    let ids =
+        |i: usize| -> Vec<&str> { groups[i].highlights.iter().map(|v| v.id.as_str()).collect() };
+    let yellow = groups
+        .iter()
+        .position(|v| v.highlight_color.as_ref().map(|c| c.id.as_str()) == Some("yellow"))
+        .unwrap();
+    assert_eq!(ids(yellow), vec!["labelled"]);
+    let unlabelled = groups
+        .iter()
+        .position(|v| v.highlight_color.is_none())
+        .unwrap();
+    assert_eq!(ids(unlabelled), vec!["unlabelled", "orphan"]);
+}
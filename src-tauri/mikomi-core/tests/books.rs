@@ -0,0 +1,281 @@
+mod common;
+
+use common::{add_book, add_collection, memory_connection};
+use diesel::prelude::*;
+use mikomi_core::{annotations, books, collections, journal, models, schema, settings};
+use std::path::Path;
+
+#[test]
+fn it_adds_a_book_with_its_authors() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &["Ada Writer", "Bo Editor"]);
+
+    let books = books::get_books(&mut conn, Path::new("covers")).unwrap();
+    assert_eq!(books.len(), 1);
+    assert_eq!(books[0].book.title, "Book a");
+    let mut authors: Vec<&str> = books[0].authors.iter().map(|v| v.name.as_str()).collect();
+    authors.sort();
+    assert_eq!(authors, vec!["Ada Writer", "Bo Editor"]);
+    assert_eq!(
+        books[0].cover,
+        Some(String::from(
+            Path::new("covers").join("a").to_string_lossy()
+        ))
+    );
+}
+
+#[test]
+fn it_shares_authors_between_books() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &["Ada Writer"]);
+    add_book(&mut conn, "b", &["Ada Writer", "Bo Editor"]);
+
+    let authors: i64 = schema::author::table.count().get_result(&mut conn).unwrap();
+    assert_eq!(authors, 2);
+
+    let primary: Vec<(String, bool)> = schema::book_author_link::table
+        .inner_join(schema::author::table)
+        .filter(schema::book_author_link::book_id.eq("b"))
+        .select((
+            schema::author::name,
+            schema::book_author_link::primary_creator,
+        ))
+        .order(schema::author::name)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(
+        primary,
+        vec![
+            (String::from("Ada Writer"), true),
+            (String::from("Bo Editor"), false)
+        ]
+    );
+}
+
+#[test]
+fn get_books_returns_the_collections_of_each_book() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    add_book(&mut conn, "b", &[]);
+    add_collection(&mut conn, "c", 1);
+    collections::insert_book_collection_link(&mut conn, "a", "c").unwrap();
+
+    let books = books::get_books(&mut conn, Path::new("covers")).unwrap();
+    let collections_of = |id: &str| -> Vec<String> {
+        books
+            .iter()
+            .find(|v| v.book.id == id)
+            .unwrap()
+            .collections
+            .iter()
+            .map(|v| v.id.clone())
+            .collect()
+    };
+    assert_eq!(collections_of("a"), vec![String::from("c")]);
+    assert!(collections_of("b").is_empty());
+}
+
+#[test]
+fn remove_book_moves_the_book_to_the_trash() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &["Ada Writer"]);
+    add_book(&mut conn, "b", &["Ada Writer"]);
+    add_collection(&mut conn, "c", 1);
+    collections::insert_book_collection_link(&mut conn, "a", "c").unwrap();
+
+    books::remove_book(&mut conn, "a").unwrap();
+
+    let books = books::get_books(&mut conn, Path::new("covers")).unwrap();
+    assert_eq!(
+        books
+            .iter()
+            .map(|v| v.book.id.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["b"]
+    );
+    let trashed = books::get_trashed_books(&mut conn, Path::new("covers")).unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].book.id, "a");
+    assert!(trashed[0].book.deleted_at.is_some());
+
+    // The book keeps its links so it can be restored as it was
+    let links: i64 = schema::book_collection_link::table
+        .filter(schema::book_collection_link::book_id.eq("a"))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(links, 1);
+
+    books::restore_books(&mut conn, vec![String::from("a")]).unwrap();
+    assert_eq!(
+        books::get_books(&mut conn, Path::new("covers"))
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn remove_book_can_be_undone() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+
+    books::remove_book(&mut conn, "a").unwrap();
+    assert!(books::get_books(&mut conn, Path::new("covers"))
+        .unwrap()
+        .is_empty());
+
+    let undone = journal::undo_last(&mut conn).unwrap();
+    assert_eq!(undone, Some(String::from("Remove book")));
+    assert_eq!(
+        books::get_books(&mut conn, Path::new("covers"))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn bulk_remove_books_can_be_undone() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    add_book(&mut conn, "b", &[]);
+
+    let results =
+        books::bulk_remove_books(&mut conn, vec![String::from("a"), String::from("missing")])
+            .unwrap();
+    assert!(results[0].success);
+    assert!(!results[1].success);
+    assert_eq!(
+        books::get_books(&mut conn, Path::new("covers"))
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let undone = journal::undo_last(&mut conn).unwrap();
+    assert_eq!(undone, Some(String::from("Remove books")));
+    assert_eq!(
+        books::get_books(&mut conn, Path::new("covers"))
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn remove_book_permanently_deletes_everything_that_references_the_book() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &["Ada Writer"]);
+    add_book(&mut conn, "b", &["Ada Writer"]);
+    add_collection(&mut conn, "c", 1);
+    for book_id in ["a", "b"] {
+        collections::insert_book_collection_link(&mut conn, book_id, "c").unwrap();
+        annotations::add_bookmark(
+            &mut conn,
+            models::Bookmark {
+                id: format!("bookmark-{book_id}"),
+                book_id: book_id.to_string(),
+                display_text: String::from("Chapter 1"),
+                date_added: 0,
+                css_selector: String::from("p"),
+            },
+        )
+        .unwrap();
+        annotations::add_highlight(
+            &mut conn,
+            models::Highlight {
+                id: format!("highlight-{book_id}"),
+                book_id: book_id.to_string(),
+                date_added: 0,
+                note: String::new(),
+                start_container: String::from("p"),
+                start_offset: 0,
+                end_container: String::from("p"),
+                end_offset: 4,
+                color: String::from("#ffff00"),
+                highlight_color_id: None,
+            },
+        )
+        .unwrap();
+    }
+
+    books::remove_book(&mut conn, "a").unwrap();
+    let path = books::remove_book_permanently(&mut conn, "a").unwrap();
+    assert_eq!(path, "/library/a.epub");
+
+    let books: Vec<String> = schema::book::table
+        .select(schema::book::id)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(books, vec![String::from("b")]);
+    let linked_books: Vec<String> = schema::book_collection_link::table
+        .select(schema::book_collection_link::book_id)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(linked_books, vec![String::from("b")]);
+    let authored_books: Vec<String> = schema::book_author_link::table
+        .select(schema::book_author_link::book_id)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(authored_books, vec![String::from("b")]);
+    let bookmarks: Vec<String> = schema::bookmark::table
+        .select(schema::bookmark::book_id)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(bookmarks, vec![String::from("b")]);
+    let highlights: Vec<String> = schema::highlight::table
+        .select(schema::highlight::book_id)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(highlights, vec![String::from("b")]);
+
+    // Authors and collections outlive the books that belong to them
+    let authors: i64 = schema::author::table.count().get_result(&mut conn).unwrap();
+    assert_eq!(authors, 1);
+    assert_eq!(collections::get_collections(&mut conn).unwrap().len(), 1);
+}
+
+#[test]
+fn purge_trashed_books_only_deletes_books_in_the_trash() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    add_book(&mut conn, "b", &[]);
+    books::remove_book(&mut conn, "a").unwrap();
+
+    let purged = books::purge_trashed_books(&mut conn, None).unwrap();
+    assert_eq!(
+        purged,
+        vec![(String::from("a"), String::from("/library/a.epub"))]
+    );
+    assert!(books::get_trashed_books(&mut conn, Path::new("covers"))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        books::get_books(&mut conn, Path::new("covers"))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn trash_retention_is_capped_and_never_overflows() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    books::remove_book(&mut conn, "a").unwrap();
+
+    assert!(books::set_trash_retention_days(&mut conn, -1).is_err());
+    assert!(books::set_trash_retention_days(&mut conn, i32::MAX).is_err());
+    books::set_trash_retention_days(&mut conn, books::MAX_TRASH_RETENTION_DAYS).unwrap();
+    assert!(books::purge_expired_trash(&mut conn).unwrap().is_empty());
+
+    // Values written before the cap keep every book instead of overflowing
+    settings::set_app_setting(
+        &mut conn,
+        books::TRASH_RETENTION_DAYS_SETTING_KEY,
+        Some(&i32::MAX.to_string()),
+    )
+    .unwrap();
+    assert!(books::purge_expired_trash(&mut conn).unwrap().is_empty());
+}
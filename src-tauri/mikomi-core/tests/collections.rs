@@ -0,0 +1,318 @@
+mod common;
+
+use common::{add_book, add_collection, memory_connection};
+use diesel::prelude::*;
+use mikomi_core::collections::{self, CollectionIdWithSortOrder};
+use mikomi_core::{journal, models, schema};
+
+fn collection_ids(conn: &mut SqliteConnection) -> Vec<String> {
+    collections::get_collections(conn)
+        .unwrap()
+        .into_iter()
+        .map(|v| v.id)
+        .collect()
+}
+
+fn links_of_book(conn: &mut SqliteConnection, book_id: &str) -> Vec<models::BookCollectionLink> {
+    schema::book_collection_link::table
+        .filter(schema::book_collection_link::book_id.eq(book_id))
+        .select(models::BookCollectionLink::as_select())
+        .order(schema::book_collection_link::collection_id)
+        .load(conn)
+        .unwrap()
+}
+
+fn smart_collection(id: &str, rules: Vec<models::SmartCollectionRule>) -> models::Collection {
+    models::Collection {
+        id: id.to_string(),
+        name: format!("Smart collection {id}"),
+        sort_order: Some(1),
+        smart_filter: Some(models::SmartCollectionFilter {
+            match_all: true,
+            rules,
+        }),
+        parent_id: None,
+    }
+}
+
+fn link(book_id: &str, collection_id: &str, sort_order: i32) -> models::BookCollectionLink {
+    models::BookCollectionLink {
+        book_id: book_id.to_string(),
+        collection_id: collection_id.to_string(),
+        sort_order: Some(sort_order),
+    }
+}
+
+#[test]
+fn reorder_collections_changes_the_order_of_the_collections() {
+    let mut conn = memory_connection();
+    add_collection(&mut conn, "a", 1);
+    add_collection(&mut conn, "b", 2);
+    add_collection(&mut conn, "c", 3);
+    assert_eq!(collection_ids(&mut conn), vec!["a", "b", "c"]);
+
+    collections::reorder_collections(
+        &mut conn,
+        vec![
+            CollectionIdWithSortOrder {
+                id: String::from("c"),
+                sort_order: 1,
+            },
+            CollectionIdWithSortOrder {
+                id: String::from("a"),
+                sort_order: 2,
+            },
+            CollectionIdWithSortOrder {
+                id: String::from("b"),
+                sort_order: 3,
+            },
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(collection_ids(&mut conn), vec!["c", "a", "b"]);
+}
+
+#[test]
+fn reorder_books_in_collection_changes_the_order_of_the_books() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "x", &[]);
+    add_book(&mut conn, "y", &[]);
+    add_collection(&mut conn, "a", 1);
+    collections::insert_book_collection_link(&mut conn, "x", "a").unwrap();
+    collections::insert_book_collection_link(&mut conn, "y", "a").unwrap();
+
+    collections::reorder_books_in_collection(&mut conn, vec![link("x", "a", 2), link("y", "a", 1)])
+        .unwrap();
+
+    let collection = collections::get_collection(&mut conn, "a").unwrap();
+    let books: Vec<String> = collections::get_collection_books(&mut conn, &collection)
+        .unwrap()
+        .into_iter()
+        .map(|v| v.id)
+        .collect();
+    assert_eq!(books, vec!["y", "x"]);
+}
+
+#[test]
+fn insert_book_collection_link_appends_the_book_to_the_collection() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "x", &[]);
+    add_book(&mut conn, "y", &[]);
+    add_collection(&mut conn, "a", 1);
+
+    let first = collections::insert_book_collection_link(&mut conn, "x", "a").unwrap();
+    let second = collections::insert_book_collection_link(&mut conn, "y", "a").unwrap();
+
+    assert_eq!(first, link("x", "a", 1));
+    assert_eq!(second, link("y", "a", 2));
+}
+
+#[test]
+fn add_book_to_collections_only_changes_the_links_that_differ() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "x", &[]);
+    add_book(&mut conn, "y", &[]);
+    for (id, sort_order) in [("a", 1), ("b", 2), ("c", 3)] {
+        add_collection(&mut conn, id, sort_order);
+    }
+    collections::insert_book_collection_link(&mut conn, "y", "b").unwrap();
+    collections::add_book_to_collections(
+        &mut conn,
+        "x",
+        vec![String::from("a"), String::from("b")],
+    )
+    .unwrap();
+    assert_eq!(
+        links_of_book(&mut conn, "x"),
+        vec![link("x", "a", 1), link("x", "b", 2)]
+    );
+
+    collections::add_book_to_collections(
+        &mut conn,
+        "x",
+        vec![String::from("b"), String::from("c")],
+    )
+    .unwrap();
+
+    // The link to `b` is kept where it was instead of being appended again
+    assert_eq!(
+        links_of_book(&mut conn, "x"),
+        vec![link("x", "b", 2), link("x", "c", 1)]
+    );
+    assert_eq!(links_of_book(&mut conn, "y"), vec![link("y", "b", 1)]);
+}
+
+#[test]
+fn add_book_to_collections_without_changes_records_nothing() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "x", &[]);
+    add_collection(&mut conn, "a", 1);
+    collections::add_book_to_collections(&mut conn, "x", vec![String::from("a")]).unwrap();
+    assert_eq!(journal::get_journal_entries(&mut conn).unwrap().len(), 1);
+
+    collections::add_book_to_collections(&mut conn, "x", vec![String::from("a")]).unwrap();
+
+    assert_eq!(links_of_book(&mut conn, "x"), vec![link("x", "a", 1)]);
+    assert_eq!(journal::get_journal_entries(&mut conn).unwrap().len(), 1);
+}
+
+#[test]
+fn add_book_to_collections_can_be_undone_and_redone() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "x", &[]);
+    add_collection(&mut conn, "a", 1);
+    add_collection(&mut conn, "b", 2);
+    collections::add_book_to_collections(&mut conn, "x", vec![String::from("a")]).unwrap();
+    collections::add_book_to_collections(&mut conn, "x", vec![String::from("b")]).unwrap();
+
+    journal::undo_last(&mut conn).unwrap();
+    assert_eq!(links_of_book(&mut conn, "x"), vec![link("x", "a", 1)]);
+
+    journal::redo(&mut conn).unwrap();
+    assert_eq!(links_of_book(&mut conn, "x"), vec![link("x", "b", 1)]);
+}
+
+#[test]
+fn smart_collections_reject_out_of_range_days() {
+    let mut conn = memory_connection();
+
+    for days in [-1, collections::MAX_SMART_FILTER_DAYS + 1, i32::MAX] {
+        let collection = smart_collection(
+            "smart",
+            vec![models::SmartCollectionRule::AddedWithinDays { days }],
+        );
+        assert!(matches!(
+            collections::add_collection(&mut conn, collection),
+            Err(mikomi_core::Error::Rejected(_))
+        ));
+    }
+
+    let collection = smart_collection(
+        "smart",
+        vec![models::SmartCollectionRule::LastReadWithinDays {
+            days: collections::MAX_SMART_FILTER_DAYS,
+        }],
+    );
+    collections::add_collection(&mut conn, collection).unwrap();
+}
+
+#[test]
+fn smart_collection_rules_with_large_days_do_not_overflow() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "book", &[]);
+
+    let filter = models::SmartCollectionFilter {
+        match_all: true,
+        rules: vec![models::SmartCollectionRule::AddedWithinDays { days: i32::MAX }],
+    };
+    let books = collections::get_smart_collection_books(&mut conn, &filter).unwrap();
+
+    assert_eq!(books.len(), 1);
+}
+
+#[test]
+fn books_cannot_be_added_to_smart_collections() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "book", &[]);
+    add_collection(&mut conn, "plain", 1);
+    let collection = smart_collection(
+        "smart",
+        vec![models::SmartCollectionRule::TitleContains {
+            text: String::from("Book"),
+        }],
+    );
+    collections::add_collection(&mut conn, collection).unwrap();
+
+    let res = collections::add_book_to_collections(
+        &mut conn,
+        "book",
+        vec![String::from("plain"), String::from("smart")],
+    );
+    assert!(matches!(res, Err(mikomi_core::Error::Rejected(_))));
+    let res = collections::bulk_add_books_to_collections(
+        &mut conn,
+        vec![String::from("book")],
+        vec![String::from("smart")],
+    );
+    assert!(matches!(res, Err(mikomi_core::Error::Rejected(_))));
+
+    assert!(links_of_book(&mut conn, "book").is_empty());
+}
+
+fn nested_collection(id: &str, parent_id: Option<&str>) -> models::Collection {
+    models::Collection {
+        id: id.to_string(),
+        name: format!("Collection {id}"),
+        sort_order: Some(1),
+        smart_filter: None,
+        parent_id: parent_id.map(String::from),
+    }
+}
+
+#[test]
+fn collections_cannot_be_nested_under_missing_collections() {
+    let mut conn = memory_connection();
+    add_collection(&mut conn, "a", 1);
+
+    let moved = collections::move_collection(&mut conn, "a", Some("missing"), None);
+    let added = collections::add_collection(&mut conn, nested_collection("b", Some("missing")));
+
+    assert!(matches!(moved, Err(mikomi_core::Error::Rejected(_))));
+    assert!(matches!(added, Err(mikomi_core::Error::Rejected(_))));
+    assert!(matches!(
+        collections::move_collection(&mut conn, "missing", Some("a"), None),
+        Err(mikomi_core::Error::NotFound)
+    ));
+    assert_eq!(collection_ids(&mut conn), vec!["a"]);
+}
+
+#[test]
+fn collections_cannot_be_nested_under_their_descendants() {
+    let mut conn = memory_connection();
+    collections::add_collection(&mut conn, nested_collection("a", None)).unwrap();
+    collections::add_collection(&mut conn, nested_collection("b", Some("a"))).unwrap();
+    collections::add_collection(&mut conn, nested_collection("c", Some("b"))).unwrap();
+
+    let moved = collections::move_collection(&mut conn, "a", Some("c"), None);
+    let added = collections::add_collection(&mut conn, nested_collection("a", Some("c")));
+    let own_parent = collections::add_collection(&mut conn, nested_collection("b", Some("b")));
+
+    assert!(matches!(moved, Err(mikomi_core::Error::Rejected(_))));
+    assert!(matches!(added, Err(mikomi_core::Error::Rejected(_))));
+    assert!(matches!(own_parent, Err(mikomi_core::Error::Rejected(_))));
+    assert_eq!(
+        collections::get_collection(&mut conn, "a")
+            .unwrap()
+            .parent_id,
+        None
+    );
+    assert_eq!(
+        collections::get_collection(&mut conn, "b")
+            .unwrap()
+            .parent_id,
+        Some(String::from("a"))
+    );
+}
+
+#[test]
+fn add_collection_can_clear_the_parent_and_the_smart_filter() {
+    let mut conn = memory_connection();
+    collections::add_collection(&mut conn, nested_collection("a", None)).unwrap();
+    let smart = models::Collection {
+        parent_id: Some(String::from("a")),
+        ..smart_collection(
+            "b",
+            vec![models::SmartCollectionRule::TitleContains {
+                text: String::from("Book"),
+            }],
+        )
+    };
+    collections::add_collection(&mut conn, smart).unwrap();
+
+    collections::add_collection(&mut conn, nested_collection("b", None)).unwrap();
+
+    let collection = collections::get_collection(&mut conn, "b").unwrap();
+    assert_eq!(collection.parent_id, None);
+    assert!(collection.smart_filter.is_none());
+}
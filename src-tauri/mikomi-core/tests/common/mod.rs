@@ -0,0 +1,52 @@
+#![allow(dead_code)]
+
+use diesel::SqliteConnection;
+use mikomi_core::{books, current_timestamp, models};
+
+// Every test gets its own empty library, so the tests never touch `mikomi-data` and can run
+// in parallel
+pub fn memory_connection() -> SqliteConnection {
+    let mut conn = mikomi_core::establish_connection(":memory:").unwrap();
+    mikomi_core::run_migrations(&mut conn).unwrap();
+    conn
+}
+
+pub fn new_book(id: &str, title: &str) -> models::Book {
+    models::Book {
+        id: id.to_string(),
+        title: title.to_string(),
+        path: format!("/library/{id}.epub"),
+        last_read: None,
+        date_added: current_timestamp(),
+        reading_status: models::ReadingStatus::PlanToRead,
+        language: None,
+        last_modified: None,
+        identifier: None,
+        published_date: None,
+        description: None,
+        publisher: None,
+        page_progression_direction: None,
+        deleted_at: None,
+    }
+}
+
+pub fn add_book(conn: &mut SqliteConnection, id: &str, authors: &[&str]) -> models::Book {
+    let book = new_book(id, &format!("Book {id}"));
+    let authors: Vec<String> = authors.iter().map(|v| v.to_string()).collect();
+    books::add_book(conn, &book, &authors).unwrap();
+    book
+}
+
+pub fn add_collection(conn: &mut SqliteConnection, id: &str, sort_order: i32) {
+    mikomi_core::collections::add_collection(
+        conn,
+        models::Collection {
+            id: id.to_string(),
+            name: format!("Collection {id}"),
+            sort_order: Some(sort_order),
+            smart_filter: None,
+            parent_id: None,
+        },
+    )
+    .unwrap();
+}
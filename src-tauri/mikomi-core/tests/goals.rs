@@ -0,0 +1,45 @@
+mod common;
+
+use common::memory_connection;
+use mikomi_core::{goals, models, Error};
+
+fn books_per_year_goal(id: &str, target: i32, year: Option<i32>) -> models::ReadingGoal {
+    models::ReadingGoal {
+        id: id.to_string(),
+        goal_type: models::ReadingGoalType::BooksPerYear,
+        target,
+        year,
+    }
+}
+
+#[test]
+fn adding_a_goal_replaces_the_goal_of_the_same_year() {
+    let mut conn = memory_connection();
+
+    goals::add_reading_goal(&mut conn, books_per_year_goal("old", 10, Some(2026))).unwrap();
+    goals::add_reading_goal(&mut conn, books_per_year_goal("other", 5, Some(2025))).unwrap();
+    goals::add_reading_goal(&mut conn, books_per_year_goal("new", 20, Some(2026))).unwrap();
+
+    let ids: Vec<String> = goals::get_reading_goals(&mut conn)
+        .unwrap()
+        .into_iter()
+        .map(|g| g.id)
+        .collect();
+    assert_eq!(ids, vec!["other", "new"]);
+    let progress = goals::get_reading_goal_progress(&mut conn, 2026).unwrap();
+    assert_eq!(progress.books_per_year_goal, Some(20));
+    assert_eq!(progress.books_completed, 0);
+}
+
+#[test]
+fn invalid_goals_are_rejected() {
+    let mut conn = memory_connection();
+
+    let without_target =
+        goals::add_reading_goal(&mut conn, books_per_year_goal("a", 0, Some(2026)));
+    let without_year = goals::add_reading_goal(&mut conn, books_per_year_goal("b", 10, None));
+
+    assert!(matches!(without_target, Err(Error::Rejected(_))));
+    assert!(matches!(without_year, Err(Error::Rejected(_))));
+    assert!(goals::get_reading_goals(&mut conn).unwrap().is_empty());
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::memory_connection;
+use mikomi_core::books;
+use mikomi_core::import::import_book;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+// A temporary covers folder that is removed with everything in it at the end of the test
+struct CoversDir(PathBuf);
+
+impl CoversDir {
+    fn new() -> Self {
+        CoversDir(env::temp_dir().join(format!("mikomi-covers-{}", Uuid::new_v4())))
+    }
+
+    fn file_count(&self) -> usize {
+        fs::read_dir(&self.0).map(|v| v.count()).unwrap_or(0)
+    }
+}
+
+impl Drop for CoversDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    String::from(path.to_string_lossy())
+}
+
+#[test]
+fn it_imports_an_epub2_book() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    let book = import_book(&mut conn, fixture("epub2.epub"), &covers_dir.0).unwrap();
+
+    assert_eq!(book.title, "An EPUB2 Book");
+    assert_eq!(book.path, fixture("epub2.epub"));
+    assert_eq!(book.language, Some(String::from("en")));
+    assert_eq!(book.publisher, Some(String::from("Mikomi Fixtures")));
+    assert_eq!(
+        book.identifier,
+        Some(String::from("urn:mikomi:fixture:epub2"))
+    );
+    assert!(covers_dir.0.join(&book.id).is_file());
+
+    let books = books::get_books(&mut conn, &covers_dir.0).unwrap();
+    assert_eq!(books.len(), 1);
+    assert_eq!(books[0].book.id, book.id);
+    assert_eq!(books[0].authors.len(), 1);
+    assert_eq!(books[0].authors[0].name, "Ada Writer");
+}
+
+#[test]
+fn it_imports_an_epub3_book() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    let book = import_book(&mut conn, fixture("epub3.epub"), &covers_dir.0).unwrap();
+
+    assert_eq!(book.title, "An EPUB3 Book");
+    assert!(covers_dir.0.join(&book.id).is_file());
+    assert_eq!(books::get_books(&mut conn, &covers_dir.0).unwrap().len(), 1);
+}
+
+#[test]
+fn it_imports_every_author_of_a_book() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    import_book(&mut conn, fixture("multiple-authors.epub"), &covers_dir.0).unwrap();
+
+    let books = books::get_books(&mut conn, &covers_dir.0).unwrap();
+    let mut authors: Vec<&str> = books[0].authors.iter().map(|v| v.name.as_str()).collect();
+    authors.sort();
+    assert_eq!(authors, vec!["Ada Writer", "Bo Editor", "Cy Translator"]);
+}
+
+#[test]
+fn it_keeps_the_page_progression_direction_of_a_book() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    let book = import_book(&mut conn, fixture("rtl.epub"), &covers_dir.0).unwrap();
+
+    assert_eq!(book.title, "كتاب من اليمين إلى اليسار");
+    assert_eq!(book.page_progression_direction, Some(String::from("rtl")));
+    assert_eq!(book.language, Some(String::from("ar")));
+}
+
+#[test]
+fn it_rejects_a_book_without_a_cover() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    let res = import_book(&mut conn, fixture("no-cover.epub"), &covers_dir.0);
+
+    assert_eq!(
+        res.err().map(|e| e.to_string()),
+        Some(String::from("No cover found in epub"))
+    );
+    assert!(books::get_books(&mut conn, &covers_dir.0)
+        .unwrap()
+        .is_empty());
+    assert_eq!(covers_dir.file_count(), 0);
+}
+
+#[test]
+fn it_rejects_a_malformed_epub() {
+    let mut conn = memory_connection();
+    let covers_dir = CoversDir::new();
+
+    let res = import_book(&mut conn, fixture("malformed.epub"), &covers_dir.0);
+
+    assert_eq!(
+        res.err().map(|e| e.to_string()),
+        Some(String::from("Cannot read epub file"))
+    );
+    assert!(books::get_books(&mut conn, &covers_dir.0)
+        .unwrap()
+        .is_empty());
+    assert_eq!(covers_dir.file_count(), 0);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{add_book, add_collection, memory_connection};
+use diesel::prelude::*;
+use mikomi_core::{collections, journal, schema};
+
+fn change_book_collections(conn: &mut SqliteConnection) {
+    add_book(conn, "book", &[]);
+    add_collection(conn, "collection", 1);
+    collections::add_book_to_collections(conn, "book", vec![String::from("collection")]).unwrap();
+}
+
+fn journal_entry_count(conn: &mut SqliteConnection) -> usize {
+    journal::get_journal_entries(conn).unwrap().len()
+}
+
+#[test]
+fn unreadable_entries_are_dropped_instead_of_blocking_undo() {
+    let mut conn = memory_connection();
+    change_book_collections(&mut conn);
+    diesel::insert_into(schema::journal_entry::table)
+        .values((
+            schema::journal_entry::id.eq("broken"),
+            schema::journal_entry::sequence.eq(i32::MAX),
+            schema::journal_entry::description.eq("Broken"),
+            schema::journal_entry::changes.eq("not json"),
+            schema::journal_entry::undone.eq(false),
+            schema::journal_entry::date_added.eq(0),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+    assert!(matches!(
+        journal::undo_last(&mut conn),
+        Err(mikomi_core::Error::Rejected(_))
+    ));
+    assert_eq!(journal_entry_count(&mut conn), 1);
+    assert_eq!(
+        journal::undo_last(&mut conn).unwrap().as_deref(),
+        Some("Change book collections")
+    );
+}
+
+#[test]
+fn entries_are_kept_when_replaying_fails_for_another_reason() {
+    let mut conn = memory_connection();
+    change_book_collections(&mut conn);
+    diesel::sql_query(
+        "CREATE TEMP TRIGGER fail_unlink BEFORE DELETE ON book_collection_link
+         BEGIN SELECT RAISE(ABORT, 'database is busy'); END",
+    )
+    .execute(&mut conn)
+    .unwrap();
+
+    assert!(matches!(
+        journal::undo_last(&mut conn),
+        Err(mikomi_core::Error::Database(_))
+    ));
+    assert_eq!(journal_entry_count(&mut conn), 1);
+
+    diesel::sql_query("DROP TRIGGER fail_unlink")
+        .execute(&mut conn)
+        .unwrap();
+    assert!(journal::undo_last(&mut conn).unwrap().is_some());
+}
+
+#[test]
+fn entries_whose_rows_are_gone_are_dropped() {
+    let mut conn = memory_connection();
+    change_book_collections(&mut conn);
+    journal::undo_last(&mut conn).unwrap();
+    diesel::delete(schema::collection::table)
+        .execute(&mut conn)
+        .unwrap();
+
+    assert!(matches!(
+        journal::redo(&mut conn),
+        Err(mikomi_core::Error::Rejected(_))
+    ));
+    assert_eq!(journal_entry_count(&mut conn), 0);
+}
@@ -0,0 +1,68 @@
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::SqliteConnection;
+use diesel_migrations::MigrationHarness;
+use mikomi_core::MIGRATIONS;
+
+const READING_STATUS_EVENT_MIGRATION: &str = "2026-10-19-160000";
+
+// Returns a library that has every migration before `version` applied
+fn connection_before(version: &str) -> SqliteConnection {
+    let mut conn = mikomi_core::establish_connection(":memory:").unwrap();
+    let migrations = conn.pending_migrations(MIGRATIONS).unwrap();
+    for migration in migrations {
+        if migration.name().version().to_string().as_str() >= version {
+            break;
+        }
+        conn.run_migration(&migration).unwrap();
+    }
+    conn
+}
+
+fn insert_book_with_reading_status(conn: &mut SqliteConnection, id: &str, reading_status: &str) {
+    diesel::sql_query(
+        "INSERT INTO book (id, title, path, date_added, reading_status) VALUES (?, ?, ?, 0, ?)",
+    )
+    .bind::<Text, _>(id)
+    .bind::<Text, _>(id)
+    .bind::<Text, _>(id)
+    .bind::<Text, _>(reading_status)
+    .execute(conn)
+    .unwrap();
+}
+
+#[derive(QueryableByName)]
+struct ReadingStatusRow {
+    #[diesel(sql_type = Text)]
+    reading_status: String,
+}
+
+#[test]
+fn it_normalises_the_spelling_of_legacy_reading_statuses() {
+    let mut conn = connection_before(READING_STATUS_EVENT_MIGRATION);
+    insert_book_with_reading_status(&mut conn, "a", " finished");
+    insert_book_with_reading_status(&mut conn, "b", "PLAN TO READ");
+
+    conn.run_pending_migrations(MIGRATIONS).unwrap();
+
+    let rows: Vec<ReadingStatusRow> =
+        diesel::sql_query("SELECT reading_status FROM book ORDER BY id")
+            .load(&mut conn)
+            .unwrap();
+    let statuses: Vec<String> = rows.into_iter().map(|v| v.reading_status).collect();
+    assert_eq!(statuses, vec!["Finished", "Plan to read"]);
+}
+
+#[test]
+fn it_refuses_to_migrate_unknown_legacy_reading_statuses() {
+    let mut conn = connection_before(READING_STATUS_EVENT_MIGRATION);
+    insert_book_with_reading_status(&mut conn, "a", "Abandoned");
+
+    let res = conn.run_pending_migrations(MIGRATIONS);
+
+    let message = res.err().unwrap().to_string();
+    assert!(
+        message.contains("unknown_reading_status_in_book"),
+        "{message}"
+    );
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{add_book, memory_connection};
+use mikomi_core::{books, sessions, COVERS_DIR};
+use std::path::Path;
+
+#[test]
+fn starting_a_session_marks_the_book_as_read() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "1", &[]);
+
+    let session = sessions::start_reading_session(&mut conn, String::from("1"), Some(10)).unwrap();
+
+    let book = books::get_book(&mut conn, "1", Path::new(COVERS_DIR))
+        .unwrap()
+        .unwrap();
+    assert_eq!(book.book.last_read, Some(session.start_time));
+}
+
+#[test]
+fn book_statistics_add_up_the_sessions_of_the_book() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "1", &[]);
+    add_book(&mut conn, "2", &[]);
+
+    for (book_id, pages_read) in [("1", 3), ("1", 4), ("2", 5)] {
+        let session =
+            sessions::start_reading_session(&mut conn, String::from(book_id), None).unwrap();
+        sessions::heartbeat_reading_session(&mut conn, &session.id, None, pages_read, 100).unwrap();
+    }
+
+    let statistics = sessions::get_book_reading_statistics(&mut conn, String::from("1")).unwrap();
+    assert_eq!(statistics.session_count, 2);
+    assert_eq!(statistics.pages_read, 7);
+    assert_eq!(statistics.characters_read, 200);
+    assert_eq!(statistics.percentage, None);
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use common::{add_book, memory_connection};
+use diesel::prelude::*;
+use mikomi_core::{models, schema, settings};
+
+fn new_book_settings(book_id: &str) -> models::BookSettings {
+    models::BookSettings {
+        id: format!("settings-{book_id}"),
+        book_id: book_id.to_string(),
+        width: None,
+        height: None,
+        percentage: None,
+        last_element: None,
+        last_page: None,
+        font_size: None,
+        line_height: None,
+        margins: None,
+        text_align: None,
+        column_count: None,
+        writing_mode: None,
+        font_family: None,
+        background_color: None,
+        color: None,
+        link_color: None,
+        primary_color: None,
+        image_blend_mode: None,
+        reader_theme_id: None,
+        user_css: None,
+    }
+}
+
+fn book_settings(conn: &mut SqliteConnection, book_id: &str) -> models::BookSettings {
+    schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq(book_id))
+        .select(models::BookSettings::as_select())
+        .first(conn)
+        .unwrap()
+}
+
+#[test]
+fn update_book_settings_can_clear_an_override() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    settings::add_book_settings(
+        &mut conn,
+        models::BookSettings {
+            font_size: Some(24),
+            ..new_book_settings("a")
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        settings::get_effective_book_settings(&mut conn, "a")
+            .unwrap()
+            .font_size,
+        24
+    );
+
+    settings::update_book_settings(&mut conn, "a", new_book_settings("a")).unwrap();
+
+    assert_eq!(book_settings(&mut conn, "a").font_size, None);
+    assert_eq!(
+        settings::get_effective_book_settings(&mut conn, "a")
+            .unwrap()
+            .font_size,
+        16
+    );
+}
+
+#[test]
+fn add_book_settings_keeps_the_values_that_are_not_given() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    settings::add_book_settings(
+        &mut conn,
+        models::BookSettings {
+            percentage: Some(40),
+            last_page: Some(12),
+            user_css: Some(String::from("p { color: red; }")),
+            ..new_book_settings("a")
+        },
+    )
+    .unwrap();
+
+    settings::add_book_settings(
+        &mut conn,
+        models::BookSettings {
+            id: String::from("another-id"),
+            width: Some(512),
+            ..new_book_settings("a")
+        },
+    )
+    .unwrap();
+
+    let saved = book_settings(&mut conn, "a");
+    assert_eq!(saved.id, "settings-a");
+    assert_eq!(saved.width, Some(512));
+    assert_eq!(saved.percentage, Some(40));
+    assert_eq!(saved.last_page, Some(12));
+    assert_eq!(saved.user_css, Some(String::from("p { color: red; }")));
+}
+
+fn new_reader_settings_profile(id: &str) -> models::ReaderSettingsProfile {
+    models::ReaderSettingsProfile {
+        id: id.to_string(),
+        name: format!("Profile {id}"),
+        font_size: 20,
+        line_height: String::from("1.5"),
+        margins: 10,
+        text_align: String::from("justify"),
+        column_count: 2,
+        writing_mode: String::from("horizontal"),
+        font_family: String::from("serif"),
+    }
+}
+
+#[test]
+fn apply_reader_settings_profile_reports_unknown_books() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+    settings::add_reader_settings_profile(&mut conn, new_reader_settings_profile("p")).unwrap();
+
+    let results = settings::apply_reader_settings_profile(
+        &mut conn,
+        "p",
+        settings::ReaderSettingsProfileTarget::Books(vec![
+            String::from("a"),
+            String::from("missing"),
+        ]),
+    )
+    .unwrap();
+
+    assert!(results[0].success);
+    assert!(!results[1].success);
+    assert_eq!(results[1].error, Some(String::from("Book not found")));
+    assert_eq!(book_settings(&mut conn, "a").font_size, Some(20));
+    let missing: i64 = schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq("missing"))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(missing, 0);
+}
+
+#[test]
+fn update_reader_settings_profile_rejects_unknown_profiles() {
+    let mut conn = memory_connection();
+    settings::add_reader_settings_profile(&mut conn, new_reader_settings_profile("p")).unwrap();
+
+    settings::update_reader_settings_profile(
+        &mut conn,
+        models::ReaderSettingsProfile {
+            font_size: 28,
+            ..new_reader_settings_profile("p")
+        },
+    )
+    .unwrap();
+    let res = settings::update_reader_settings_profile(&mut conn, new_reader_settings_profile("q"));
+
+    assert!(matches!(res, Err(mikomi_core::Error::NotFound)));
+    let profiles = settings::get_reader_settings_profiles(&mut conn).unwrap();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].font_size, 28);
+}
+
+#[test]
+fn bulk_update_book_settings_rejects_empty_settings() {
+    let mut conn = memory_connection();
+    add_book(&mut conn, "a", &[]);
+
+    let res = settings::bulk_update_book_settings(
+        &mut conn,
+        vec![String::from("a")],
+        settings::BulkBookSettings {
+            font_size: None,
+            line_height: None,
+            margins: None,
+            text_align: None,
+            column_count: None,
+            writing_mode: None,
+            font_family: None,
+            reader_theme_id: None,
+        },
+    );
+
+    assert!(matches!(res, Err(mikomi_core::Error::Rejected(_))));
+    let count: i64 = schema::book_settings::table
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(count, 0);
+}
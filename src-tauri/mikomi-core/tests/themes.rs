@@ -0,0 +1,80 @@
+mod common;
+
+use common::memory_connection;
+use mikomi_core::{models, themes};
+
+fn new_reader_theme(id: &str) -> models::ReaderTheme {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": "Custom theme",
+        "background_color": "#000000",
+        "color": "#ffffff",
+        "link_color": "#00aaff",
+        "primary_color": "#ffaa00",
+        "image_blend_mode": "normal",
+    }))
+    .unwrap()
+}
+
+fn reader_theme(conn: &mut diesel::SqliteConnection, id: &str) -> models::ReaderTheme {
+    themes::get_reader_themes_by_ids(conn, vec![id.to_string()])
+        .unwrap()
+        .remove(0)
+}
+
+#[test]
+fn client_cannot_mark_reader_themes_as_built_in() {
+    let mut conn = memory_connection();
+
+    let added = models::ReaderTheme {
+        built_in: true,
+        ..new_reader_theme("added")
+    };
+    themes::add_reader_theme(&mut conn, added).unwrap();
+    let imported = models::ReaderTheme {
+        built_in: true,
+        ..new_reader_theme("imported")
+    };
+    themes::add_reader_themes(&mut conn, &[imported]).unwrap();
+    let renamed = models::ReaderTheme {
+        name: String::from("Renamed"),
+        built_in: true,
+        ..new_reader_theme("added")
+    };
+    themes::update_reader_theme(&mut conn, renamed).unwrap();
+
+    let added = reader_theme(&mut conn, "added");
+    assert_eq!(added.name, "Renamed");
+    assert!(!added.built_in);
+    assert!(!reader_theme(&mut conn, "imported").built_in);
+    assert!(themes::remove_reader_theme(&mut conn, "added").is_ok());
+}
+
+#[test]
+fn built_in_reader_themes_cannot_be_changed() {
+    let mut conn = memory_connection();
+
+    let added = themes::add_reader_theme(&mut conn, new_reader_theme("light"));
+    let updated = themes::update_reader_theme(&mut conn, new_reader_theme("light"));
+
+    assert!(matches!(added, Err(mikomi_core::Error::Rejected(_))));
+    assert!(matches!(updated, Err(mikomi_core::Error::Rejected(_))));
+    let light = reader_theme(&mut conn, "light");
+    assert_eq!(light.name, "Light");
+    assert_eq!(light.background_color, "#ffffff");
+    assert!(light.built_in);
+}
+
+#[test]
+fn updating_an_unknown_reader_theme_fails() {
+    let mut conn = memory_connection();
+
+    let res = themes::update_reader_theme(&mut conn, new_reader_theme("missing"));
+
+    assert!(matches!(res, Err(mikomi_core::Error::NotFound)));
+    assert!(
+        themes::get_reader_themes_by_ids(&mut conn, vec![String::from("missing")])
+            .unwrap()
+            .is_empty()
+    );
+}
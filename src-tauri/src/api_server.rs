@@ -0,0 +1,639 @@
+use crate::db::{self, establish_connection};
+use crate::models;
+use mikomi_core::settings::{get_app_setting, set_app_setting};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use url::Url;
+use uuid::Uuid;
+
+pub const API_SERVER_SETTINGS_KEY: &str = "api_server";
+const DEFAULT_API_SERVER_PORT: i32 = 8586;
+const MAX_REQUEST_BODY_SIZE: u64 = 16 * 1024 * 1024;
+const BIND_RETRIES: usize = 20;
+const BIND_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Deserialize)]
+struct ApiServerSettings {
+    enabled: bool,
+    port: i32,
+    token: String,
+}
+
+impl Default for ApiServerSettings {
+    fn default() -> Self {
+        ApiServerSettings {
+            enabled: false,
+            port: DEFAULT_API_SERVER_PORT,
+            token: new_token(),
+        }
+    }
+}
+
+#[derive(Serialize, Type)]
+pub struct ApiServerStatus {
+    pub running: bool,
+    pub port: i32,
+    pub token: String,
+}
+
+struct RunningServer {
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+}
+
+static RUNNING_SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+
+// The request bodies of the commands that take more than a single struct, with the same
+// field names as the commands' arguments
+
+#[derive(Deserialize)]
+struct AddBookRequest {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct ReadingStatusRequest {
+    reading_status: models::ReadingStatus,
+}
+
+#[derive(Deserialize)]
+struct CollectionIdsRequest {
+    collection_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ReaderThemeIdRequest {
+    reader_theme_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BulkReadingStatusRequest {
+    book_ids: Vec<String>,
+    reading_status: models::ReadingStatus,
+}
+
+#[derive(Deserialize)]
+struct BulkCollectionsRequest {
+    book_ids: Vec<String>,
+    collection_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct NameRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct MoveCollectionRequest {
+    parent_id: Option<String>,
+    sort_order: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct UpdateHighlightRequest {
+    note: String,
+    color: String,
+    highlight_color_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DisplayTextRequest {
+    display_text: String,
+}
+
+#[derive(Deserialize)]
+struct ContentRequest {
+    content: String,
+}
+
+struct ApiResponse {
+    status: u16,
+    body: Option<Value>,
+}
+
+fn new_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+fn read_settings(conn: &mut diesel::SqliteConnection) -> ApiServerSettings {
+    get_app_setting(conn, API_SERVER_SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(
+    conn: &mut diesel::SqliteConnection,
+    settings: &ApiServerSettings,
+) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    let res = set_app_setting(conn, API_SERVER_SETTINGS_KEY, Some(&json));
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update API server settings")),
+    }
+}
+
+fn error(status: u16, message: &str) -> ApiResponse {
+    ApiResponse {
+        status,
+        body: Some(serde_json::json!({ "error": message })),
+    }
+}
+
+fn ok<T: Serialize>(value: T) -> ApiResponse {
+    match serde_json::to_value(value) {
+        Ok(v) => ApiResponse {
+            status: 200,
+            body: Some(v),
+        },
+        Err(_) => error(500, "Cannot serialize response"),
+    }
+}
+
+// Maps the result of a command, where the command's error message is the client's fault
+fn from_command<T: Serialize>(res: Result<T, String>) -> ApiResponse {
+    match res {
+        Ok(v) => ok(v),
+        Err(e) => error(400, &e),
+    }
+}
+
+fn from_unit_command(res: Result<(), String>) -> ApiResponse {
+    match res {
+        Ok(_) => ApiResponse {
+            status: 204,
+            body: None,
+        },
+        Err(e) => error(400, &e),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiResponse> {
+    serde_json::from_slice(body).map_err(|e| error(400, &format!("Invalid request body: {}", e)))
+}
+
+fn route(
+    method: &Method,
+    segments: &[&str],
+    query: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<ApiResponse, ApiResponse> {
+    use Method::{Delete, Get, Patch, Post, Put};
+
+    let response = match (method, segments) {
+        // Books
+        (Get, ["books"]) => ok(db::get_books()),
+        (Post, ["books"]) => {
+            let request: AddBookRequest = parse_body(body)?;
+            from_command(tauri::async_runtime::block_on(db::add_book_from_file(
+                request.path,
+            )))
+        }
+        (Get, ["books", id]) => match db::get_book(id.to_string()) {
+            Some(book) => ok(book),
+            None => error(404, "Cannot find book"),
+        },
+        (Put, ["books", id]) => {
+            let book: models::Book = parse_body(body)?;
+            if book.id != *id {
+                return Err(error(400, "Book id does not match the URL"));
+            }
+            from_unit_command(db::update_book(book))
+        }
+        (Delete, ["books", id]) => from_unit_command(db::remove_book(id.to_string())),
+        (Put, ["books", id, "reading-status"]) => {
+            let request: ReadingStatusRequest = parse_body(body)?;
+            from_unit_command(db::update_book_reading_status(
+                id.to_string(),
+                request.reading_status,
+            ))
+        }
+        (Put, ["books", id, "collections"]) => {
+            let request: CollectionIdsRequest = parse_body(body)?;
+            from_unit_command(db::add_book_to_collections(
+                id.to_string(),
+                request.collection_ids,
+            ))
+        }
+        (Delete, ["books", id, "collections", collection_id]) => from_unit_command(
+            db::remove_book_from_collection(id.to_string(), collection_id.to_string()),
+        ),
+        (Put, ["books", id, "theme"]) => {
+            let request: ReaderThemeIdRequest = parse_body(body)?;
+            from_unit_command(db::set_book_reader_theme(
+                id.to_string(),
+                request.reader_theme_id,
+            ))
+        }
+        (Post, ["books", "bulk", "reading-status"]) => {
+            let request: BulkReadingStatusRequest = parse_body(body)?;
+            from_command(db::bulk_update_reading_status(
+                request.book_ids,
+                request.reading_status,
+            ))
+        }
+        (Post, ["books", "bulk", "collections"]) => {
+            let request: BulkCollectionsRequest = parse_body(body)?;
+            from_command(db::bulk_add_books_to_collections(
+                request.book_ids,
+                request.collection_ids,
+            ))
+        }
+        (Delete, ["books", "bulk", "collections"]) => {
+            let request: BulkCollectionsRequest = parse_body(body)?;
+            from_command(db::bulk_remove_books_from_collections(
+                request.book_ids,
+                request.collection_ids,
+            ))
+        }
+
+        // Collections
+        (Get, ["collections"]) => ok(db::get_collections()),
+        (Post, ["collections"]) => from_unit_command(db::add_collection(parse_body(body)?)),
+        (Put, ["collections", "order"]) => {
+            from_unit_command(db::reorder_collections(parse_body(body)?))
+        }
+        (Get, ["collections", "tree"]) => from_command(db::get_collection_tree()),
+        (Patch, ["collections", id]) => {
+            let request: NameRequest = parse_body(body)?;
+            from_unit_command(db::update_collection_name(id.to_string(), request.name))
+        }
+        (Delete, ["collections", id]) => {
+            let policy = match query.get("policy").map(|p| p.as_str()) {
+                Some(policy) => Some(
+                    serde_json::from_value(Value::String(policy.to_string()))
+                        .map_err(|_| error(400, "Invalid collection removal policy"))?,
+                ),
+                None => None,
+            };
+            from_unit_command(db::remove_collection(id.to_string(), policy))
+        }
+        (Put, ["collections", id, "parent"]) => {
+            let request: MoveCollectionRequest = parse_body(body)?;
+            from_unit_command(db::move_collection(
+                id.to_string(),
+                request.parent_id,
+                request.sort_order,
+            ))
+        }
+        (Get, ["collections", id, "books"]) => {
+            if query.get("tree").is_some_and(|v| v == "true") {
+                from_command(db::get_books_belonging_to_collection_tree(id.to_string()))
+            } else {
+                ok(db::get_books_belonging_to_collections(id.to_string()))
+            }
+        }
+        (Put, ["collections", id, "books", "order"]) => {
+            let links: Vec<models::BookCollectionLink> = parse_body(body)?;
+            if links.iter().any(|link| link.collection_id != *id) {
+                return Err(error(400, "Collection id does not match the URL"));
+            }
+            from_unit_command(db::reorder_books_in_collection(links))
+        }
+
+        // Highlights
+        (Get, ["highlights"]) => {
+            let color_ids: Vec<String> = query
+                .get("color_ids")
+                .map(|ids| ids.split(',').map(String::from).collect())
+                .unwrap_or_default();
+            from_command(db::get_highlights(query.get("book_id").cloned(), color_ids))
+        }
+        (Post, ["highlights"]) => from_unit_command(db::add_highlight(parse_body(body)?)),
+        (Patch, ["highlights", id]) => {
+            let request: UpdateHighlightRequest = parse_body(body)?;
+            from_unit_command(db::update_highlight(
+                id.to_string(),
+                request.note,
+                request.color,
+                request.highlight_color_id,
+            ))
+        }
+        (Delete, ["highlights", id]) => from_unit_command(db::remove_highlight(id.to_string())),
+        (Get, ["highlights", id, "notes"]) => from_command(db::get_highlight_notes(id.to_string())),
+        (Post, ["highlight-notes"]) => from_unit_command(db::add_highlight_note(parse_body(body)?)),
+        (Patch, ["highlight-notes", id]) => {
+            let request: ContentRequest = parse_body(body)?;
+            from_unit_command(db::update_highlight_note(id.to_string(), request.content))
+        }
+        (Delete, ["highlight-notes", id]) => {
+            from_unit_command(db::remove_highlight_note(id.to_string()))
+        }
+        (Get, ["highlight-colors"]) => ok(db::get_highlight_colors()),
+
+        // Bookmarks
+        (Post, ["bookmarks"]) => from_unit_command(db::add_bookmark(parse_body(body)?)),
+        (Patch, ["bookmarks", id]) => {
+            let request: DisplayTextRequest = parse_body(body)?;
+            from_unit_command(db::update_bookmark(id.to_string(), request.display_text))
+        }
+        (Delete, ["bookmarks", id]) => from_unit_command(db::remove_bookmark(id.to_string())),
+
+        // Themes
+        (Get, ["themes"]) => ok(db::get_reader_themes()),
+        (Post, ["themes"]) => from_unit_command(db::add_reader_theme(parse_body(body)?)),
+        (Put, ["themes", id]) => {
+            let theme: models::ReaderTheme = parse_body(body)?;
+            if theme.id != *id {
+                return Err(error(400, "Theme id does not match the URL"));
+            }
+            from_unit_command(db::update_reader_theme(theme))
+        }
+        (Delete, ["themes", id]) => from_unit_command(db::remove_reader_theme(id.to_string())),
+
+        _ => error(404, "Not found"),
+    };
+
+    Ok(response)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+// Reads the body of a request, refusing one that is larger than the limit instead of cutting
+// it short. One byte more than the limit is read to tell a body of exactly the limit from a
+// larger one that was sent without its length.
+fn read_body(reader: impl Read, length: Option<usize>, limit: u64) -> Result<Vec<u8>, ApiResponse> {
+    let too_large = error(413, "Request body is too large");
+    if length.is_some_and(|length| length as u64 > limit) {
+        return Err(too_large);
+    }
+
+    let mut body: Vec<u8> = vec![];
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| error(400, "Invalid request"))?;
+    if body.len() as u64 > limit {
+        return Err(too_large);
+    }
+
+    Ok(body)
+}
+
+fn handle_request(mut request: Request, token: &str) {
+    let expected = format!("Bearer {}", token);
+    let authorized = request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == expected);
+
+    let response = if !authorized {
+        error(401, "Invalid API token")
+    } else {
+        let length = request.body_length();
+        match (
+            read_body(request.as_reader(), length, MAX_REQUEST_BODY_SIZE),
+            Url::parse(&format!("http://localhost{}", request.url())),
+        ) {
+            (Err(response), _) => response,
+            (Ok(body), Ok(url)) => {
+                let segments: Vec<String> = url
+                    .path_segments()
+                    .map(|s| {
+                        s.filter(|s| !s.is_empty())
+                            .map(|s| {
+                                percent_encoding::percent_decode_str(s)
+                                    .decode_utf8_lossy()
+                                    .to_string()
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+                let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+                match segments.as_slice() {
+                    ["api", segments @ ..] => {
+                        route(request.method(), segments, &query, &body).unwrap_or_else(|e| e)
+                    }
+                    _ => error(404, "Not found"),
+                }
+            }
+            _ => error(400, "Invalid request"),
+        }
+    };
+
+    let status = StatusCode(response.status);
+    let _ = match response.body {
+        Some(body) => request.respond(
+            Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(header("Content-Type", "application/json")),
+        ),
+        None => request.respond(Response::empty(status)),
+    };
+}
+
+fn stop_server(running: RunningServer) {
+    running.server.unblock();
+    let _ = running.thread.join();
+}
+
+pub fn stop_running_server() {
+    let running = RUNNING_SERVER.lock().unwrap().take();
+    if let Some(running) = running {
+        stop_server(running);
+    }
+}
+
+fn running_server_port() -> Option<u16> {
+    RUNNING_SERVER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|running| running.server.server_addr().to_ip())
+        .map(|addr| addr.port())
+}
+
+// Binds the new server before the running one is stopped, like the OPDS server does. Only
+// scripts on this machine can reach the API.
+fn bind_server(port: u16) -> Result<Arc<Server>, String> {
+    let mut server = Server::http(("127.0.0.1", port));
+    if server.is_err() && running_server_port() == Some(port) {
+        stop_running_server();
+        for _ in 0..BIND_RETRIES {
+            server = Server::http(("127.0.0.1", port));
+            if server.is_ok() {
+                break;
+            }
+            thread::sleep(BIND_RETRY_DELAY);
+        }
+    }
+
+    server
+        .map(Arc::new)
+        .map_err(|_| format!("Cannot listen on port {}", port))
+}
+
+fn start_server(settings: &ApiServerSettings) -> Result<(), String> {
+    let port = u16::try_from(settings.port).map_err(|_| String::from("Invalid port"))?;
+    let server = bind_server(port)?;
+
+    let token = settings.token.clone();
+    let thread = {
+        let server = server.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let token = token.clone();
+                thread::spawn(move || handle_request(request, &token));
+            }
+        })
+    };
+
+    let previous = RUNNING_SERVER
+        .lock()
+        .unwrap()
+        .replace(RunningServer { server, thread });
+    if let Some(previous) = previous {
+        stop_server(previous);
+    }
+    Ok(())
+}
+
+pub fn start_api_server_if_enabled() -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = read_settings(&mut conn);
+    drop(conn);
+
+    if !settings.enabled {
+        return Ok(());
+    }
+    start_server(&settings)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_api_server_status() -> Result<ApiServerStatus, String> {
+    let mut conn = establish_connection();
+    let settings = read_settings(&mut conn);
+    // The token is generated on first use, so it has to be stored for the scripts to keep
+    // working
+    save_settings(&mut conn, &settings)?;
+
+    Ok(ApiServerStatus {
+        running: RUNNING_SERVER.lock().unwrap().is_some(),
+        port: settings.port,
+        token: settings.token,
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn start_api_server(port: i32) -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = ApiServerSettings {
+        enabled: true,
+        port,
+        ..read_settings(&mut conn)
+    };
+    drop(conn);
+    start_server(&settings)?;
+
+    let mut conn = establish_connection();
+    save_settings(&mut conn, &settings)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_api_server() -> Result<(), String> {
+    stop_running_server();
+
+    let mut conn = establish_connection();
+    let settings = ApiServerSettings {
+        enabled: false,
+        ..read_settings(&mut conn)
+    };
+    save_settings(&mut conn, &settings)
+}
+
+// Invalidates the current token, restarting the server with the new one if it is running
+#[tauri::command]
+#[specta::specta]
+pub fn regenerate_api_token() -> Result<String, String> {
+    let mut conn = establish_connection();
+    let settings = ApiServerSettings {
+        token: new_token(),
+        ..read_settings(&mut conn)
+    };
+    save_settings(&mut conn, &settings)?;
+    drop(conn);
+
+    if RUNNING_SERVER.lock().unwrap().is_some() {
+        start_server(&settings)?;
+    }
+    Ok(settings.token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_body, running_server_port, start_server, stop_running_server, ApiServerSettings,
+    };
+    use std::net::TcpListener;
+
+    fn settings(port: u16) -> ApiServerSettings {
+        ApiServerSettings {
+            enabled: true,
+            port: port as i32,
+            ..ApiServerSettings::default()
+        }
+    }
+
+    #[test]
+    fn it_reads_bodies_up_to_the_limit() {
+        let body = read_body(&b"12345678"[..], Some(8), 8).ok();
+        assert_eq!(body.as_deref(), Some(&b"12345678"[..]));
+        let body = read_body(&b"12345678"[..], None, 8).ok();
+        assert_eq!(body.as_deref(), Some(&b"12345678"[..]));
+    }
+
+    #[test]
+    fn it_refuses_bodies_over_the_limit() {
+        for length in [Some(9), None] {
+            let status = read_body(&b"123456789"[..], length, 8)
+                .err()
+                .map(|r| r.status);
+            assert_eq!(status, Some(413));
+        }
+        // The declared length is enough to refuse the body without reading it
+        let status = read_body(&b""[..], Some(usize::MAX), 8)
+            .err()
+            .map(|r| r.status);
+        assert_eq!(status, Some(413));
+    }
+
+    #[test]
+    fn restarting_keeps_the_running_server_when_the_port_is_taken() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        start_server(&settings(port)).unwrap();
+
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        assert!(start_server(&settings(taken_port)).is_err());
+        assert_eq!(running_server_port(), Some(port));
+
+        // Restarting on its own port, like a new token does, rebinds it
+        start_server(&settings(port)).unwrap();
+        assert_eq!(running_server_port(), Some(port));
+
+        stop_running_server();
+        assert_eq!(running_server_port(), None);
+    }
+}
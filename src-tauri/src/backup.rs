@@ -0,0 +1,356 @@
+use crate::db::{command_error, establish_connection};
+use diesel::connection::SimpleConnection;
+use diesel::SqliteConnection;
+use mikomi_core::backup::{
+    backup_database, extract_backup_archive, migrate_restored_database, write_backup_archive,
+    BackupManifest, BACKUP_DIRS, DATABASE_FILE_NAME,
+};
+use mikomi_core::settings::{get_app_setting, set_app_setting};
+use mikomi_core::{current_timestamp, database_url};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+const DATA_DIR: &str = "mikomi-data";
+
+// The live database, which is wherever `establish_connection` opens it
+fn database_path() -> PathBuf {
+    PathBuf::from(database_url())
+}
+
+// Where an entry of a backup is kept in the live library
+fn live_path(name: &str) -> PathBuf {
+    if name == DATABASE_FILE_NAME {
+        database_path()
+    } else {
+        Path::new(DATA_DIR).join(name)
+    }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+// Moves the staged database and data directories into the library. Only the entries present
+// in the staging directory are replaced; the current ones are moved aside first and are put
+// back if any step fails. Nothing else may use the database while this runs, see
+// `restore::with_background_tasks_stopped`.
+fn swap_in_staged_data(staging_dir: &Path) -> Result<(), String> {
+    let previous_dir = Path::new(DATA_DIR).join(".previous");
+    remove_path(&previous_dir).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&previous_dir).map_err(|e| e.to_string())?;
+
+    let mut conn = establish_connection();
+    let _ = conn.batch_execute("PRAGMA wal_checkpoint(TRUNCATE);");
+    drop(conn);
+
+    let mut names: Vec<&str> = vec![DATABASE_FILE_NAME];
+    names.extend(BACKUP_DIRS);
+    names.retain(|name| staging_dir.join(name).exists());
+
+    let mut moved_aside: Vec<&str> = vec![];
+    let mut moved_in: Vec<&str> = vec![];
+    let res = (|| {
+        for name in &names {
+            if live_path(name).exists() {
+                fs::rename(live_path(name), previous_dir.join(name))?;
+                moved_aside.push(*name);
+            }
+        }
+        for suffix in ["-wal", "-shm"] {
+            remove_path(Path::new(&format!(
+                "{}{suffix}",
+                database_path().to_string_lossy()
+            )))?;
+        }
+        for name in &names {
+            fs::rename(staging_dir.join(name), live_path(name))?;
+            moved_in.push(*name);
+        }
+
+        io::Result::Ok(())
+    })();
+
+    match res {
+        Ok(_) => {
+            let _ = remove_path(&previous_dir);
+            Ok(())
+        }
+        Err(_) => {
+            for name in moved_in {
+                let _ = remove_path(&live_path(name));
+            }
+            for name in moved_aside {
+                let _ = fs::rename(previous_dir.join(name), live_path(name));
+            }
+            Err(String::from("Cannot replace library data"))
+        }
+    }
+}
+
+pub fn restore_backup_archive(path: &Path) -> Result<BackupManifest, String> {
+    let staging_dir = Path::new(DATA_DIR).join(".restore");
+    remove_path(&staging_dir).map_err(|e| e.to_string())?;
+
+    let res = (|| {
+        let manifest = extract_backup_archive(path, &staging_dir)
+            .and_then(|manifest| {
+                migrate_restored_database(&staging_dir.join(DATABASE_FILE_NAME))?;
+                Ok(manifest)
+            })
+            .map_err(command_error("Cannot restore backup"))?;
+        swap_in_staged_data(&staging_dir)?;
+        Ok(manifest)
+    })();
+
+    let _ = remove_path(&staging_dir);
+
+    res
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn create_backup(path: String) -> Result<(), String> {
+    write_backup_archive(
+        &database_path(),
+        Path::new(DATA_DIR),
+        Path::new(&path),
+        env!("CARGO_PKG_VERSION"),
+    )
+    .map(|_| ())
+    .map_err(command_error("Cannot create backup"))
+}
+
+pub const SNAPSHOT_SETTINGS_KEY: &str = "snapshot_settings";
+const SNAPSHOT_DIR: &str = "mikomi-data/snapshots";
+const SNAPSHOT_FILE_PREFIX: &str = "snapshot-";
+const SNAPSHOT_FILE_EXTENSION: &str = ".sqlite";
+const SNAPSHOT_SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+// A year, which keeps the interval in seconds well within an i32
+const MAX_SNAPSHOT_INTERVAL_HOURS: i32 = 24 * 365;
+
+// Held by the snapshot scheduler while it checks for and takes a snapshot
+static SNAPSHOT_SCHEDULER_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Type)]
+pub struct SnapshotSettings {
+    pub on_startup: bool,
+    pub on_exit: bool,
+    pub interval_hours: Option<i32>,
+    pub keep: i32,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        SnapshotSettings {
+            on_startup: true,
+            on_exit: false,
+            interval_hours: None,
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Type)]
+pub struct Snapshot {
+    file_name: String,
+    date_added: i32,
+    // In bytes, as a string because the TypeScript bindings cannot export 64-bit integers
+    size: String,
+}
+
+fn read_snapshot_settings(conn: &mut SqliteConnection) -> SnapshotSettings {
+    get_app_setting(conn, SNAPSHOT_SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn snapshot_date(file_name: &str) -> Option<i32> {
+    file_name
+        .strip_prefix(SNAPSHOT_FILE_PREFIX)?
+        .strip_suffix(SNAPSHOT_FILE_EXTENSION)?
+        .parse()
+        .ok()
+}
+
+// Returns the snapshots from newest to oldest
+fn read_snapshots() -> Vec<Snapshot> {
+    let entries = match fs::read_dir(SNAPSHOT_DIR) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let date_added = snapshot_date(&file_name)?;
+            let size = entry.metadata().ok()?.len();
+            Some(Snapshot {
+                file_name,
+                date_added,
+                size: size.to_string(),
+            })
+        })
+        .collect();
+    snapshots.sort_by_key(|s| Reverse(s.date_added));
+
+    snapshots
+}
+
+pub fn take_snapshot(keep: i32) -> Result<(), String> {
+    fs::create_dir_all(SNAPSHOT_DIR).map_err(|e| e.to_string())?;
+
+    let mut date_added = current_timestamp();
+    // Snapshots are named by their timestamp, so a second snapshot within the same second
+    // would otherwise overwrite the first
+    if let Some(newest) = read_snapshots().first() {
+        date_added = date_added.max(newest.date_added + 1);
+    }
+    let file_name = format!("{SNAPSHOT_FILE_PREFIX}{date_added}{SNAPSHOT_FILE_EXTENSION}");
+    backup_database(&database_path(), &Path::new(SNAPSHOT_DIR).join(file_name))
+        .map_err(command_error("Cannot take snapshot"))?;
+
+    for snapshot in read_snapshots().iter().skip(keep.max(1) as usize) {
+        let _ = fs::remove_file(Path::new(SNAPSHOT_DIR).join(&snapshot.file_name));
+    }
+
+    Ok(())
+}
+
+pub fn take_startup_snapshot() -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = read_snapshot_settings(&mut conn);
+    if !settings.on_startup {
+        return Ok(());
+    }
+
+    take_snapshot(settings.keep)
+}
+
+pub fn take_exit_snapshot() -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = read_snapshot_settings(&mut conn);
+    if !settings.on_exit {
+        return Ok(());
+    }
+
+    take_snapshot(settings.keep)
+}
+
+// Keeps the snapshot scheduler from running until the guard is dropped
+pub fn pause_snapshot_scheduler() -> MutexGuard<'static, ()> {
+    SNAPSHOT_SCHEDULER_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Periodically takes a snapshot when the newest one is older than the configured interval
+pub fn spawn_snapshot_scheduler() {
+    thread::spawn(|| loop {
+        thread::sleep(SNAPSHOT_SCHEDULER_INTERVAL);
+        let _guard = SNAPSHOT_SCHEDULER_LOCK.lock().unwrap();
+
+        let mut conn = establish_connection();
+        let settings = read_snapshot_settings(&mut conn);
+        drop(conn);
+
+        let interval_hours = match settings.interval_hours {
+            Some(v) if v > 0 => v,
+            _ => continue,
+        };
+        let last_snapshot = read_snapshots().first().map_or(0, |s| s.date_added);
+        let elapsed = i64::from(current_timestamp()) - i64::from(last_snapshot);
+        if elapsed >= i64::from(interval_hours) * 60 * 60 {
+            let _ = take_snapshot(settings.keep);
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_snapshot_settings() -> SnapshotSettings {
+    let mut conn = establish_connection();
+    read_snapshot_settings(&mut conn)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_snapshot_settings(settings: SnapshotSettings) -> Result<(), String> {
+    if settings.keep < 1 {
+        return Err(String::from("At least one snapshot must be kept"));
+    }
+    if settings
+        .interval_hours
+        .is_some_and(|v| !(1..=MAX_SNAPSHOT_INTERVAL_HOURS).contains(&v))
+    {
+        return Err(format!(
+            "Snapshot interval must be between 1 and {MAX_SNAPSHOT_INTERVAL_HOURS} hours"
+        ));
+    }
+
+    let mut conn = establish_connection();
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let res = set_app_setting(&mut conn, SNAPSHOT_SETTINGS_KEY, Some(&json));
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update snapshot settings")),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_snapshots() -> Vec<Snapshot> {
+    read_snapshots()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn create_snapshot() -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = read_snapshot_settings(&mut conn);
+    drop(conn);
+
+    take_snapshot(settings.keep)
+}
+
+pub fn restore_snapshot_file(file_name: &str) -> Result<(), String> {
+    if snapshot_date(file_name).is_none() {
+        return Err(String::from("Invalid snapshot"));
+    }
+    let snapshot_path = Path::new(SNAPSHOT_DIR).join(file_name);
+    if !snapshot_path.is_file() {
+        return Err(String::from("Snapshot not found"));
+    }
+
+    let staging_dir = Path::new(DATA_DIR).join(".restore");
+    remove_path(&staging_dir).map_err(|e| e.to_string())?;
+
+    let res = (|| {
+        fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+        fs::copy(&snapshot_path, staging_dir.join(DATABASE_FILE_NAME))
+            .map_err(|_| String::from("Cannot read snapshot"))?;
+        migrate_restored_database(&staging_dir.join(DATABASE_FILE_NAME))
+            .map_err(command_error("Cannot restore snapshot"))?;
+        swap_in_staged_data(&staging_dir)
+    })();
+
+    let _ = remove_path(&staging_dir);
+
+    res
+}
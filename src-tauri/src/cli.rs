@@ -0,0 +1,669 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::SqliteConnection;
+use mikomi_core::backup::write_backup_archive;
+use mikomi_core::import::import_book;
+use mikomi_core::{annotations, books, collections, database_url, Error, COVERS_DIR};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use mikomi_core::{models, schema};
+
+const DATA_DIR: &str = "mikomi-data";
+
+#[derive(Parser)]
+#[command(
+    name = "mikomi-cli",
+    version,
+    about = "Manage the Mikomi library without the app"
+)]
+struct Cli {
+    /// Directory that contains the `mikomi-data` folder, instead of the current directory
+    #[arg(long, global = true)]
+    library: Option<PathBuf>,
+
+    /// Print JSON instead of plain text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Import EPUB files, or every EPUB file found in a folder
+    Import {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// List the books in the library
+    List(ListFilters),
+    /// Search the books by title or author
+    Search {
+        query: String,
+
+        #[command(flatten)]
+        filters: ListFilters,
+    },
+    /// Show a book with its authors, collections and reading progress
+    Show { book_id: String },
+    /// Export the bookmarks, highlights and notes of a book, or of the whole library
+    ExportAnnotations {
+        book_id: Option<String>,
+
+        /// Write the annotations to this file instead of printing them
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add books to a collection, given by its id or name
+    AddToCollection {
+        collection: String,
+
+        #[arg(required = true)]
+        book_ids: Vec<String>,
+    },
+    /// Set the reading status of books
+    SetStatus {
+        status: Status,
+
+        #[arg(required = true)]
+        book_ids: Vec<String>,
+    },
+    /// Write a backup archive of the library
+    Backup { path: PathBuf },
+    /// Check the database for corruption and for books whose files are missing
+    Check,
+}
+
+#[derive(Args)]
+struct ListFilters {
+    /// Only list books with this reading status
+    #[arg(long)]
+    status: Option<Status>,
+
+    /// Only list books in this collection, given by its id or name
+    #[arg(long)]
+    collection: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Status {
+    Reading,
+    PlanToRead,
+    Finished,
+}
+
+impl From<Status> for models::ReadingStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Reading => models::ReadingStatus::Reading,
+            Status::PlanToRead => models::ReadingStatus::PlanToRead,
+            Status::Finished => models::ReadingStatus::Finished,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ImportResult {
+    path: String,
+    book: Option<models::Book>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HighlightWithNotes {
+    #[serde(flatten)]
+    highlight: models::Highlight,
+    notes: Vec<models::HighlightNote>,
+}
+
+#[derive(Serialize)]
+struct BookAnnotations {
+    book_id: String,
+    title: String,
+    authors: Vec<String>,
+    bookmarks: Vec<models::Bookmark>,
+    highlights: Vec<HighlightWithNotes>,
+}
+
+#[derive(QueryableByName)]
+struct IntegrityCheckRow {
+    #[diesel(sql_type = Text)]
+    integrity_check: String,
+}
+
+#[derive(QueryableByName)]
+struct ForeignKeyCheckRow {
+    #[diesel(sql_type = Text)]
+    table: String,
+    #[diesel(sql_type = Text)]
+    parent: String,
+}
+
+#[derive(Serialize)]
+struct MissingBookFile {
+    book_id: String,
+    title: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct DatabaseCheck {
+    integrity_errors: Vec<String>,
+    foreign_key_violations: Vec<String>,
+    missing_book_files: Vec<MissingBookFile>,
+}
+
+impl DatabaseCheck {
+    fn problem_count(&self) -> usize {
+        self.integrity_errors.len()
+            + self.foreign_key_violations.len()
+            + self.missing_book_files.len()
+    }
+}
+
+// Keeps the reason of a refused operation, and adds the cause to the other errors
+fn cli_error(message: &str) -> impl Fn(Error) -> String + '_ {
+    move |e| match e {
+        Error::Rejected(reason) => reason,
+        e => format!("{message}: {e}"),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{json}");
+
+    Ok(())
+}
+
+fn author_names(authors: &[models::Author]) -> Vec<String> {
+    authors.iter().map(|a| a.name.clone()).collect()
+}
+
+fn find_collection(
+    conn: &mut SqliteConnection,
+    collection: &str,
+) -> Result<models::Collection, String> {
+    let mut collections =
+        collections::get_collections(conn).map_err(cli_error("Cannot get collections"))?;
+    let index = collections
+        .iter()
+        .position(|c| c.id == collection)
+        .or_else(|| collections.iter().position(|c| c.name == collection));
+
+    match index {
+        Some(i) => Ok(collections.swap_remove(i)),
+        None => Err(format!("Cannot find collection {collection}")),
+    }
+}
+
+fn collect_epub_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|e| format!("Cannot read {}: {e}", path.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        let is_epub = entry
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("epub"));
+        if entry.is_dir() {
+            collect_epub_files(&entry, files)?;
+        } else if is_epub {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+fn import(conn: &mut SqliteConnection, paths: Vec<PathBuf>, json: bool) -> Result<(), String> {
+    let mut files: Vec<PathBuf> = vec![];
+    for path in &paths {
+        collect_epub_files(path, &mut files)?;
+    }
+
+    let results: Vec<ImportResult> = files
+        .into_iter()
+        .map(|file| {
+            let path = file.to_string_lossy().to_string();
+            match import_book(conn, path.clone(), Path::new(COVERS_DIR)) {
+                Ok(book) => ImportResult {
+                    path,
+                    book: Some(book),
+                    error: None,
+                },
+                Err(e) => ImportResult {
+                    path,
+                    book: None,
+                    error: Some(cli_error("Cannot add epub to database")(e)),
+                },
+            }
+        })
+        .collect();
+
+    if json {
+        print_json(&results)?;
+    } else {
+        for result in &results {
+            match (&result.book, &result.error) {
+                (Some(book), _) => println!("Imported {} ({})", book.title, book.id),
+                (None, error) => eprintln!(
+                    "Cannot import {}: {}",
+                    result.path,
+                    error.as_deref().unwrap_or_default()
+                ),
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if failed > 0 {
+        return Err(format!(
+            "{failed} of {} files were not imported",
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+fn list(
+    conn: &mut SqliteConnection,
+    query: Option<&str>,
+    filters: ListFilters,
+    json: bool,
+) -> Result<(), String> {
+    let collection_id = match &filters.collection {
+        Some(collection) => Some(find_collection(conn, collection)?.id),
+        None => None,
+    };
+    let query = query.map(|q| q.to_lowercase());
+
+    let books: Vec<books::BookWithAuthorsAndCoverAndSettingsAndCollections> =
+        books::get_books(conn, Path::new(COVERS_DIR))
+            .map_err(cli_error("Cannot get books"))?
+            .into_iter()
+            .filter(|b| match filters.status {
+                Some(status) => b.book.reading_status == status.into(),
+                None => true,
+            })
+            .filter(|b| match &collection_id {
+                Some(id) => b.collections.iter().any(|c| &c.id == id),
+                None => true,
+            })
+            .filter(|b| match &query {
+                Some(query) => {
+                    b.book.title.to_lowercase().contains(query)
+                        || b.authors
+                            .iter()
+                            .any(|a| a.name.to_lowercase().contains(query))
+                }
+                None => true,
+            })
+            .collect();
+
+    if json {
+        return print_json(&books);
+    }
+
+    // One tab separated line per book, so that the output can be piped into other tools
+    for book in &books {
+        println!(
+            "{}\t{}\t{}\t{}",
+            book.book.id,
+            book.book.reading_status.as_str(),
+            book.book.title,
+            author_names(&book.authors).join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn find_book(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+) -> Result<books::BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections, String>
+{
+    books::get_book(conn, book_id, Path::new(COVERS_DIR))
+        .map_err(cli_error("Cannot get book"))?
+        .ok_or(String::from("Cannot find book"))
+}
+
+fn show(conn: &mut SqliteConnection, book_id: String, json: bool) -> Result<(), String> {
+    let book = find_book(conn, &book_id)?;
+
+    if json {
+        return print_json(&book);
+    }
+
+    let optional_fields = [
+        ("Language", &book.book.language),
+        ("Publisher", &book.book.publisher),
+        ("Published", &book.book.published_date),
+        ("Identifier", &book.book.identifier),
+    ];
+
+    println!("{}", book.book.title);
+    println!("Id: {}", book.book.id);
+    println!("Authors: {}", author_names(&book.authors).join(", "));
+    println!("Status: {}", book.book.reading_status.as_str());
+    if let Some(percentage) = book.settings.as_ref().and_then(|s| s.percentage) {
+        println!("Progress: {percentage}%");
+    }
+    for (name, value) in optional_fields {
+        if let Some(value) = value {
+            println!("{name}: {value}");
+        }
+    }
+    println!("File: {}", book.book.path);
+    if !book.collections.is_empty() {
+        let collections: Vec<&str> = book.collections.iter().map(|c| c.name.as_str()).collect();
+        println!("Collections: {}", collections.join(", "));
+    }
+    println!("Bookmarks: {}", book.bookmarks.len());
+    println!("Highlights: {}", book.highlights.len());
+    Ok(())
+}
+
+fn get_book_annotations(
+    conn: &mut SqliteConnection,
+    book_id: String,
+) -> Result<BookAnnotations, String> {
+    let book = find_book(conn, &book_id)?;
+
+    let highlights = book
+        .highlights
+        .into_iter()
+        .map(|highlight| {
+            let notes = annotations::get_highlight_notes(conn, &highlight.id)
+                .map_err(cli_error("Cannot get highlight notes"))?;
+            Ok(HighlightWithNotes { highlight, notes })
+        })
+        .collect::<Result<Vec<HighlightWithNotes>, String>>()?;
+
+    Ok(BookAnnotations {
+        book_id: book.book.id,
+        title: book.book.title,
+        authors: author_names(&book.authors),
+        bookmarks: book.bookmarks,
+        highlights,
+    })
+}
+
+fn format_annotations(annotations: &[BookAnnotations]) -> String {
+    let mut text = String::new();
+
+    for book in annotations {
+        text.push_str(&format!("# {}\n", book.title));
+        if !book.authors.is_empty() {
+            text.push_str(&format!("by {}\n", book.authors.join(", ")));
+        }
+
+        if !book.bookmarks.is_empty() {
+            text.push_str("\n## Bookmarks\n\n");
+            for bookmark in &book.bookmarks {
+                text.push_str(&format!("- {}\n", bookmark.display_text));
+            }
+        }
+
+        if !book.highlights.is_empty() {
+            text.push_str("\n## Highlights\n\n");
+            for highlight in &book.highlights {
+                let note = match highlight.highlight.note.as_str() {
+                    "" => "(no note)",
+                    note => note,
+                };
+                text.push_str(&format!("- {} [{}]\n", note, highlight.highlight.color));
+                for note in &highlight.notes {
+                    text.push_str(&format!("  - {}\n", note.content));
+                }
+            }
+        }
+
+        text.push('\n');
+    }
+
+    text
+}
+
+fn export_annotations(
+    conn: &mut SqliteConnection,
+    book_id: Option<String>,
+    output: Option<PathBuf>,
+    json: bool,
+) -> Result<(), String> {
+    let annotations: Vec<BookAnnotations> = match book_id {
+        Some(book_id) => vec![get_book_annotations(conn, book_id)?],
+        None => books::get_books(conn, Path::new(COVERS_DIR))
+            .map_err(cli_error("Cannot get books"))?
+            .into_iter()
+            .map(|b| get_book_annotations(conn, b.book.id))
+            .collect::<Result<Vec<BookAnnotations>, String>>()?
+            .into_iter()
+            .filter(|a| !a.bookmarks.is_empty() || !a.highlights.is_empty())
+            .collect(),
+    };
+
+    let contents = if json {
+        serde_json::to_string_pretty(&annotations).map_err(|e| e.to_string())?
+    } else {
+        format_annotations(&annotations)
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(&path, contents).map_err(|e| format!("Cannot write {}: {e}", path.display()))
+        }
+        None => {
+            print!("{contents}");
+            Ok(())
+        }
+    }
+}
+
+fn add_to_collection(
+    conn: &mut SqliteConnection,
+    collection: String,
+    book_ids: Vec<String>,
+    json: bool,
+) -> Result<(), String> {
+    let collection = find_collection(conn, &collection)?;
+    let results = collections::bulk_add_books_to_collections(conn, book_ids, vec![collection.id])
+        .map_err(cli_error("Cannot add book to collection"))?;
+    print_bulk_results(&results, json)
+}
+
+fn set_status(
+    conn: &mut SqliteConnection,
+    status: Status,
+    book_ids: Vec<String>,
+    json: bool,
+) -> Result<(), String> {
+    let results = books::bulk_update_reading_status(conn, book_ids, status.into())
+        .map_err(cli_error("Cannot update book"))?;
+    print_bulk_results(&results, json)
+}
+
+fn print_bulk_results(results: &[books::BulkOperationResult], json: bool) -> Result<(), String> {
+    if json {
+        print_json(&results)?;
+    } else {
+        for result in results {
+            match &result.error {
+                None => println!("Updated {}", result.book_id),
+                Some(e) => eprintln!("Cannot update {}: {e}", result.book_id),
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    if failed > 0 {
+        return Err(format!(
+            "{failed} of {} books were not updated",
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+fn create_backup(path: PathBuf, json: bool) -> Result<(), String> {
+    let manifest = write_backup_archive(
+        Path::new(&database_url()),
+        Path::new(DATA_DIR),
+        &path,
+        env!("CARGO_PKG_VERSION"),
+    )
+    .map_err(cli_error("Cannot create backup"))?;
+
+    if json {
+        return print_json(&manifest);
+    }
+    println!(
+        "Backed up the library (schema {}) to {}",
+        manifest.schema_version,
+        path.display()
+    );
+    Ok(())
+}
+
+fn check_database(conn: &mut SqliteConnection) -> Result<DatabaseCheck, String> {
+    let integrity_errors: Vec<String> = diesel::sql_query("PRAGMA integrity_check")
+        .load::<IntegrityCheckRow>(conn)
+        .map_err(|_| String::from("Cannot check database integrity"))?
+        .into_iter()
+        .map(|r| r.integrity_check)
+        .filter(|r| r != "ok")
+        .collect();
+
+    let foreign_key_violations: Vec<String> = diesel::sql_query("PRAGMA foreign_key_check")
+        .load::<ForeignKeyCheckRow>(conn)
+        .map_err(|_| String::from("Cannot check foreign keys"))?
+        .into_iter()
+        .map(|r| {
+            format!(
+                "A row in {} references a missing row in {}",
+                r.table, r.parent
+            )
+        })
+        .collect();
+
+    let books: Vec<(String, String, String)> = schema::book::table
+        .select((schema::book::id, schema::book::title, schema::book::path))
+        .load(conn)
+        .map_err(|_| String::from("Cannot get books"))?;
+    let missing_book_files: Vec<MissingBookFile> = books
+        .into_iter()
+        .filter(|(_, _, path)| !Path::new(path).is_file())
+        .map(|(book_id, title, path)| MissingBookFile {
+            book_id,
+            title,
+            path,
+        })
+        .collect();
+
+    Ok(DatabaseCheck {
+        integrity_errors,
+        foreign_key_violations,
+        missing_book_files,
+    })
+}
+
+fn check(conn: &mut SqliteConnection, json: bool) -> Result<(), String> {
+    let check = check_database(conn)?;
+
+    if json {
+        print_json(&check)?;
+    } else {
+        for error in &check.integrity_errors {
+            println!("Integrity: {error}");
+        }
+        for violation in &check.foreign_key_violations {
+            println!("Foreign key: {violation}");
+        }
+        for missing in &check.missing_book_files {
+            println!(
+                "Missing file: {} ({}) at {}",
+                missing.title, missing.book_id, missing.path
+            );
+        }
+        if check.problem_count() == 0 {
+            println!("No problems found");
+        }
+    }
+
+    match check.problem_count() {
+        0 => Ok(()),
+        count => Err(format!("Found {count} problems")),
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    // The paths given on the command line are relative to where it was run, not to the library
+    let current_dir = std::env::current_dir().map_err(|e| e.to_string())?;
+    let mut command = cli.command;
+    match &mut command {
+        Command::Import { paths } => {
+            for path in paths.iter_mut() {
+                *path = current_dir.join(&path);
+            }
+        }
+        Command::ExportAnnotations {
+            output: Some(path), ..
+        }
+        | Command::Backup { path } => *path = current_dir.join(&path),
+        _ => {}
+    }
+
+    if let Some(library) = &cli.library {
+        std::env::set_current_dir(library)
+            .map_err(|e| format!("Cannot open library {}: {e}", library.display()))?;
+    }
+
+    let database_url = database_url();
+    if let Some(parent) = Path::new(&database_url).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create library: {e}"))?;
+    }
+    let mut conn = mikomi_core::establish_connection(&database_url)
+        .map_err(|_| format!("Cannot open library database {database_url}"))?;
+    mikomi_core::run_migrations(&mut conn).map_err(|_| String::from("Unable to run migrations"))?;
+
+    let conn = &mut conn;
+    let json = cli.json;
+    match command {
+        Command::Import { paths } => import(conn, paths, json),
+        Command::List(filters) => list(conn, None, filters, json),
+        Command::Search { query, filters } => list(conn, Some(&query), filters, json),
+        Command::Show { book_id } => show(conn, book_id, json),
+        Command::ExportAnnotations { book_id, output } => {
+            export_annotations(conn, book_id, output, json)
+        }
+        Command::AddToCollection {
+            collection,
+            book_ids,
+        } => add_to_collection(conn, collection, book_ids, json),
+        Command::SetStatus { status, book_ids } => set_status(conn, status, book_ids, json),
+        Command::Backup { path } => create_backup(path, json),
+        Command::Check => check(conn, json),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("mikomi-cli: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,134 +1,94 @@
+use crate::fonts;
 use crate::models;
-use crate::schema;
-use diesel::prelude::*;
 use diesel::{Connection, SqliteConnection};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use epub::doc::EpubDoc;
+use mikomi_core::annotations::{self, HighlightColorIdWithSortOrder, HighlightColorWithHighlights};
+use mikomi_core::books::{
+    self, BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections,
+    BookWithAuthorsAndCoverAndSettingsAndCollections, BookWithCover, BulkOperationResult,
+};
+use mikomi_core::collections::{
+    self, CollectionIdWithSortOrder, CollectionTreeNode, CollectionWithBooks,
+    RemoveCollectionPolicy,
+};
+use mikomi_core::goals::{self, ReadingGoalProgress};
+use mikomi_core::sessions::{
+    self, BookReadingStatistics, ReadingHistory, ReadingTimePerAuthor, ReadingTimePerBook,
+    ReadingTimePerDay,
+};
+use mikomi_core::settings::{
+    self, BulkBookSettings, EffectiveBookSettings, ReaderSettingsProfileTarget,
+    USER_CSS_SETTING_KEY,
+};
+use mikomi_core::{current_timestamp, database_url, import, themes, Error, COVERS_DIR};
 use serde::Deserialize;
 use serde::Serialize;
 use specta::Type;
-use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
-pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+pub const DOWNLOADED_BOOKS_DIR: &str = "mikomi-data/books";
 
 pub fn establish_connection() -> SqliteConnection {
-    let _ = fs::create_dir_all("mikomi-data").unwrap();
-    let database_url = "mikomi-data/db.sqlite";
-
-    SqliteConnection::establish(&database_url)
-        .expect(&format!("Error connecting to {}", database_url))
-}
-
-pub fn run_migrations(
-    conn: &mut SqliteConnection,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    conn.run_pending_migrations(MIGRATIONS)?;
-
-    Ok(())
-}
-
-struct BookWithAuthors {
-    book: models::Book,
-    authors: Vec<models::Author>,
-}
+    let database_url = database_url();
+    if let Some(parent) = Path::new(&database_url).parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
 
-#[derive(Serialize, Type)]
-pub struct BookWithAuthorsAndCoverAndSettingsAndCollections {
-    #[serde(flatten)]
-    book: models::Book,
-    authors: Vec<models::Author>,
-    cover: Option<String>,
-    settings: Option<models::BookSettings>,
-    collections: Vec<models::Collection>,
+    mikomi_core::establish_connection(&database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
 }
 
-#[derive(Serialize, Type)]
-pub struct BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections {
-    #[serde(flatten)]
-    book: models::Book,
-    authors: Vec<models::Author>,
-    bookmarks: Vec<models::Bookmark>,
-    highlights: Vec<models::Highlight>,
-    collections: Vec<models::Collection>,
-    cover: Option<String>,
-    settings: Option<models::BookSettings>,
+// Keeps the message of the errors that explain why an operation was refused, and replaces the
+// others with the generic message of the command
+pub fn command_error(message: &str) -> impl Fn(Error) -> String + '_ {
+    move |e| match e {
+        Error::Rejected(reason) => reason,
+        _ => String::from(message),
+    }
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn add_bookmark(new_bookmark: models::Bookmark) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::insert_into(schema::bookmark::table)
-        .values(&new_bookmark)
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot add bookmark")),
-    }
+    annotations::add_bookmark(&mut conn, new_bookmark).map_err(command_error("Cannot add bookmark"))
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn remove_bookmark(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-
-    let res = diesel::delete(schema::bookmark::table.filter(schema::bookmark::id.eq(id)))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete bookmark")),
-    }
+    annotations::remove_bookmark(&mut conn, &id).map_err(command_error("Cannot delete bookmark"))
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn update_bookmark(id: String, display_text: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-
-    let res = diesel::update(schema::bookmark::table.filter(schema::bookmark::id.eq(id)))
-        .set(schema::bookmark::display_text.eq(display_text))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update bookmark")),
-    }
+    annotations::update_bookmark(&mut conn, &id, &display_text)
+        .map_err(command_error("Cannot update bookmark"))
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn get_languages() -> Vec<models::Language> {
     let mut conn: SqliteConnection = establish_connection();
-    schema::language::table
-        .select(models::Language::as_select())
-        .get_results(&mut conn)
-        .unwrap()
+    books::get_languages(&mut conn).unwrap()
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn add_book_settings(new_book_settings: models::BookSettings) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::insert_into(schema::book_settings::table)
-        .values(&new_book_settings)
-        .on_conflict(schema::book_settings::book_id)
-        .do_update()
-        .set(&new_book_settings)
-        .execute(&mut conn);
+    let res = settings::add_book_settings(&mut conn, new_book_settings);
 
     match res {
-        Ok(_) => return Ok(()),
+        Ok(_) => Ok(()),
         Err(e) => {
             println!("{e}");
-            return Err(String::from("Cannot add book settings"));
+            Err(String::from("Cannot add book settings"))
         }
     }
 }
@@ -137,13 +97,8 @@ pub fn add_book_settings(new_book_settings: models::BookSettings) -> Result<(),
 #[specta::specta]
 pub fn remove_book_settings(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::delete(schema::book_settings::table.filter(schema::book_settings::id.eq(id)))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete book settings")),
-    }
+    settings::remove_book_settings(&mut conn, &id)
+        .map_err(command_error("Cannot delete book settings"))
 }
 
 #[tauri::command]
@@ -153,750 +108,636 @@ pub fn update_book_settings(
     new_book_settings: models::BookSettings,
 ) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::update(
-        schema::book_settings::table.filter(schema::book_settings::book_id.eq(book_id)),
-    )
-    .set(&new_book_settings)
-    .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update book settings")),
-    }
+    settings::update_book_settings(&mut conn, &book_id, new_book_settings)
+        .map_err(command_error("Cannot update book settings"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_reader_themes() -> Vec<models::ReaderTheme> {
+pub fn get_reader_settings_defaults() -> Vec<models::ReaderSettingsDefault> {
     let mut conn: SqliteConnection = establish_connection();
-
-    let themes: Vec<models::ReaderTheme> = schema::reader_theme::table
-        .select(models::ReaderTheme::as_select())
-        .get_results(&mut conn)
-        .unwrap();
-
-    themes
+    settings::get_reader_settings_defaults(&mut conn).unwrap()
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn add_reader_theme(new_reader_theme: models::ReaderTheme) -> Result<(), String> {
+pub fn add_reader_settings_default(
+    new_reader_settings_default: models::ReaderSettingsDefault,
+) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::insert_into(schema::reader_theme::table)
-        .values(&new_reader_theme)
-        .on_conflict(schema::reader_theme::id)
-        .do_update()
-        .set(&new_reader_theme)
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot add reader theme")),
-    }
+    settings::add_reader_settings_default(&mut conn, new_reader_settings_default)
+        .map_err(command_error("Cannot add reader settings defaults"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn remove_reader_theme(id: String) -> Result<(), String> {
+pub fn remove_reader_settings_default(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::delete(schema::reader_theme::table.filter(schema::reader_theme::id.eq(id)))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete reader theme")),
-    }
+    settings::remove_reader_settings_default(&mut conn, &id)
+        .map_err(command_error("Cannot delete reader settings defaults"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn update_reader_theme(id: String, name: String) -> Result<(), String> {
+pub fn get_effective_settings(book_id: String) -> Result<EffectiveBookSettings, String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::update(schema::reader_theme::table.filter(schema::reader_theme::id.eq(id)))
-        .set((schema::reader_theme::name.eq(name),))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update reader theme")),
-    }
+    conn.transaction(|conn| settings::get_effective_book_settings(conn, &book_id))
+        .map_err(command_error("Cannot get effective book settings"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_collections() -> Vec<models::Collection> {
+pub fn reset_book_settings_to_defaults(book_id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-
-    schema::collection::table
-        .select(models::Collection::as_select())
-        .order(schema::collection::sort_order)
-        .get_results(&mut conn)
-        .unwrap()
+    settings::reset_book_settings_to_defaults(&mut conn, &book_id)
+        .map_err(command_error("Cannot reset book settings"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn add_collection(new_collection: models::Collection) -> Result<(), String> {
+pub fn get_global_user_css() -> Result<Option<String>, String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::insert_into(schema::collection::table)
-        .values(&new_collection)
-        .on_conflict(schema::collection::id)
-        .do_update()
-        .set(&new_collection)
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot add collection")),
-    }
+    settings::get_app_setting(&mut conn, USER_CSS_SETTING_KEY)
+        .map_err(command_error("Cannot get global user css"))
 }
 
-#[derive(Serialize, Deserialize, Type)]
-pub struct CollectionIdWithSortOrder {
-    pub id: String,
-    pub sort_order: i32,
+#[tauri::command]
+#[specta::specta]
+pub fn update_global_user_css(user_css: Option<String>) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    settings::set_app_setting(&mut conn, USER_CSS_SETTING_KEY, user_css.as_deref())
+        .map_err(command_error("Cannot update global user css"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn reorder_collections(collections: Vec<CollectionIdWithSortOrder>) -> Result<(), String> {
+pub fn get_book_user_css(book_id: String) -> Result<Option<String>, String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = conn.transaction(|conn| {
-        for col in collections {
-            diesel::update(schema::collection::table.filter(schema::collection::id.eq(col.id)))
-                .set(schema::collection::sort_order.eq(col.sort_order))
-                .execute(conn)?;
-        }
-
-        diesel::result::QueryResult::Ok(())
-    });
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot reorder collections")),
-    }
+    settings::get_book_user_css(&mut conn, &book_id)
+        .map_err(command_error("Cannot get book user css"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn update_collection_name(id: String, name: String) -> Result<(), String> {
+pub fn update_book_user_css(book_id: String, user_css: Option<String>) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
+    settings::update_book_user_css(&mut conn, &book_id, user_css.as_deref())
+        .map_err(command_error("Cannot update book user css"))
+}
 
-    let res = diesel::update(schema::collection::table.filter(schema::collection::id.eq(id)))
-        .set(schema::collection::name.eq(name))
-        .execute(&mut conn);
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update collection")),
-    }
+#[derive(Serialize, Type)]
+pub struct EpubStylesheet {
+    path: String,
+    disabled: bool,
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn remove_collection(id: String) -> Result<(), String> {
+pub fn get_book_stylesheets(book_id: String) -> Result<Vec<EpubStylesheet>, String> {
     let mut conn: SqliteConnection = establish_connection();
 
-    let res = conn.transaction(|conn| {
-        diesel::delete(
-            schema::book_collection_link::table
-                .filter(schema::book_collection_link::collection_id.eq(id.clone())),
-        )
-        .execute(conn)?;
+    let path =
+        books::get_book_path(&mut conn, &book_id).map_err(|_| String::from("Cannot find book"))?;
 
-        diesel::delete(schema::collection::table.filter(schema::collection::id.eq(id)))
-            .execute(conn)?;
+    let disabled_paths = settings::get_disabled_stylesheets(&mut conn, &book_id)
+        .map_err(|_| String::from("Cannot get disabled stylesheets"))?;
 
-        diesel::result::QueryResult::Ok(())
-    });
+    let doc = EpubDoc::new(path).map_err(|_| String::from("Cannot read epub file"))?;
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete collection")),
-    }
+    let mut stylesheets: Vec<EpubStylesheet> = doc
+        .resources
+        .values()
+        .filter(|(_, mime)| mime == "text/css")
+        .map(|(path, _)| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            EpubStylesheet {
+                disabled: disabled_paths.contains(&path),
+                path,
+            }
+        })
+        .collect();
+    stylesheets.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(stylesheets)
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn reorder_books_in_collection(
-    book_collection_links: Vec<models::BookCollectionLink>,
+pub fn set_book_stylesheet_disabled(
+    book_id: String,
+    path: String,
+    disabled: bool,
 ) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = conn.transaction(|conn| {
-        for book_collection_link in book_collection_links {
-            diesel::update(
-                schema::book_collection_link::table
-                    .filter(schema::book_collection_link::book_id.eq(book_collection_link.book_id))
-                    .filter(
-                        schema::book_collection_link::collection_id
-                            .eq(book_collection_link.collection_id),
-                    ),
-            )
-            .set(schema::book_collection_link::sort_order.eq(book_collection_link.sort_order))
-            .execute(conn)?;
-        }
-
-        diesel::result::QueryResult::Ok(())
-    });
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot reorder books in collection")),
-    }
+    settings::set_book_stylesheet_disabled(&mut conn, &book_id, &path, disabled)
+        .map_err(command_error("Cannot update book stylesheet"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn add_book_to_collections(book_id: String, collection_ids: Vec<String>) -> Result<(), String> {
+pub fn get_reader_settings_profiles() -> Vec<models::ReaderSettingsProfile> {
     let mut conn: SqliteConnection = establish_connection();
+    settings::get_reader_settings_profiles(&mut conn).unwrap()
+}
 
-    let res = conn.transaction(|conn| {
-        let book_collection_links: Vec<models::BookCollectionLink> =
-            schema::book_collection_link::table
-                .filter(schema::book_collection_link::book_id.eq(book_id.clone()))
-                .select(models::BookCollectionLink::as_select())
-                .load(conn)?;
-
-        let old_collection_ids: Vec<String> = book_collection_links
-            .into_iter()
-            .map(|v| v.collection_id)
-            .collect();
-
-        let to_remove: Vec<String> = old_collection_ids
-            .clone()
-            .into_iter()
-            .filter(|v| !collection_ids.contains(v))
-            .collect();
-
-        let to_add: Vec<String> = collection_ids
-            .into_iter()
-            .filter(|v| !old_collection_ids.contains(v))
-            .collect();
-
-        for collection_id in to_remove {
-            diesel::delete(
-                schema::book_collection_link::table
-                    .filter(schema::book_collection_link::book_id.eq(book_id.clone()))
-                    .filter(schema::book_collection_link::collection_id.eq(collection_id)),
-            )
-            .execute(conn)?;
-        }
-
-        for collection_id in to_add {
-            let links: Vec<models::BookCollectionLink> = schema::book_collection_link::table
-                .filter(schema::book_collection_link::collection_id.eq(collection_id.clone()))
-                .select(models::BookCollectionLink::as_select())
-                .order(schema::book_collection_link::sort_order.desc())
-                .load(conn)?;
-            let first_link = links.first();
-
-            let count = match first_link {
-                Some(v) => v.sort_order,
-                None => Some(0),
-            };
-            let count = match count {
-                Some(v) => v,
-                None => 0,
-            };
-
-            let count: i32 = (count + 1).try_into().unwrap();
-
-            diesel::insert_into(schema::book_collection_link::table)
-                .values((
-                    schema::book_collection_link::book_id.eq(book_id.clone()),
-                    schema::book_collection_link::collection_id.eq(collection_id),
-                    schema::book_collection_link::sort_order.eq(count),
-                ))
-                .execute(conn)?;
-        }
-
-        diesel::result::QueryResult::Ok(())
-    });
-
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot add book to collection")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn add_reader_settings_profile(
+    new_reader_settings_profile: models::ReaderSettingsProfile,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    settings::add_reader_settings_profile(&mut conn, new_reader_settings_profile)
+        .map_err(command_error("Cannot add reader settings profile"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn remove_book_from_collection(book_id: String, collection_id: String) -> Result<(), String> {
+pub fn update_reader_settings_profile(
+    reader_settings_profile: models::ReaderSettingsProfile,
+) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
+    settings::update_reader_settings_profile(&mut conn, reader_settings_profile)
+        .map_err(command_error("Cannot update reader settings profile"))
+}
 
-    let res = diesel::delete(
-        schema::book_collection_link::table.filter(
-            schema::book_collection_link::collection_id
-                .eq(collection_id)
-                .and(schema::book_collection_link::book_id.eq(book_id)),
-        ),
-    )
-    .execute(&mut conn);
+#[tauri::command]
+#[specta::specta]
+pub fn remove_reader_settings_profile(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    settings::remove_reader_settings_profile(&mut conn, &id)
+        .map_err(command_error("Cannot delete reader settings profile"))
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot remove book from collection")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn apply_reader_settings_profile(
+    id: String,
+    target: ReaderSettingsProfileTarget,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    settings::apply_reader_settings_profile(&mut conn, &id, target)
+        .map_err(command_error("Cannot apply reader settings profile"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn add_highlight(new_highlight: models::Highlight) -> Result<(), String> {
+pub fn get_reader_themes() -> Vec<models::ReaderTheme> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::insert_into(schema::highlight::table)
-        .values(&new_highlight)
-        .on_conflict(schema::highlight::id)
-        .do_update()
-        .set(&new_highlight)
-        .execute(&mut conn);
+    themes::get_reader_themes(&mut conn).unwrap()
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot add highlight")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn add_reader_theme(new_reader_theme: models::ReaderTheme) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    themes::add_reader_theme(&mut conn, new_reader_theme)
+        .map_err(command_error("Cannot add reader theme"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn remove_highlight(id: String) -> Result<(), String> {
+pub fn remove_reader_theme(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::delete(schema::highlight::table.filter(schema::highlight::id.eq(id)))
-        .execute(&mut conn);
+    themes::remove_reader_theme(&mut conn, &id).map_err(command_error("Cannot delete reader theme"))
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete highlight")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn update_reader_theme(reader_theme: models::ReaderTheme) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    themes::update_reader_theme(&mut conn, reader_theme)
+        .map_err(command_error("Cannot update reader theme"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn update_highlight(id: String, note: String, color: String) -> Result<(), String> {
+pub fn set_book_reader_theme(
+    book_id: String,
+    reader_theme_id: Option<String>,
+) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::update(schema::highlight::table.filter(schema::highlight::id.eq(id)))
-        .set((
-            schema::highlight::note.eq(note),
-            schema::highlight::color.eq(color),
-        ))
-        .execute(&mut conn);
+    themes::set_book_reader_theme(&mut conn, &book_id, reader_theme_id.as_deref())
+        .map_err(command_error("Cannot set book reader theme"))
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update highlight")),
-    }
+#[derive(Serialize, Deserialize)]
+struct ReaderThemeFile {
+    name: String,
+    background_color: String,
+    color: String,
+    link_color: String,
+    primary_color: String,
+    image_blend_mode: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReaderThemeFileContents {
+    Many(Vec<ReaderThemeFile>),
+    One(ReaderThemeFile),
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_book(
-    id: String,
-) -> Option<BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections> {
+pub fn export_reader_themes(ids: Vec<String>, path: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
 
-    let books: Vec<models::Book> = schema::book::table
-        .filter(schema::book::id.eq(id))
-        .select(models::Book::as_select())
-        .get_results(&mut conn)
-        .unwrap();
-
-    let bookmarks: Vec<models::Bookmark> = models::Bookmark::belonging_to(&books)
-        .select(models::Bookmark::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let highlights: Vec<models::Highlight> = models::Highlight::belonging_to(&books)
-        .select(models::Highlight::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let settings: Vec<models::BookSettings> = models::BookSettings::belonging_to(&books)
-        .select(models::BookSettings::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let settings = settings.into_iter().nth(0);
-
-    let authors_with_book_link: Vec<(models::BookAuthorLink, models::Author)> =
-        models::BookAuthorLink::belonging_to(&books)
-            .inner_join(schema::author::table)
-            .select((
-                models::BookAuthorLink::as_select(),
-                models::Author::as_select(),
-            ))
-            .load::<(models::BookAuthorLink, models::Author)>(&mut conn)
-            .unwrap();
-
-    let collections_with_book_link: Vec<(models::BookCollectionLink, models::Collection)> =
-        models::BookCollectionLink::belonging_to(&books)
-            .inner_join(schema::collection::table)
-            .select((
-                models::BookCollectionLink::as_select(),
-                models::Collection::as_select(),
-            ))
-            .load::<(models::BookCollectionLink, models::Collection)>(&mut conn)
-            .unwrap();
-
-    let book = match books.into_iter().nth(0) {
-        Some(v) => v,
-        None => return None,
-    };
+    let themes = themes::get_reader_themes_by_ids(&mut conn, ids)
+        .map_err(|_| String::from("Cannot export reader themes"))?;
 
-    let path = Path::new("mikomi-data/covers").join(book.id.clone());
-
-    Some(
-        BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections {
-            book,
-            authors: authors_with_book_link.into_iter().map(|(_, a)| a).collect(),
-            bookmarks,
-            highlights,
-            cover: Some(String::from(path.to_string_lossy())),
-            settings,
-            collections: collections_with_book_link
-                .into_iter()
-                .map(|(_, c)| c)
-                .collect(),
-        },
-    )
+    let themes: Vec<ReaderThemeFile> = themes
+        .into_iter()
+        .map(|t| ReaderThemeFile {
+            name: t.name,
+            background_color: t.background_color,
+            color: t.color,
+            link_color: t.link_color,
+            primary_color: t.primary_color,
+            image_blend_mode: t.image_blend_mode,
+        })
+        .collect();
+
+    let json = serde_json::to_string_pretty(&themes)
+        .map_err(|_| String::from("Cannot export reader themes"))?;
+    fs::write(path, json).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_books() -> Vec<BookWithAuthorsAndCoverAndSettingsAndCollections> {
-    let mut conn: SqliteConnection = establish_connection();
+pub fn import_reader_themes(path: String) -> Result<Vec<models::ReaderTheme>, String> {
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let themes = match serde_json::from_str(&json) {
+        Ok(ReaderThemeFileContents::Many(v)) => v,
+        Ok(ReaderThemeFileContents::One(v)) => vec![v],
+        Err(_) => return Err(String::from("Invalid reader theme file")),
+    };
 
-    let all_books = schema::book::table
-        .select(models::Book::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let mut settings: Vec<models::BookSettings> = models::BookSettings::belonging_to(&all_books)
-        .select(models::BookSettings::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let mut collections_with_book_link: Vec<(models::BookCollectionLink, models::Collection)> =
-        models::BookCollectionLink::belonging_to(&all_books)
-            .inner_join(schema::collection::table)
-            .select((
-                models::BookCollectionLink::as_select(),
-                models::Collection::as_select(),
-            ))
-            .load::<(models::BookCollectionLink, models::Collection)>(&mut conn)
-            .unwrap();
-
-    let authors_with_book_link: Vec<(models::BookAuthorLink, models::Author)> =
-        models::BookAuthorLink::belonging_to(&all_books)
-            .inner_join(schema::author::table)
-            .select((
-                models::BookAuthorLink::as_select(),
-                models::Author::as_select(),
-            ))
-            .load::<(models::BookAuthorLink, models::Author)>(&mut conn)
-            .unwrap();
-
-    let books_with_authors: Vec<BookWithAuthors> = authors_with_book_link
-        .grouped_by(&all_books)
+    let themes: Vec<models::ReaderTheme> = themes
         .into_iter()
-        .zip(all_books)
-        .map(|(book_author_link_with_author, book)| BookWithAuthors {
-            book,
-            authors: book_author_link_with_author
-                .into_iter()
-                .map(|(_, author)| author)
-                .collect(),
+        .map(|t| models::ReaderTheme {
+            id: Uuid::new_v4().to_string(),
+            name: t.name,
+            background_color: t.background_color,
+            color: t.color,
+            link_color: t.link_color,
+            primary_color: t.primary_color,
+            image_blend_mode: t.image_blend_mode,
+            built_in: false,
         })
         .collect();
 
-    let books_with_authors_and_cover: Vec<BookWithAuthorsAndCoverAndSettingsAndCollections> =
-        books_with_authors
-            .into_iter()
-            .map(|book| {
-                let path = Path::new("mikomi-data/covers").join(book.book.id.clone());
-
-                let mut book_settings: Option<models::BookSettings> = None;
-                for setting in &mut settings {
-                    if setting.book_id == book.book.id {
-                        book_settings = Some(setting.clone());
-                        break;
-                    }
-                }
-
-                let mut book_collections: Vec<models::Collection> = vec![];
-                for book_collection_link_with_collection in &mut collections_with_book_link {
-                    if book_collection_link_with_collection.0.book_id == book.book.id {
-                        book_collections.push(book_collection_link_with_collection.1.clone());
-                    }
-                }
-
-                BookWithAuthorsAndCoverAndSettingsAndCollections {
-                    book: book.book,
-                    authors: book.authors,
-                    cover: Some(String::from(path.to_string_lossy())),
-                    settings: book_settings,
-                    collections: book_collections,
-                }
-            })
-            .collect();
-
-    books_with_authors_and_cover
-}
-
-#[derive(Serialize, Deserialize, Type)]
-pub struct BookWithCover {
+    let mut conn: SqliteConnection = establish_connection();
+    let res = themes::add_reader_themes(&mut conn, &themes);
+
+    match res {
+        Ok(_) => Ok(themes),
+        Err(_) => Err(String::from("Cannot import reader themes")),
+    }
+}
+
+#[derive(Serialize, Type)]
+pub struct FontWithPath {
     #[serde(flatten)]
-    pub book: models::Book,
-    pub cover: Option<String>,
+    font: models::Font,
+    path: String,
 }
-#[derive(Serialize, Deserialize, Type)]
-pub struct CollectionWithBooks {
-    pub collection: models::Collection,
-    pub books: Vec<BookWithCover>,
+
+fn font_path(font: &models::Font) -> std::path::PathBuf {
+    Path::new("mikomi-data/fonts").join(format!("{}.{}", font.id, font.format))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_books_belonging_to_collections(collection_id: String) -> CollectionWithBooks {
+pub fn get_fonts() -> Vec<FontWithPath> {
     let mut conn: SqliteConnection = establish_connection();
 
-    let collection = schema::collection::table
-        .filter(schema::collection::id.eq(collection_id))
-        .select(models::Collection::as_select())
-        .get_result(&mut conn)
-        .unwrap();
-
-    let books = models::BookCollectionLink::belonging_to(&collection)
-        .inner_join(schema::book::table)
-        .select(models::Book::as_select())
-        .order(schema::book_collection_link::sort_order)
-        .load(&mut conn)
-        .unwrap();
-
-    let books = books
+    mikomi_core::fonts::get_fonts(&mut conn)
+        .unwrap()
         .into_iter()
-        .map(|b| {
-            let path = Path::new("mikomi-data/covers").join(b.id.clone());
-            BookWithCover {
-                book: b,
-                cover: Some(String::from(path.to_string_lossy())),
+        .map(|font| {
+            let path = font_path(&font);
+            FontWithPath {
+                font,
+                path: String::from(path.to_string_lossy()),
             }
         })
-        .collect();
+        .collect()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn add_font_from_file(path: String) -> Result<FontWithPath, String> {
+    let source = Path::new(&path);
+    let format = fonts::font_format(source).ok_or(String::from("Unsupported font format"))?;
+    let data = fs::read(source).map_err(|_| String::from("Cannot read font file"))?;
+    let metadata = fonts::read_font_metadata(source, &data)?;
+
+    let font = models::Font {
+        id: Uuid::new_v4().to_string(),
+        family: metadata.family,
+        style: metadata.style,
+        weight: metadata.weight,
+        format,
+        date_added: current_timestamp(),
+    };
+
+    let font_path = font_path(&font);
+    fs::create_dir_all("mikomi-data/fonts").map_err(|e| e.to_string())?;
+    fs::write(&font_path, &data).map_err(|_| String::from("Error saving font file"))?;
 
-    CollectionWithBooks { collection, books }
+    let mut conn: SqliteConnection = establish_connection();
+    match mikomi_core::fonts::add_font(&mut conn, &font) {
+        Ok(_) => Ok(FontWithPath {
+            font,
+            path: String::from(font_path.to_string_lossy()),
+        }),
+        Err(_) => {
+            let _ = fs::remove_file(&font_path);
+            Err(String::from("Cannot add font"))
+        }
+    }
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_collections_and_their_books() -> Vec<CollectionWithBooks> {
+pub fn remove_font(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
+    let font = mikomi_core::fonts::remove_font(&mut conn, &id).map_err(|e| match e {
+        Error::NotFound => String::from("Cannot find font"),
+        _ => String::from("Cannot delete font"),
+    })?;
+    let _ = fs::remove_file(font_path(&font));
 
-    let all_collections = schema::collection::table
-        .order(schema::collection::sort_order)
-        .select(models::Collection::as_select())
-        .load(&mut conn)
-        .unwrap();
-
-    let books_with_collection_link: Vec<(models::BookCollectionLink, models::Book)> =
-        models::BookCollectionLink::belonging_to(&all_collections)
-            .inner_join(schema::book::table)
-            .order(schema::book_collection_link::sort_order)
-            .select((
-                models::BookCollectionLink::as_select(),
-                models::Book::as_select(),
-            ))
-            .load::<(models::BookCollectionLink, models::Book)>(&mut conn)
-            .unwrap();
-
-    let books_per_collection = books_with_collection_link
-        .grouped_by(&all_collections)
-        .into_iter()
-        .zip(all_collections)
-        .map(|(links, col)| {
-            let books = links
-                .into_iter()
-                .map(|(_, b)| {
-                    let path = Path::new("mikomi-data/covers").join(b.id.clone());
-                    BookWithCover {
-                        book: b,
-                        cover: Some(String::from(path.to_string_lossy())),
-                    }
-                })
-                .collect();
-
-            CollectionWithBooks {
-                collection: col,
-                books,
-            }
-        })
-        .collect();
+    Ok(())
+}
 
-    books_per_collection
+#[tauri::command]
+#[specta::specta]
+pub fn get_collections() -> Vec<models::Collection> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::get_collections(&mut conn).unwrap()
 }
 
-pub fn upsert_author(name: String) -> String {
-    let mut conn = establish_connection();
+#[tauri::command]
+#[specta::specta]
+pub fn add_collection(new_collection: models::Collection) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::add_collection(&mut conn, new_collection)
+        .map_err(command_error("Cannot add collection"))
+}
 
-    let id_result: Result<Vec<String>, diesel::result::Error> = schema::author::table
-        .filter(schema::author::name.eq(name.clone()))
-        .select(schema::author::id)
-        .load::<String>(&mut conn);
+#[tauri::command]
+#[specta::specta]
+pub fn reorder_collections(collections: Vec<CollectionIdWithSortOrder>) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::reorder_collections(&mut conn, collections)
+        .map_err(command_error("Cannot reorder collections"))
+}
 
-    let id = id_result.unwrap();
-    let first = id.first();
-    let id;
+#[tauri::command]
+#[specta::specta]
+pub fn update_collection_name(id: String, name: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::update_collection_name(&mut conn, &id, &name)
+        .map_err(command_error("Cannot update collection"))
+}
 
-    match first {
-        Some(v) => id = v.clone(),
-        None => {
-            let new_id = Uuid::new_v4().to_string();
+#[tauri::command]
+#[specta::specta]
+pub fn remove_collection(id: String, policy: Option<RemoveCollectionPolicy>) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::remove_collection(&mut conn, &id, policy)
+        .map_err(command_error("Cannot delete collection"))
+}
 
-            let new_author = models::Author {
-                name: name.clone(),
-                id: new_id.clone(),
-            };
+#[tauri::command]
+#[specta::specta]
+pub fn move_collection(
+    id: String,
+    parent_id: Option<String>,
+    sort_order: Option<i32>,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::move_collection(&mut conn, &id, parent_id.as_deref(), sort_order)
+        .map_err(command_error("Cannot move collection"))
+}
 
-            diesel::insert_into(schema::author::table)
-                .values(&new_author)
-                .execute(&mut conn)
-                .expect("Error adding new author");
+#[tauri::command]
+#[specta::specta]
+pub fn get_collection_tree() -> Result<Vec<CollectionTreeNode>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::get_collection_tree(&mut conn).map_err(command_error("Cannot get collection tree"))
+}
 
-            id = new_id;
-        }
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn reorder_books_in_collection(
+    book_collection_links: Vec<models::BookCollectionLink>,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::reorder_books_in_collection(&mut conn, book_collection_links)
+        .map_err(command_error("Cannot reorder books in collection"))
+}
 
-    id
+#[tauri::command]
+#[specta::specta]
+pub fn add_book_to_collections(book_id: String, collection_ids: Vec<String>) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::add_book_to_collections(&mut conn, &book_id, collection_ids)
+        .map_err(command_error("Cannot add book to collection"))
 }
 
-fn insert_book_author_link(book_id: String, author_id: String, primary: bool) {
-    let mut conn = establish_connection();
-    let new_book_author_link = models::BookAuthorLink {
-        book_id,
-        author_id,
-        primary_creator: primary,
-    };
+#[tauri::command]
+#[specta::specta]
+pub fn remove_book_from_collection(book_id: String, collection_id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::remove_book_from_collection(&mut conn, &book_id, &collection_id)
+        .map_err(command_error("Cannot remove book from collection"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn add_highlight(new_highlight: models::Highlight) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::add_highlight(&mut conn, new_highlight)
+        .map_err(command_error("Cannot add highlight"))
+}
 
-    diesel::insert_into(schema::book_author_link::table)
-        .values(&new_book_author_link)
-        .execute(&mut conn)
-        .expect("Error adding new book");
+#[tauri::command]
+#[specta::specta]
+pub fn remove_highlight(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::remove_highlight(&mut conn, &id).map_err(command_error("Cannot delete highlight"))
 }
 
-fn write_cover_to_file(
-    cover_data: (Vec<u8>, String),
-    path: std::path::PathBuf,
+#[tauri::command]
+#[specta::specta]
+pub fn update_highlight(
+    id: String,
+    note: String,
+    color: String,
+    highlight_color_id: Option<String>,
 ) -> Result<(), String> {
-    let res = fs::create_dir_all("mikomi-data/covers");
-    match res {
-        Ok(_) => (),
-        Err(e) => return Err(e.to_string()),
-    }
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::update_highlight(&mut conn, &id, &note, &color, highlight_color_id.as_deref())
+        .map_err(command_error("Cannot update highlight"))
+}
 
-    let res = File::create(path);
-    let mut file;
-    match res {
-        Ok(f) => file = f,
-        Err(e) => return Err(e.to_string()),
-    }
-    let cover = cover_data.0;
-    let res = file.write_all(&cover);
-    match res {
-        Ok(_) => (),
-        Err(e) => return Err(e.to_string()),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn get_highlight_notes(highlight_id: String) -> Result<Vec<models::HighlightNote>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::get_highlight_notes(&mut conn, &highlight_id)
+        .map_err(command_error("Cannot get highlight notes"))
+}
 
-    Ok(())
+#[tauri::command]
+#[specta::specta]
+pub fn get_highlight_note_revisions(
+    highlight_note_id: String,
+) -> Result<Vec<models::HighlightNoteRevision>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::get_highlight_note_revisions(&mut conn, &highlight_note_id)
+        .map_err(command_error("Cannot get highlight note revisions"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub async fn add_book_from_file(path: String) -> Result<models::Book, String> {
+pub fn add_highlight_note(new_highlight_note: models::HighlightNote) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
+    annotations::add_highlight_note(&mut conn, new_highlight_note)
+        .map_err(command_error("Cannot add highlight note"))
+}
 
-    let mut doc = EpubDoc::new(path.clone()).map_err(|_| String::from("Cannot read epub file"))?;
+#[tauri::command]
+#[specta::specta]
+pub fn update_highlight_note(id: String, content: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::update_highlight_note(&mut conn, &id, &content)
+        .map_err(command_error("Cannot update highlight note"))
+}
 
-    let uuid = Uuid::new_v4().to_string();
+#[tauri::command]
+#[specta::specta]
+pub fn remove_highlight_note(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::remove_highlight_note(&mut conn, &id)
+        .map_err(command_error("Cannot delete highlight note"))
+}
 
-    let cover_op = doc.get_cover();
-    match cover_op {
-        Some(data) => {
-            match write_cover_to_file(
-                (data.0, data.1),
-                Path::new("mikomi-data/covers").join(uuid.clone()),
-            ) {
-                Ok(_) => (),
-                Err(_) => return Err(String::from("Error saving epub cover")),
-            }
-        }
-        None => return Err(String::from("No cover found in epub")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn get_highlights(
+    book_id: Option<String>,
+    highlight_color_ids: Vec<String>,
+) -> Result<Vec<models::Highlight>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::get_highlights(&mut conn, book_id.as_deref(), highlight_color_ids)
+        .map_err(command_error("Cannot get highlights"))
+}
 
-    let empty_vec = vec![];
-    let authors = doc.metadata.get("creator").unwrap_or(&empty_vec);
-    let mut author_ids: Vec<String> = vec![];
-    for a in authors {
-        let id = upsert_author(a.to_string());
-        author_ids.push(id);
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn get_highlights_grouped_by_color(
+    book_id: Option<String>,
+) -> Result<Vec<HighlightColorWithHighlights>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::get_highlights_grouped_by_color(&mut conn, book_id.as_deref())
+        .map_err(command_error("Cannot get highlights"))
+}
 
-    let title_res = doc.mdata("title");
-    let title;
-    match title_res {
-        Some(v) => title = v,
-        None => return Err(String::from("Epub does not have a title")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn get_highlight_colors() -> Vec<models::HighlightColor> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::get_highlight_colors(&mut conn).unwrap()
+}
 
-    let language = doc.mdata("language");
-    let description = doc.mdata("description");
-    let identifier = doc.mdata("identifier");
-    let last_modified = doc.mdata("dcterms:modified");
-    let published_date = doc.mdata("date");
-    let publisher = doc.mdata("publisher");
-
-    let start = SystemTime::now();
-
-    match language {
-        Some(v) => {
-            let _ = diesel::insert_into(schema::language::table)
-                .values(models::Language { name: v })
-                .on_conflict(schema::language::name)
-                .do_nothing()
-                .execute(&mut conn);
-        }
-        None => {}
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn add_highlight_color(new_highlight_color: models::HighlightColor) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::add_highlight_color(&mut conn, new_highlight_color)
+        .map_err(command_error("Cannot add highlight color"))
+}
 
-    let language = doc.mdata("language");
-    let new_book = models::Book {
-        title,
-        path,
-        id: uuid.clone(),
-        last_read: None,
-        date_added: start.duration_since(UNIX_EPOCH).unwrap().as_secs() as i32,
-        reading_status: String::from("Plan to read"),
-        language,
-        description,
-        identifier,
-        last_modified,
-        published_date,
-        publisher,
-        page_progression_direction: doc.page_progression_direction,
-    };
+#[tauri::command]
+#[specta::specta]
+pub fn update_highlight_color(id: String, name: String, color: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::update_highlight_color(&mut conn, &id, &name, &color)
+        .map_err(command_error("Cannot update highlight color"))
+}
 
-    let res = diesel::insert_into(schema::book::table)
-        .values(&new_book)
-        .execute(&mut conn);
+#[tauri::command]
+#[specta::specta]
+pub fn reorder_highlight_colors(
+    highlight_colors: Vec<HighlightColorIdWithSortOrder>,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::reorder_highlight_colors(&mut conn, highlight_colors)
+        .map_err(command_error("Cannot reorder highlight colors"))
+}
 
-    match res {
-        Ok(_) => (),
-        Err(_) => return Err(String::from("Cannot add epub to database")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn remove_highlight_color(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    annotations::remove_highlight_color(&mut conn, &id)
+        .map_err(command_error("Cannot delete highlight color"))
+}
 
-    for (i, author_id) in author_ids.iter().enumerate() {
-        let primary = i == 0;
-        insert_book_author_link(uuid.clone(), author_id.to_string(), primary);
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn get_book(
+    id: String,
+) -> Option<BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::get_book(&mut conn, &id, Path::new(COVERS_DIR)).unwrap()
+}
 
-    Ok(new_book)
+#[tauri::command]
+#[specta::specta]
+pub fn get_books() -> Vec<BookWithAuthorsAndCoverAndSettingsAndCollections> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::get_books(&mut conn, Path::new(COVERS_DIR)).unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_books_belonging_to_collections(collection_id: String) -> CollectionWithBooks {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::get_books_belonging_to_collection(&mut conn, &collection_id, Path::new(COVERS_DIR))
+        .unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_books_belonging_to_collection_tree(
+    collection_id: String,
+) -> Result<CollectionWithBooks, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::get_books_belonging_to_collection_tree(
+        &mut conn,
+        &collection_id,
+        Path::new(COVERS_DIR),
+    )
+    .map_err(command_error("Cannot get collection books"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_collections_and_their_books() -> Vec<CollectionWithBooks> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::get_collections_and_their_books(&mut conn, Path::new(COVERS_DIR)).unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn add_book_from_file(path: String) -> Result<models::Book, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    import::import_book(&mut conn, path, Path::new(COVERS_DIR))
+        .map_err(command_error("Cannot add epub to database"))
 }
 
 #[tauri::command]
@@ -920,84 +761,288 @@ pub async fn add_multiple_books_from_files(paths: Vec<String>) -> Result<(), Str
 #[specta::specta]
 pub fn update_book(book: models::Book) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::update(schema::book::table.filter(schema::book::id.eq(book.id.clone())))
-        .set(&book)
-        .execute(&mut conn);
+    books::update_book(&mut conn, book).map_err(command_error("Cannot update book"))
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update book")),
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn update_book_reading_status(
+    id: String,
+    reading_status: models::ReadingStatus,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::update_book_reading_status(&mut conn, &id, reading_status)
+        .map_err(command_error("Cannot update book"))
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn update_book_reading_status(id: String, reading_status: String) -> Result<(), String> {
+pub fn remove_book(id: String) -> Result<(), String> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = diesel::update(schema::book::table.filter(schema::book::id.eq(id)))
-        .set(schema::book::reading_status.eq(reading_status))
-        .execute(&mut conn);
+    books::remove_book(&mut conn, &id).map_err(command_error("Cannot delete book"))
+}
 
-    match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot update book")),
+// Books that were imported from a file elsewhere are left alone, but the files that the app
+// downloaded itself are removed with their book
+fn remove_downloaded_book_file(path: &str) {
+    if Path::new(path).starts_with(DOWNLOADED_BOOKS_DIR) {
+        let _ = fs::remove_file(path);
     }
 }
 
+// Removes the covers and downloaded files of the purged books and returns their ids
+fn remove_purged_book_files(books: Vec<(String, String)>) -> Vec<String> {
+    for (book_id, path) in &books {
+        let _ = fs::remove_file(Path::new(COVERS_DIR).join(book_id));
+        remove_downloaded_book_file(path);
+    }
+
+    books.into_iter().map(|(book_id, _)| book_id).collect()
+}
+
+pub fn purge_expired_trash(conn: &mut SqliteConnection) -> mikomi_core::Result<Vec<String>> {
+    books::purge_expired_trash(conn).map(remove_purged_book_files)
+}
+
 #[tauri::command]
 #[specta::specta]
-pub fn remove_book(id: String) -> Result<(), String> {
+pub fn get_trashed_books() -> Vec<BookWithCover> {
     let mut conn: SqliteConnection = establish_connection();
-    let res = conn.transaction(|conn| {
-        diesel::delete(
-            schema::book_collection_link::table
-                .filter(schema::book_collection_link::book_id.eq(&id)),
-        )
-        .execute(conn)?;
-        diesel::delete(
-            schema::book_author_link::table.filter(schema::book_author_link::book_id.eq(&id)),
-        )
-        .execute(conn)?;
-        diesel::delete(schema::bookmark::table.filter(schema::bookmark::book_id.eq(&id)))
-            .execute(conn)?;
-        diesel::delete(schema::highlight::table.filter(schema::highlight::book_id.eq(&id)))
-            .execute(conn)?;
-        diesel::delete(schema::book_settings::table.filter(schema::book_settings::book_id.eq(&id)))
-            .execute(conn)?;
-        diesel::delete(schema::book::table.filter(schema::book::id.eq(&id))).execute(conn)?;
-
-        diesel::result::QueryResult::Ok(())
-    });
+    books::get_trashed_books(&mut conn, Path::new(COVERS_DIR)).unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn restore_books(book_ids: Vec<String>) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::restore_books(&mut conn, book_ids).map_err(command_error("Cannot restore books"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_book_permanently(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let res = books::remove_book_permanently(&mut conn, &id);
 
     match res {
-        Ok(_) => {}
+        Ok(path) => remove_downloaded_book_file(&path),
         Err(_) => return Err(String::from("Cannot delete book")),
     }
 
-    let cover_path = Path::new("mikomi-data/covers").join(&id);
+    let cover_path = Path::new(COVERS_DIR).join(&id);
     let res = fs::remove_file(cover_path);
 
     match res {
-        Ok(_) => return Ok(()),
-        Err(_) => return Err(String::from("Cannot delete book")),
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot delete book")),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{establish_connection, run_migrations};
-
-    #[test]
-    fn it_establishes_a_connection() {
-        establish_connection();
-    }
+#[tauri::command]
+#[specta::specta]
+pub fn empty_trash() -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let res = books::purge_trashed_books(&mut conn, None);
 
-    #[test]
-    fn it_runs_migrations() {
-        let res = run_migrations(&mut establish_connection());
-        match res {
-            Ok(_) => (),
-            Err(_) => panic!(),
+    match res {
+        Ok(books) => {
+            remove_purged_book_files(books);
+            Ok(())
         }
+        Err(_) => Err(String::from("Cannot empty trash")),
     }
 }
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_trash_retention_period() -> Result<i32, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::get_trash_retention_days(&mut conn).map_err(command_error("Cannot get trash retention"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_trash_retention_period(days: i32) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::set_trash_retention_days(&mut conn, days)
+        .map_err(command_error("Cannot update trash retention"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_update_reading_status(
+    book_ids: Vec<String>,
+    reading_status: models::ReadingStatus,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::bulk_update_reading_status(&mut conn, book_ids, reading_status)
+        .map_err(command_error("Cannot update book"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_add_books_to_collections(
+    book_ids: Vec<String>,
+    collection_ids: Vec<String>,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::bulk_add_books_to_collections(&mut conn, book_ids, collection_ids)
+        .map_err(command_error("Cannot add book to collection"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_remove_books_from_collections(
+    book_ids: Vec<String>,
+    collection_ids: Vec<String>,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    collections::bulk_remove_books_from_collections(&mut conn, book_ids, collection_ids)
+        .map_err(command_error("Cannot remove book from collection"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_update_book_settings(
+    book_ids: Vec<String>,
+    settings: BulkBookSettings,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    settings::bulk_update_book_settings(&mut conn, book_ids, settings)
+        .map_err(command_error("Cannot update book settings"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_update_book_language(
+    book_ids: Vec<String>,
+    language: Option<String>,
+) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::bulk_update_book_language(&mut conn, book_ids, language)
+        .map_err(command_error("Cannot update book"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn bulk_remove_books(book_ids: Vec<String>) -> Result<Vec<BulkOperationResult>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    books::bulk_remove_books(&mut conn, book_ids).map_err(command_error("Cannot delete book"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn start_reading_session(
+    book_id: String,
+    percentage: Option<i32>,
+) -> Result<models::ReadingSession, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::start_reading_session(&mut conn, book_id, percentage)
+        .map_err(command_error("Cannot start reading session"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn heartbeat_reading_session(
+    id: String,
+    percentage: Option<i32>,
+    pages_read: i32,
+    characters_read: i32,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::heartbeat_reading_session(&mut conn, &id, percentage, pages_read, characters_read)
+        .map_err(command_error("Cannot update reading session"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn end_reading_session(
+    id: String,
+    percentage: Option<i32>,
+    pages_read: i32,
+    characters_read: i32,
+) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::end_reading_session(&mut conn, &id, percentage, pages_read, characters_read)
+        .map_err(command_error("Cannot end reading session"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_time_per_day(from: i32, to: i32) -> Result<Vec<ReadingTimePerDay>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_reading_time_per_day(&mut conn, from, to)
+        .map_err(command_error("Cannot get reading time per day"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_time_per_book() -> Result<Vec<ReadingTimePerBook>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_reading_time_per_book(&mut conn)
+        .map_err(command_error("Cannot get reading time per book"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_time_per_author() -> Result<Vec<ReadingTimePerAuthor>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_reading_time_per_author(&mut conn)
+        .map_err(command_error("Cannot get reading time per author"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_book_reading_statistics(book_id: String) -> Result<BookReadingStatistics, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_book_reading_statistics(&mut conn, book_id)
+        .map_err(command_error("Cannot get book reading statistics"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_status_events(
+    book_id: String,
+) -> Result<Vec<models::ReadingStatusEvent>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_reading_status_events(&mut conn, &book_id)
+        .map_err(command_error("Cannot get reading status events"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_histories() -> Result<Vec<ReadingHistory>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    sessions::get_reading_histories(&mut conn)
+        .map_err(command_error("Cannot get reading histories"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_goals() -> Vec<models::ReadingGoal> {
+    let mut conn: SqliteConnection = establish_connection();
+    goals::get_reading_goals(&mut conn).unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn add_reading_goal(new_reading_goal: models::ReadingGoal) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    goals::add_reading_goal(&mut conn, new_reading_goal)
+        .map_err(command_error("Cannot add reading goal"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_reading_goal(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    goals::remove_reading_goal(&mut conn, &id).map_err(command_error("Cannot delete reading goal"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_reading_goal_progress(year: i32) -> Result<ReadingGoalProgress, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    goals::get_reading_goal_progress(&mut conn, year)
+        .map_err(command_error("Cannot get reading goal progress"))
+}
@@ -0,0 +1,242 @@
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::path::Path;
+
+const WOFF_SIGNATURE: u32 = 0x774F4646;
+const WOFF_HEADER_LENGTH: usize = 44;
+const WOFF_TABLE_ENTRY_LENGTH: usize = 20;
+const SFNT_TABLE_ENTRY_LENGTH: usize = 16;
+// Upper bound for the decompressed font so that a forged header cannot make us allocate
+// or inflate more than any real font needs
+const MAX_SFNT_SIZE: usize = 32 * 1024 * 1024;
+
+pub struct FontMetadata {
+    pub family: String,
+    pub style: String,
+    pub weight: i32,
+}
+
+pub fn font_format(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "ttf" | "otf" | "woff" => Some(extension),
+        _ => None,
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset.checked_add(2)?)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset.checked_add(4)?)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Rebuilds the sfnt (TTF/OTF) data wrapped by a WOFF 1.0 file so that it can be parsed
+fn woff_to_sfnt(data: &[u8]) -> Option<Vec<u8>> {
+    if read_u32(data, 0)? != WOFF_SIGNATURE {
+        return None;
+    }
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)? as usize;
+    if num_tables > (u16::MAX as usize) / SFNT_TABLE_ENTRY_LENGTH {
+        return None;
+    }
+
+    let mut entry_selector: u16 = 0;
+    while (1usize << (entry_selector + 1)) <= num_tables {
+        entry_selector += 1;
+    }
+    let search_range: u16 = 1u16
+        .checked_shl(entry_selector as u32)?
+        .checked_mul(SFNT_TABLE_ENTRY_LENGTH as u16)?;
+    let range_shift: u16 = (num_tables as u16)
+        .checked_mul(SFNT_TABLE_ENTRY_LENGTH as u16)?
+        .saturating_sub(search_range);
+
+    let mut sfnt_size = 12 + SFNT_TABLE_ENTRY_LENGTH * num_tables;
+    let mut tables: Vec<(u32, u32, Vec<u8>)> = vec![];
+    for i in 0..num_tables {
+        let entry = WOFF_HEADER_LENGTH + i * WOFF_TABLE_ENTRY_LENGTH;
+        let tag = read_u32(data, entry)?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let compressed_length = read_u32(data, entry + 8)? as usize;
+        let original_length = read_u32(data, entry + 12)? as usize;
+        let checksum = read_u32(data, entry + 16)?;
+
+        sfnt_size = sfnt_size.checked_add((original_length.checked_add(3)?) & !3)?;
+        if sfnt_size > MAX_SFNT_SIZE {
+            return None;
+        }
+
+        let table = data.get(offset..offset.checked_add(compressed_length)?)?;
+        let table = if compressed_length < original_length {
+            let mut decompressed = Vec::with_capacity(original_length);
+            ZlibDecoder::new(table)
+                .take(original_length as u64 + 1)
+                .read_to_end(&mut decompressed)
+                .ok()?;
+            if decompressed.len() != original_length {
+                return None;
+            }
+            decompressed
+        } else if compressed_length == original_length {
+            table.to_vec()
+        } else {
+            return None;
+        };
+        tables.push((tag, checksum, table));
+    }
+
+    let mut sfnt: Vec<u8> = Vec::with_capacity(sfnt_size);
+    sfnt.extend_from_slice(&flavor.to_be_bytes());
+    sfnt.extend_from_slice(&(num_tables as u16).to_be_bytes());
+    sfnt.extend_from_slice(&search_range.to_be_bytes());
+    sfnt.extend_from_slice(&entry_selector.to_be_bytes());
+    sfnt.extend_from_slice(&range_shift.to_be_bytes());
+
+    let mut offset = 12 + SFNT_TABLE_ENTRY_LENGTH * num_tables;
+    for (tag, checksum, table) in &tables {
+        sfnt.extend_from_slice(&tag.to_be_bytes());
+        sfnt.extend_from_slice(&checksum.to_be_bytes());
+        sfnt.extend_from_slice(&(offset as u32).to_be_bytes());
+        sfnt.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += (table.len() + 3) & !3;
+    }
+    for (_, _, table) in &tables {
+        sfnt.extend_from_slice(table);
+        sfnt.resize((sfnt.len() + 3) & !3, 0);
+    }
+
+    Some(sfnt)
+}
+
+fn read_sfnt_metadata(data: &[u8]) -> Option<FontMetadata> {
+    let face = ttf_parser::Face::parse(data, 0).ok()?;
+
+    let names: Vec<ttf_parser::name::Name> = face.names().into_iter().collect();
+    let family = [
+        ttf_parser::name_id::TYPOGRAPHIC_FAMILY,
+        ttf_parser::name_id::FAMILY,
+    ]
+    .iter()
+    .find_map(|name_id| {
+        names
+            .iter()
+            .filter(|n| n.name_id == *name_id)
+            .find_map(|n| n.to_string())
+    })?;
+
+    let style = match face.style() {
+        ttf_parser::Style::Normal => "normal",
+        ttf_parser::Style::Italic => "italic",
+        ttf_parser::Style::Oblique => "oblique",
+    };
+
+    Some(FontMetadata {
+        family,
+        style: String::from(style),
+        weight: face.weight().to_number() as i32,
+    })
+}
+
+pub fn read_font_metadata(path: &Path, data: &[u8]) -> Result<FontMetadata, String> {
+    let format = font_format(path).ok_or(String::from("Unsupported font format"))?;
+
+    let metadata = match format.as_str() {
+        "woff" => woff_to_sfnt(data).and_then(|sfnt| read_sfnt_metadata(&sfnt)),
+        _ => read_sfnt_metadata(data),
+    };
+
+    metadata.ok_or(String::from("Cannot read font file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_font_metadata, woff_to_sfnt, MAX_SFNT_SIZE, WOFF_HEADER_LENGTH};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    fn set_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn set_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    #[test]
+    fn reads_metadata_from_ttf_and_woff() {
+        for name in ["font.ttf", "font.woff"] {
+            let metadata = read_font_metadata(Path::new(name), &fixture(name)).unwrap();
+
+            assert_eq!(metadata.family, "Mikomi Test");
+            assert_eq!(metadata.style, "italic");
+            assert_eq!(metadata.weight, 700);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_fonts() {
+        for name in ["font.ttf", "font.woff"] {
+            let data = fixture(name);
+            for len in [0, 10, WOFF_HEADER_LENGTH, data.len() - 8] {
+                assert!(read_font_metadata(Path::new(name), &data[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_woff_with_oversized_header_fields() {
+        let woff = fixture("font.woff");
+        let first_entry = WOFF_HEADER_LENGTH;
+
+        let mut too_many_tables = woff.clone();
+        set_u16(&mut too_many_tables, 12, u16::MAX);
+        assert!(woff_to_sfnt(&too_many_tables).is_none());
+
+        let mut huge_table = woff.clone();
+        set_u32(&mut huge_table, first_entry + 12, u32::MAX);
+        assert!(woff_to_sfnt(&huge_table).is_none());
+
+        let mut huge_offset = woff;
+        set_u32(&mut huge_offset, first_entry + 4, u32::MAX - 1);
+        assert!(woff_to_sfnt(&huge_offset).is_none());
+    }
+
+    #[test]
+    fn rejects_woff_tables_inflating_past_their_length() {
+        let mut woff = fixture("font.woff");
+        let first_entry = WOFF_HEADER_LENGTH;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![0; MAX_SFNT_SIZE]).unwrap();
+        let bomb = encoder.finish().unwrap();
+
+        let offset = woff.len() as u32;
+        woff.extend_from_slice(&bomb);
+        set_u32(&mut woff, first_entry + 4, offset);
+        set_u32(&mut woff, first_entry + 8, bomb.len() as u32);
+        set_u32(&mut woff, first_entry + 12, bomb.len() as u32 + 1);
+
+        assert!(woff_to_sfnt(&woff).is_none());
+    }
+
+    #[test]
+    fn rejects_woff2() {
+        assert!(read_font_metadata(Path::new("font.woff2"), &fixture("font.woff")).is_err());
+    }
+}
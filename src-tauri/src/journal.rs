@@ -0,0 +1,24 @@
+use crate::db::{command_error, establish_connection};
+use diesel::SqliteConnection;
+use mikomi_core::journal::{self, JournalEntrySummary};
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_journal_entries() -> Vec<JournalEntrySummary> {
+    let mut conn: SqliteConnection = establish_connection();
+    journal::get_journal_entries(&mut conn).unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn undo_last() -> Result<Option<String>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    journal::undo_last(&mut conn).map_err(command_error("Cannot read journal"))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn redo() -> Result<Option<String>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    journal::redo(&mut conn).map_err(command_error("Cannot read journal"))
+}
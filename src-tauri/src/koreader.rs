@@ -0,0 +1,679 @@
+use crate::db::establish_connection;
+use crate::models;
+use crate::schema;
+use crate::sync::get_device_id;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use epub::doc::EpubDoc;
+use md5::{Digest, Md5};
+use mikomi_core::settings::{get_app_setting, set_app_setting, set_book_progress};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const KOREADER_SYNC_SETTING_KEY: &str = "koreader_sync";
+const KOREADER_ACCEPT_HEADER: &str = "application/vnd.koreader.v1+json";
+const KOREADER_DEVICE_NAME: &str = "Mikomi";
+const PARTIAL_MD5_STEP: u64 = 1024;
+const PARTIAL_MD5_SIZE: usize = 1024;
+
+// How KOReader identifies a document on the server. It has to be the same method that is
+// chosen in KOReader for the positions to be matched.
+#[derive(Serialize, Deserialize, Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentMatching {
+    Binary,
+    FileName,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KOReaderAccount {
+    server_url: String,
+    username: String,
+    // The MD5 hash of the password, which is what KOReader sends as the key
+    userkey: String,
+    document_matching: DocumentMatching,
+}
+
+#[derive(Serialize, Type)]
+pub struct KOReaderSyncSettings {
+    pub server_url: String,
+    pub username: String,
+    pub document_matching: DocumentMatching,
+}
+
+#[derive(Serialize, Type)]
+pub struct KOReaderProgress {
+    pub percentage: i32,
+    pub device: Option<String>,
+    pub timestamp: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct ProgressUpdate<'a> {
+    document: &'a str,
+    progress: &'a str,
+    percentage: f64,
+    device: &'a str,
+    device_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RemoteProgress {
+    progress: Option<String>,
+    percentage: Option<f64>,
+    device: Option<String>,
+    timestamp: Option<i64>,
+}
+
+fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", Md5::digest(data))
+}
+
+// KOReader's "binary" document hash, which is the MD5 of 1 KiB samples taken at
+// exponentially growing offsets so that large files do not have to be read entirely
+fn partial_md5(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buf = [0u8; PARTIAL_MD5_SIZE];
+
+    for i in -1..=10 {
+        // KOReader computes the offsets with 32-bit shifts, so the first sample is at 0
+        let offset = if i < 0 {
+            0
+        } else {
+            PARTIAL_MD5_STEP << (2 * i)
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn document_hash(book: &models::Book, matching: DocumentMatching) -> Result<String, String> {
+    let path = Path::new(&book.path);
+    match matching {
+        DocumentMatching::Binary => {
+            partial_md5(path).map_err(|_| String::from("Cannot read epub file"))
+        }
+        DocumentMatching::FileName => path
+            .file_name()
+            .map(|name| md5_hex(name.to_string_lossy().as_bytes()))
+            .ok_or(String::from("Cannot read epub file")),
+    }
+}
+
+// Equivalent of the `CSS.escape` that the reader uses to build its selectors
+fn css_escape(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut escaped = String::new();
+
+    for (i, c) in chars.iter().copied().enumerate() {
+        if c == '\0' {
+            escaped.push('\u{FFFD}');
+        } else if ('\u{1}'..='\u{1f}').contains(&c)
+            || c == '\u{7f}'
+            || (i == 0 && c.is_ascii_digit())
+            || (i == 1 && c.is_ascii_digit() && chars[0] == '-')
+        {
+            escaped.push_str(&format!("\\{:x} ", c as u32));
+        } else if i == 0 && c == '-' && chars.len() == 1 {
+            escaped.push_str("\\-");
+        } else if c as u32 >= 0x80 || c == '-' || c == '_' || c.is_ascii_alphanumeric() {
+            escaped.push(c);
+        } else {
+            escaped.push('\\');
+            escaped.push(c);
+        }
+    }
+
+    escaped
+}
+
+struct Section {
+    path: String,
+    content: Vec<u8>,
+}
+
+// The reader wraps every spine document in an element whose id is the document's path
+fn read_sections(book: &models::Book) -> Result<Vec<Section>, String> {
+    let mut doc = EpubDoc::new(&book.path).map_err(|_| String::from("Cannot read epub file"))?;
+
+    let spine = doc.spine.clone();
+    let mut sections: Vec<Section> = vec![];
+    for id in spine {
+        let Some((path, _)) = doc.resources.get(&id).cloned() else {
+            continue;
+        };
+        let content = doc.get_resource(&id).map(|(v, _)| v).unwrap_or_default();
+        sections.push(Section {
+            path: path.to_string_lossy().replace('\\', "/"),
+            content,
+        });
+    }
+
+    if sections.is_empty() {
+        return Err(String::from("Cannot read epub file"));
+    }
+    Ok(sections)
+}
+
+// Finds the spine document of the reader's last element, falling back to the document that
+// contains the given percentage of the book's text
+fn section_index(sections: &[Section], settings: &models::BookSettings) -> usize {
+    if let Some(component) = settings
+        .last_element
+        .as_deref()
+        .and_then(|selector| selector.split(" > ").next())
+        .and_then(|component| component.strip_prefix('#'))
+    {
+        if let Some(index) = sections
+            .iter()
+            .position(|s| css_escape(&s.path) == component)
+        {
+            return index;
+        }
+        if !component.contains('\\') {
+            let needle = format!("id=\"{}\"", component);
+            if let Some(index) = sections
+                .iter()
+                .position(|s| String::from_utf8_lossy(&s.content).contains(needle.as_str()))
+            {
+                return index;
+            }
+        }
+    }
+
+    let total: usize = sections.iter().map(|s| s.content.len()).sum();
+    let target = total as f64 * settings.percentage.unwrap_or(0).clamp(0, 100) as f64 / 100.0;
+    let mut length = 0;
+    for (index, section) in sections.iter().enumerate() {
+        length += section.content.len();
+        if length as f64 >= target {
+            return index;
+        }
+    }
+    sections.len() - 1
+}
+
+fn xpointer_section(progress: &str) -> Option<usize> {
+    let start = progress.find("DocFragment[")? + "DocFragment[".len();
+    let end = start + progress[start..].find(']')?;
+    progress[start..end].parse::<usize>().ok()?.checked_sub(1)
+}
+
+fn read_account(conn: &mut SqliteConnection) -> Result<KOReaderAccount, String> {
+    get_app_setting(conn, KOREADER_SYNC_SETTING_KEY)
+        .map_err(|_| String::from("Cannot get KOReader sync settings"))?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .ok_or(String::from("Not logged in to a KOReader sync server"))
+}
+
+fn endpoint(server_url: &str, path: &str) -> String {
+    format!("{}{}", server_url.trim_end_matches('/'), path)
+}
+
+fn authorized(request: ureq::Request, username: &str, userkey: &str) -> ureq::Request {
+    request
+        .set("Accept", KOREADER_ACCEPT_HEADER)
+        .set("x-auth-user", username)
+        .set("x-auth-key", userkey)
+}
+
+fn request_error(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(401, _) => String::from("Invalid KOReader sync username or password"),
+        ureq::Error::Status(402, _) => String::from("Username is already registered"),
+        ureq::Error::Status(code, _) => format!("KOReader sync server returned {}", code),
+        ureq::Error::Transport(_) => String::from("Cannot connect to KOReader sync server"),
+    }
+}
+
+fn save_account(conn: &mut SqliteConnection, account: &KOReaderAccount) -> Result<(), String> {
+    let json = serde_json::to_string(account).map_err(|e| e.to_string())?;
+    let res = set_app_setting(conn, KOREADER_SYNC_SETTING_KEY, Some(&json));
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update KOReader sync settings")),
+    }
+}
+
+fn get_book_and_settings(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+) -> Result<(models::Book, Option<models::BookSettings>), String> {
+    let book: models::Book = schema::book::table
+        .find(book_id)
+        .select(models::Book::as_select())
+        .get_result(conn)
+        .map_err(|_| String::from("Cannot find book"))?;
+
+    let settings: Option<models::BookSettings> = schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq(book_id))
+        .select(models::BookSettings::as_select())
+        .first(conn)
+        .optional()
+        .map_err(|_| String::from("Cannot get book settings"))?;
+
+    Ok((book, settings))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_koreader_sync_settings() -> Option<KOReaderSyncSettings> {
+    let mut conn: SqliteConnection = establish_connection();
+
+    read_account(&mut conn)
+        .ok()
+        .map(|account| KOReaderSyncSettings {
+            server_url: account.server_url,
+            username: account.username,
+            document_matching: account.document_matching,
+        })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn register_koreader_sync_user(
+    server_url: String,
+    username: String,
+    password: String,
+    document_matching: DocumentMatching,
+) -> Result<(), String> {
+    let userkey = md5_hex(password.as_bytes());
+
+    ureq::post(&endpoint(&server_url, "/users/create"))
+        .set("Accept", KOREADER_ACCEPT_HEADER)
+        .send_json(serde_json::json!({ "username": username, "password": userkey }))
+        .map_err(request_error)?;
+
+    let mut conn: SqliteConnection = establish_connection();
+    save_account(
+        &mut conn,
+        &KOReaderAccount {
+            server_url,
+            username,
+            userkey,
+            document_matching,
+        },
+    )
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn login_koreader_sync(
+    server_url: String,
+    username: String,
+    password: String,
+    document_matching: DocumentMatching,
+) -> Result<(), String> {
+    let userkey = md5_hex(password.as_bytes());
+
+    authorized(
+        ureq::get(&endpoint(&server_url, "/users/auth")),
+        &username,
+        &userkey,
+    )
+    .call()
+    .map_err(request_error)?;
+
+    let mut conn: SqliteConnection = establish_connection();
+    save_account(
+        &mut conn,
+        &KOReaderAccount {
+            server_url,
+            username,
+            userkey,
+            document_matching,
+        },
+    )
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn logout_koreader_sync() -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let res = set_app_setting(&mut conn, KOREADER_SYNC_SETTING_KEY, None);
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update KOReader sync settings")),
+    }
+}
+
+fn push_progress(
+    conn: &mut SqliteConnection,
+    account: &KOReaderAccount,
+    book_id: &str,
+) -> Result<(), String> {
+    let device_id = get_device_id(conn).map_err(|_| String::from("Cannot get sync settings"))?;
+    let (book, settings) = get_book_and_settings(conn, book_id)?;
+    let settings = settings.ok_or(String::from("Book has no reading progress"))?;
+
+    let document = document_hash(&book, account.document_matching)?;
+    let sections = read_sections(&book)?;
+    let progress = format!(
+        "/body/DocFragment[{}]/body",
+        section_index(&sections, &settings) + 1
+    );
+
+    authorized(
+        ureq::put(&endpoint(&account.server_url, "/syncs/progress")),
+        &account.username,
+        &account.userkey,
+    )
+    .send_json(ProgressUpdate {
+        document: &document,
+        progress: &progress,
+        percentage: settings.percentage.unwrap_or(0).clamp(0, 100) as f64 / 100.0,
+        device: KOREADER_DEVICE_NAME,
+        device_id: &device_id,
+    })
+    .map_err(request_error)?;
+
+    Ok(())
+}
+
+// Replaces the book's reading position with the one on the server, if there is one
+fn pull_progress(
+    conn: &mut SqliteConnection,
+    account: &KOReaderAccount,
+    book_id: &str,
+) -> Result<Option<KOReaderProgress>, String> {
+    let (book, _) = get_book_and_settings(conn, book_id)?;
+
+    let document = document_hash(&book, account.document_matching)?;
+    let remote: RemoteProgress = authorized(
+        ureq::get(&endpoint(
+            &account.server_url,
+            &format!("/syncs/progress/{}", document),
+        )),
+        &account.username,
+        &account.userkey,
+    )
+    .call()
+    .map_err(request_error)?
+    .into_json()
+    .map_err(|_| String::from("Invalid response from KOReader sync server"))?;
+
+    // The server returns an empty object for documents without a position
+    let Some(percentage) = remote.percentage else {
+        return Ok(None);
+    };
+    let percentage = (percentage * 100.0).round().clamp(0.0, 100.0) as i32;
+
+    let last_element = match remote.progress.as_deref().and_then(xpointer_section) {
+        Some(index) => read_sections(&book)?
+            .get(index)
+            .map(|section| format!("#{}", css_escape(&section.path))),
+        None => None,
+    };
+
+    // The page is cleared because the reader prefers it over the last element
+    set_book_progress(
+        conn,
+        &book.id,
+        Some(percentage),
+        last_element.as_deref(),
+        None,
+    )
+    .map_err(|_| String::from("Cannot update book settings"))?;
+
+    Ok(Some(KOReaderProgress {
+        percentage,
+        device: remote.device,
+        timestamp: remote.timestamp.and_then(|t| i32::try_from(t).ok()),
+    }))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn push_koreader_progress(book_id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let account = read_account(&mut conn)?;
+    push_progress(&mut conn, &account, &book_id)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn pull_koreader_progress(book_id: String) -> Result<Option<KOReaderProgress>, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let account = read_account(&mut conn)?;
+    pull_progress(&mut conn, &account, &book_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        css_escape, get_book_and_settings, md5_hex, partial_md5, pull_progress, push_progress,
+        xpointer_section, DocumentMatching, KOReaderAccount,
+    };
+    use crate::models;
+    use diesel::SqliteConnection;
+    use mikomi_core::settings::set_book_progress;
+    use mikomi_core::{books, current_timestamp, establish_connection, run_migrations};
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::path::Path;
+    use std::thread::{self, JoinHandle};
+    use tiny_http::{Response, Server};
+    use uuid::Uuid;
+
+    const BOOK_ID: &str = "book";
+
+    struct ReceivedRequest {
+        method: String,
+        url: String,
+        username: Option<String>,
+        userkey: Option<String>,
+        body: String,
+    }
+
+    // A sync server that answers a single request and hands it back for inspection
+    fn stand_in_server(status: u16, body: &'static str) -> (String, JoinHandle<ReceivedRequest>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr());
+
+        let handle = thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv(name))
+                    .map(|h| h.value.to_string())
+            };
+            let username = header("x-auth-user");
+            let userkey = header("x-auth-key");
+            let mut received = String::new();
+            request.as_reader().read_to_string(&mut received).unwrap();
+            let received = ReceivedRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                username,
+                userkey,
+                body: received,
+            };
+
+            request
+                .respond(Response::from_string(body).with_status_code(status))
+                .unwrap();
+            received
+        });
+
+        (url, handle)
+    }
+
+    // The EPUB fixtures are kept with the importer's tests in mikomi-core
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("mikomi-core/tests/fixtures")
+            .join(name);
+        String::from(path.to_string_lossy())
+    }
+
+    fn connection_with_book() -> SqliteConnection {
+        let mut conn = establish_connection(":memory:").unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let book = models::Book {
+            id: String::from(BOOK_ID),
+            title: String::from("An EPUB2 Book"),
+            path: fixture("epub2.epub"),
+            last_read: None,
+            date_added: current_timestamp(),
+            reading_status: models::ReadingStatus::Reading,
+            language: None,
+            last_modified: None,
+            identifier: None,
+            published_date: None,
+            description: None,
+            publisher: None,
+            page_progression_direction: None,
+            deleted_at: None,
+        };
+        books::add_book(&mut conn, &book, &[]).unwrap();
+        conn
+    }
+
+    fn account(server_url: String) -> KOReaderAccount {
+        KOReaderAccount {
+            server_url,
+            username: String::from("reader"),
+            userkey: md5_hex(b"secret"),
+            document_matching: DocumentMatching::Binary,
+        }
+    }
+
+    fn book_progress(conn: &mut SqliteConnection) -> (Option<i32>, Option<String>) {
+        let settings = get_book_and_settings(conn, BOOK_ID).unwrap().1.unwrap();
+        (settings.percentage, settings.last_element)
+    }
+
+    #[test]
+    fn it_pushes_the_progress_of_a_book() {
+        let mut conn = connection_with_book();
+        set_book_progress(&mut conn, BOOK_ID, Some(50), None, None).unwrap();
+        let (url, server) = stand_in_server(200, "{}");
+
+        push_progress(&mut conn, &account(url), BOOK_ID).unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "/syncs/progress");
+        assert_eq!(request.username.as_deref(), Some("reader"));
+        assert_eq!(request.userkey, Some(md5_hex(b"secret")));
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        let document = partial_md5(Path::new(&fixture("epub2.epub"))).unwrap();
+        assert_eq!(body["document"], document.as_str());
+        assert_eq!(body["progress"], "/body/DocFragment[1]/body");
+        assert_eq!(body["percentage"], 0.5);
+        assert_eq!(body["device"], "Mikomi");
+    }
+
+    #[test]
+    fn it_pulls_the_progress_of_a_book() {
+        let mut conn = connection_with_book();
+        let (url, server) = stand_in_server(
+            200,
+            r#"{"progress":"/body/DocFragment[1]/body/p[3]","percentage":0.25,"device":"Kobo","timestamp":1700000000}"#,
+        );
+
+        let progress = pull_progress(&mut conn, &account(url), BOOK_ID)
+            .unwrap()
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.method, "GET");
+        let document = partial_md5(Path::new(&fixture("epub2.epub"))).unwrap();
+        assert_eq!(request.url, format!("/syncs/progress/{document}"));
+        assert_eq!(progress.percentage, 25);
+        assert_eq!(progress.device.as_deref(), Some("Kobo"));
+        assert_eq!(progress.timestamp, Some(1700000000));
+        assert_eq!(
+            book_progress(&mut conn),
+            (Some(25), Some(String::from("#OEBPS\\/chapter\\.xhtml")))
+        );
+    }
+
+    #[test]
+    fn it_reports_rejected_credentials() {
+        let mut conn = connection_with_book();
+        let (url, server) = stand_in_server(401, r#"{"message":"Unauthorized"}"#);
+
+        let res = pull_progress(&mut conn, &account(url), BOOK_ID);
+
+        server.join().unwrap();
+        assert_eq!(
+            res.err().as_deref(),
+            Some("Invalid KOReader sync username or password")
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_progress_when_the_server_has_none() {
+        let mut conn = connection_with_book();
+        set_book_progress(&mut conn, BOOK_ID, Some(40), Some("#start"), None).unwrap();
+        let (url, server) = stand_in_server(200, "{}");
+
+        let progress = pull_progress(&mut conn, &account(url), BOOK_ID).unwrap();
+
+        server.join().unwrap();
+        assert!(progress.is_none());
+        assert_eq!(
+            book_progress(&mut conn),
+            (Some(40), Some(String::from("#start")))
+        );
+    }
+
+    #[test]
+    fn partial_md5_samples_the_file_at_growing_offsets() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let path = env::temp_dir().join(format!("mikomi-partial-md5-{}", Uuid::new_v4()));
+        fs::write(&path, &data).unwrap();
+
+        let hash = partial_md5(&path);
+        let small_hash = {
+            fs::write(&path, &data[..100]).unwrap();
+            partial_md5(&path)
+        };
+        let _ = fs::remove_file(&path);
+
+        let sampled = [&data[..1024], &data[1024..2048], &data[4096..]].concat();
+        assert_eq!(hash.unwrap(), md5_hex(&sampled));
+        assert_eq!(small_hash.unwrap(), md5_hex(&data[..100]));
+    }
+
+    #[test]
+    fn css_escape_matches_the_browser() {
+        assert_eq!(css_escape("OEBPS/chapter.xhtml"), "OEBPS\\/chapter\\.xhtml");
+        assert_eq!(css_escape("1st"), "\\31 st");
+        assert_eq!(css_escape("-1"), "-\\31 ");
+        assert_eq!(css_escape("-"), "\\-");
+        assert_eq!(css_escape("a\u{0}b"), "a\u{FFFD}b");
+        assert_eq!(css_escape("a\u{7f}"), "a\\7f ");
+        assert_eq!(css_escape("kapitel_é-2"), "kapitel_é-2");
+    }
+
+    #[test]
+    fn xpointer_section_reads_the_doc_fragment_index() {
+        assert_eq!(
+            xpointer_section("/body/DocFragment[3]/body/p[2]/text().4"),
+            Some(2)
+        );
+        assert_eq!(xpointer_section("/body/DocFragment[1]/body"), Some(0));
+        assert_eq!(xpointer_section("/body/DocFragment[0]/body"), None);
+        assert_eq!(xpointer_section("/body/DocFragment[x]/body"), None);
+        assert_eq!(xpointer_section("/body/DocFragment[2"), None);
+        assert_eq!(xpointer_section("#chapter"), None);
+    }
+}
@@ -5,9 +5,18 @@ use diesel::connection::SimpleConnection;
 use specta::collect_types;
 use tauri_specta::ts;
 
+mod api_server;
+mod backup;
 mod db;
-pub mod models;
-pub mod schema;
+mod fonts;
+mod journal;
+mod koreader;
+mod opds;
+mod opds_server;
+mod restore;
+mod sync;
+
+pub use mikomi_core::{models, schema};
 
 fn main() {
     #[cfg(debug_assertions)]
@@ -16,24 +25,117 @@ fn main() {
             db::get_book,
             db::get_books,
             db::get_books_belonging_to_collections,
+            db::get_books_belonging_to_collection_tree,
             db::add_book_from_file,
             db::add_multiple_books_from_files,
             db::update_book,
             db::update_book_reading_status,
             db::remove_book,
+            db::get_trashed_books,
+            db::restore_books,
+            db::remove_book_permanently,
+            db::empty_trash,
+            journal::get_journal_entries,
+            journal::undo_last,
+            journal::redo,
+            backup::create_backup,
+            restore::restore_backup,
+            backup::get_snapshot_settings,
+            backup::update_snapshot_settings,
+            backup::get_snapshots,
+            backup::create_snapshot,
+            sync::get_sync_settings,
+            sync::update_sync_folder,
+            sync::sync_now,
+            koreader::get_koreader_sync_settings,
+            koreader::register_koreader_sync_user,
+            koreader::login_koreader_sync,
+            koreader::logout_koreader_sync,
+            koreader::push_koreader_progress,
+            koreader::pull_koreader_progress,
+            opds::get_opds_catalogs,
+            opds::add_opds_catalog,
+            opds::update_opds_catalog,
+            opds::remove_opds_catalog,
+            opds::browse_opds_catalog,
+            opds::search_opds_catalog,
+            opds::download_opds_book,
+            opds_server::get_opds_server_status,
+            opds_server::start_opds_server,
+            opds_server::stop_opds_server,
+            api_server::get_api_server_status,
+            api_server::start_api_server,
+            api_server::stop_api_server,
+            api_server::regenerate_api_token,
+            restore::restore_snapshot,
+            db::get_trash_retention_period,
+            db::update_trash_retention_period,
+            db::bulk_update_reading_status,
+            db::bulk_add_books_to_collections,
+            db::bulk_remove_books_from_collections,
+            db::bulk_update_book_settings,
+            db::bulk_update_book_language,
+            db::bulk_remove_books,
+            db::start_reading_session,
+            db::heartbeat_reading_session,
+            db::end_reading_session,
+            db::get_reading_time_per_day,
+            db::get_reading_time_per_book,
+            db::get_reading_time_per_author,
+            db::get_book_reading_statistics,
+            db::get_reading_status_events,
+            db::get_reading_histories,
+            db::get_reading_goals,
+            db::add_reading_goal,
+            db::remove_reading_goal,
+            db::get_reading_goal_progress,
             db::add_bookmark,
             db::remove_bookmark,
             db::update_bookmark,
             db::add_highlight,
             db::remove_highlight,
             db::update_highlight,
+            db::get_highlight_notes,
+            db::get_highlight_note_revisions,
+            db::add_highlight_note,
+            db::update_highlight_note,
+            db::remove_highlight_note,
+            db::get_highlights,
+            db::get_highlights_grouped_by_color,
+            db::get_highlight_colors,
+            db::add_highlight_color,
+            db::update_highlight_color,
+            db::reorder_highlight_colors,
+            db::remove_highlight_color,
             db::add_book_settings,
             db::remove_book_settings,
             db::update_book_settings,
+            db::get_reader_settings_defaults,
+            db::add_reader_settings_default,
+            db::remove_reader_settings_default,
+            db::get_effective_settings,
+            db::reset_book_settings_to_defaults,
+            db::get_global_user_css,
+            db::update_global_user_css,
+            db::get_book_user_css,
+            db::update_book_user_css,
+            db::get_book_stylesheets,
+            db::set_book_stylesheet_disabled,
+            db::get_reader_settings_profiles,
+            db::add_reader_settings_profile,
+            db::update_reader_settings_profile,
+            db::remove_reader_settings_profile,
+            db::apply_reader_settings_profile,
             db::get_reader_themes,
             db::add_reader_theme,
             db::remove_reader_theme,
             db::update_reader_theme,
+            db::set_book_reader_theme,
+            db::export_reader_themes,
+            db::import_reader_themes,
+            db::get_fonts,
+            db::add_font_from_file,
+            db::remove_font,
             db::get_collections,
             db::get_collections_and_their_books,
             db::add_collection,
@@ -41,6 +143,8 @@ fn main() {
             db::reorder_collections,
             db::reorder_books_in_collection,
             db::remove_collection,
+            db::move_collection,
+            db::get_collection_tree,
             db::add_book_to_collections,
             db::remove_book_from_collection,
             db::get_languages,
@@ -50,32 +154,131 @@ fn main() {
     .unwrap();
 
     let mut conn = db::establish_connection();
-    db::run_migrations(&mut conn).expect("Unable to run migrations");
+    mikomi_core::run_migrations(&mut conn).expect("Unable to run migrations");
     let _ = conn.batch_execute("PRAGMA journal_mode = WAL;");
+    let _ = db::purge_expired_trash(&mut conn);
+    drop(conn);
+    let _ = backup::take_startup_snapshot();
+    backup::spawn_snapshot_scheduler();
+    let _ = opds_server::start_opds_server_if_enabled();
+    let _ = api_server::start_api_server_if_enabled();
 
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             db::get_book,
             db::get_books,
             db::get_books_belonging_to_collections,
+            db::get_books_belonging_to_collection_tree,
             db::add_book_from_file,
             db::add_multiple_books_from_files,
             db::update_book,
             db::update_book_reading_status,
             db::remove_book,
+            db::get_trashed_books,
+            db::restore_books,
+            db::remove_book_permanently,
+            db::empty_trash,
+            journal::get_journal_entries,
+            journal::undo_last,
+            journal::redo,
+            backup::create_backup,
+            restore::restore_backup,
+            backup::get_snapshot_settings,
+            backup::update_snapshot_settings,
+            backup::get_snapshots,
+            backup::create_snapshot,
+            sync::get_sync_settings,
+            sync::update_sync_folder,
+            sync::sync_now,
+            koreader::get_koreader_sync_settings,
+            koreader::register_koreader_sync_user,
+            koreader::login_koreader_sync,
+            koreader::logout_koreader_sync,
+            koreader::push_koreader_progress,
+            koreader::pull_koreader_progress,
+            opds::get_opds_catalogs,
+            opds::add_opds_catalog,
+            opds::update_opds_catalog,
+            opds::remove_opds_catalog,
+            opds::browse_opds_catalog,
+            opds::search_opds_catalog,
+            opds::download_opds_book,
+            opds_server::get_opds_server_status,
+            opds_server::start_opds_server,
+            opds_server::stop_opds_server,
+            api_server::get_api_server_status,
+            api_server::start_api_server,
+            api_server::stop_api_server,
+            api_server::regenerate_api_token,
+            restore::restore_snapshot,
+            db::get_trash_retention_period,
+            db::update_trash_retention_period,
+            db::bulk_update_reading_status,
+            db::bulk_add_books_to_collections,
+            db::bulk_remove_books_from_collections,
+            db::bulk_update_book_settings,
+            db::bulk_update_book_language,
+            db::bulk_remove_books,
+            db::start_reading_session,
+            db::heartbeat_reading_session,
+            db::end_reading_session,
+            db::get_reading_time_per_day,
+            db::get_reading_time_per_book,
+            db::get_reading_time_per_author,
+            db::get_book_reading_statistics,
+            db::get_reading_status_events,
+            db::get_reading_histories,
+            db::get_reading_goals,
+            db::add_reading_goal,
+            db::remove_reading_goal,
+            db::get_reading_goal_progress,
             db::add_bookmark,
             db::remove_bookmark,
             db::update_bookmark,
             db::add_highlight,
             db::remove_highlight,
             db::update_highlight,
+            db::get_highlight_notes,
+            db::get_highlight_note_revisions,
+            db::add_highlight_note,
+            db::update_highlight_note,
+            db::remove_highlight_note,
+            db::get_highlights,
+            db::get_highlights_grouped_by_color,
+            db::get_highlight_colors,
+            db::add_highlight_color,
+            db::update_highlight_color,
+            db::reorder_highlight_colors,
+            db::remove_highlight_color,
             db::add_book_settings,
             db::remove_book_settings,
             db::update_book_settings,
+            db::get_reader_settings_defaults,
+            db::add_reader_settings_default,
+            db::remove_reader_settings_default,
+            db::get_effective_settings,
+            db::reset_book_settings_to_defaults,
+            db::get_global_user_css,
+            db::update_global_user_css,
+            db::get_book_user_css,
+            db::update_book_user_css,
+            db::get_book_stylesheets,
+            db::set_book_stylesheet_disabled,
+            db::get_reader_settings_profiles,
+            db::add_reader_settings_profile,
+            db::update_reader_settings_profile,
+            db::remove_reader_settings_profile,
+            db::apply_reader_settings_profile,
             db::get_reader_themes,
             db::add_reader_theme,
             db::remove_reader_theme,
             db::update_reader_theme,
+            db::set_book_reader_theme,
+            db::export_reader_themes,
+            db::import_reader_themes,
+            db::get_fonts,
+            db::add_font_from_file,
+            db::remove_font,
             db::get_collections,
             db::get_collections_and_their_books,
             db::add_collection,
@@ -83,6 +286,8 @@ fn main() {
             db::reorder_collections,
             db::reorder_books_in_collection,
             db::remove_collection,
+            db::move_collection,
+            db::get_collection_tree,
             db::add_book_to_collections,
             db::remove_book_from_collection,
             db::get_languages,
@@ -93,6 +298,7 @@ fn main() {
             tauri::RunEvent::ExitRequested { .. } => {
                 let mut conn = db::establish_connection();
                 let _ = conn.batch_execute("PRAGMA wal_checkpoint(TRUNCATE);");
+                let _ = backup::take_exit_snapshot();
             }
             _ => {}
         });
@@ -0,0 +1,774 @@
+use crate::db::{self, establish_connection, DOWNLOADED_BOOKS_DIR};
+use crate::models;
+use crate::schema;
+use base64::Engine;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use mikomi_core::current_timestamp;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use serde_json::Value;
+use specta::Type;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use url::Url;
+use uuid::Uuid;
+
+const OPDS_ACCEPT_HEADER: &str =
+    "application/opds+json, application/atom+xml;q=0.9, application/xml;q=0.8, */*;q=0.5";
+const ACQUISITION_REL: &str = "http://opds-spec.org/acquisition";
+const IMAGE_REL: &str = "http://opds-spec.org/image";
+const THUMBNAIL_REL: &str = "http://opds-spec.org/image/thumbnail";
+const EPUB_MIME_TYPE: &str = "application/epub+zip";
+const MAX_BOOK_SIZE: u64 = 500 * 1024 * 1024;
+
+#[derive(Serialize, Type, PartialEq, Debug)]
+pub struct OpdsAcquisition {
+    pub url: String,
+    pub mime_type: Option<String>,
+}
+
+#[derive(Serialize, Type, PartialEq, Debug)]
+pub struct OpdsEntry {
+    pub id: Option<String>,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub summary: Option<String>,
+    pub cover_url: Option<String>,
+    // Set for entries that lead to another feed instead of a book
+    pub navigation_url: Option<String>,
+    pub acquisitions: Vec<OpdsAcquisition>,
+}
+
+#[derive(Serialize, Type, PartialEq, Debug)]
+pub struct OpdsFeed {
+    pub url: String,
+    pub title: String,
+    pub entries: Vec<OpdsEntry>,
+    pub next_url: Option<String>,
+    pub previous_url: Option<String>,
+    pub searchable: bool,
+}
+
+struct FeedLink {
+    rels: Vec<String>,
+    href: String,
+    mime_type: Option<String>,
+}
+
+// A feed of either OPDS version, together with its links that are not shown as entries
+struct ParsedFeed {
+    title: String,
+    entries: Vec<OpdsEntry>,
+    links: Vec<FeedLink>,
+}
+
+fn find_link<'a>(links: &'a [FeedLink], rel: &str) -> Option<&'a FeedLink> {
+    links.iter().find(|l| l.rels.iter().any(|r| r == rel))
+}
+
+fn resolve(base: &Url, href: &str) -> Option<String> {
+    base.join(href).ok().map(|u| u.to_string())
+}
+
+fn is_feed_mime_type(mime_type: Option<&str>) -> bool {
+    mime_type.is_some_and(|t| {
+        t.starts_with("application/atom+xml")
+            || t.starts_with("application/opds+json")
+            || t.starts_with("application/json")
+    })
+}
+
+fn entry_from_links(
+    id: Option<String>,
+    title: String,
+    authors: Vec<String>,
+    summary: Option<String>,
+    links: Vec<FeedLink>,
+    base: &Url,
+) -> OpdsEntry {
+    let cover_url = find_link(&links, IMAGE_REL)
+        .or(find_link(&links, THUMBNAIL_REL))
+        .and_then(|l| resolve(base, &l.href));
+
+    let acquisitions: Vec<OpdsAcquisition> = links
+        .iter()
+        .filter(|l| l.rels.iter().any(|r| r.starts_with(ACQUISITION_REL)))
+        .filter_map(|l| {
+            Some(OpdsAcquisition {
+                url: resolve(base, &l.href)?,
+                mime_type: l.mime_type.clone(),
+            })
+        })
+        .collect();
+
+    let navigation_url = if acquisitions.is_empty() {
+        links
+            .iter()
+            .find(|l| is_feed_mime_type(l.mime_type.as_deref()))
+            .and_then(|l| resolve(base, &l.href))
+    } else {
+        None
+    };
+
+    OpdsEntry {
+        id,
+        title,
+        authors,
+        summary,
+        cover_url,
+        navigation_url,
+        acquisitions,
+    }
+}
+
+fn xml_attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .filter_map(|a| a.ok())
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.to_string()))
+}
+
+fn xml_link(element: &BytesStart) -> Option<FeedLink> {
+    Some(FeedLink {
+        rels: vec![xml_attribute(element, b"rel").unwrap_or(String::from("alternate"))],
+        href: xml_attribute(element, b"href")?,
+        mime_type: xml_attribute(element, b"type"),
+    })
+}
+
+#[derive(Default)]
+struct AtomEntry {
+    id: Option<String>,
+    title: String,
+    authors: Vec<String>,
+    summary: Option<String>,
+    links: Vec<FeedLink>,
+}
+
+// OPDS 1.2 feeds are Atom documents
+fn parse_atom_feed(xml: &str, base: &Url) -> Result<ParsedFeed, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut feed = ParsedFeed {
+        title: String::new(),
+        entries: vec![],
+        links: vec![],
+    };
+    let mut path: Vec<Vec<u8>> = vec![];
+    let mut entry: Option<AtomEntry> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|_| String::from("Invalid OPDS feed"))?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"link" => {
+                if let Some(link) = xml_link(e) {
+                    match entry.as_mut() {
+                        Some(entry) => entry.links.push(link),
+                        None => feed.links.push(link),
+                    }
+                }
+                if matches!(event, Event::Start(_)) {
+                    path.push(b"link".to_vec());
+                }
+            }
+            Event::Start(e) => {
+                if e.local_name().as_ref() == b"entry" {
+                    entry = Some(AtomEntry::default());
+                }
+                path.push(e.local_name().as_ref().to_vec());
+            }
+            Event::End(e) => {
+                path.pop();
+                if e.local_name().as_ref() == b"entry" {
+                    if let Some(e) = entry.take() {
+                        feed.entries.push(entry_from_links(
+                            e.id, e.title, e.authors, e.summary, e.links, base,
+                        ));
+                    }
+                }
+            }
+            Event::Text(_) | Event::CData(_) => {
+                let text = match &event {
+                    Event::Text(t) => t.unescape().map(|t| t.to_string()).unwrap_or_default(),
+                    Event::CData(t) => String::from_utf8_lossy(t).to_string(),
+                    _ => continue,
+                };
+                let tail: Vec<&[u8]> = path.iter().rev().take(3).map(|p| p.as_slice()).collect();
+                match (entry.as_mut(), tail.as_slice()) {
+                    (None, [b"title", b"feed", ..]) => feed.title.push_str(&text),
+                    (Some(entry), [b"title", b"entry", ..]) => entry.title.push_str(&text),
+                    (Some(entry), [b"id", b"entry", ..]) => entry.id = Some(text),
+                    (Some(entry), [b"name", b"author", b"entry"]) => entry.authors.push(text),
+                    (Some(entry), [b"summary" | b"content", b"entry", ..]) => entry
+                        .summary
+                        .get_or_insert_with(String::new)
+                        .push_str(&text),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(feed)
+}
+
+fn json_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(values) => values.iter().flat_map(json_strings).collect(),
+        // Contributors can be objects with a name
+        Value::Object(o) => o.get("name").map(json_strings).unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+fn json_links(value: &Value) -> Vec<FeedLink> {
+    value
+        .as_array()
+        .map(|links| {
+            links
+                .iter()
+                .filter_map(|l| {
+                    Some(FeedLink {
+                        rels: l.get("rel").map(json_strings).unwrap_or_default(),
+                        href: l.get("href")?.as_str()?.to_string(),
+                        mime_type: l.get("type").and_then(|t| t.as_str()).map(String::from),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn json_publication(publication: &Value, base: &Url) -> OpdsEntry {
+    let metadata = &publication["metadata"];
+    let mut links = json_links(&publication["links"]);
+    // Images are listed separately from the other links in OPDS 2.0
+    for mut image in json_links(&publication["images"]) {
+        image.rels = vec![String::from(IMAGE_REL)];
+        links.push(image);
+    }
+
+    entry_from_links(
+        metadata["identifier"].as_str().map(String::from),
+        metadata["title"].as_str().unwrap_or_default().to_string(),
+        json_strings(&metadata["author"]),
+        metadata["description"].as_str().map(String::from),
+        links,
+        base,
+    )
+}
+
+fn json_navigation(navigation: &Value, base: &Url) -> Vec<OpdsEntry> {
+    navigation
+        .as_array()
+        .map(|links| {
+            links
+                .iter()
+                .filter_map(|l| {
+                    Some(OpdsEntry {
+                        id: None,
+                        title: l["title"].as_str().unwrap_or_default().to_string(),
+                        authors: vec![],
+                        summary: None,
+                        cover_url: None,
+                        navigation_url: Some(resolve(base, l["href"].as_str()?)?),
+                        acquisitions: vec![],
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_opds2_feed(json: &str, base: &Url) -> Result<ParsedFeed, String> {
+    let feed: Value = serde_json::from_str(json).map_err(|_| String::from("Invalid OPDS feed"))?;
+
+    let mut entries = json_navigation(&feed["navigation"], base);
+    for publication in feed["publications"].as_array().into_iter().flatten() {
+        entries.push(json_publication(publication, base));
+    }
+    for group in feed["groups"].as_array().into_iter().flatten() {
+        entries.extend(json_navigation(&group["navigation"], base));
+        for publication in group["publications"].as_array().into_iter().flatten() {
+            entries.push(json_publication(publication, base));
+        }
+    }
+
+    Ok(ParsedFeed {
+        title: feed["metadata"]["title"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        entries,
+        links: json_links(&feed["links"]),
+    })
+}
+
+fn parse_feed(body: &str, base: &Url) -> Result<ParsedFeed, String> {
+    if body.trim_start().starts_with('{') {
+        parse_opds2_feed(body, base)
+    } else {
+        parse_atom_feed(body, base)
+    }
+}
+
+// Fills in the query of an OpenSearch template or an RFC 6570 URI template
+fn expand_search_template(template: &str, query: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20");
+
+    let mut expanded = template
+        .replace("{searchTerms}", &encoded)
+        .replace("{searchTerms?}", &encoded);
+    while let Some(start) = expanded.find('{') {
+        let Some(end) = expanded[start..].find('}').map(|i| start + i) else {
+            break;
+        };
+        let expression = &expanded[start + 1..end];
+        let replacement = match expression.chars().next() {
+            Some(operator @ ('?' | '&')) => {
+                let name = expression[1..].split(',').next().unwrap_or("query");
+                format!("{}{}={}", operator, name, encoded)
+            }
+            _ if expression == "query" => encoded.clone(),
+            _ => String::new(),
+        };
+        expanded.replace_range(start..=end, &replacement);
+    }
+
+    expanded
+}
+
+fn open_search_template(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut templates: Vec<(Option<String>, String)> = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.local_name().as_ref() == b"Url" => {
+                if let Some(template) = xml_attribute(&e, b"template") {
+                    templates.push((xml_attribute(&e, b"type"), template));
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    templates
+        .iter()
+        .find(|(t, _)| is_feed_mime_type(t.as_deref()))
+        .or(templates.first())
+        .map(|(_, template)| template.clone())
+}
+
+fn get_catalog(
+    conn: &mut SqliteConnection,
+    catalog_id: &str,
+) -> Result<models::OpdsCatalog, String> {
+    schema::opds_catalog::table
+        .find(catalog_id)
+        .select(models::OpdsCatalog::as_select())
+        .get_result(conn)
+        .map_err(|_| String::from("Cannot find OPDS catalog"))
+}
+
+fn request(catalog: &models::OpdsCatalog, url: &str) -> Result<ureq::Response, String> {
+    let target = Url::parse(url).map_err(|_| String::from("Invalid URL"))?;
+    let mut request = ureq::get(target.as_str()).set("Accept", OPDS_ACCEPT_HEADER);
+
+    // The credentials are only sent to the catalog's own server, since feeds can link to others
+    let same_origin = Url::parse(&catalog.url).is_ok_and(|u| u.origin() == target.origin());
+    if let (true, Some(username)) = (same_origin, &catalog.username) {
+        let credentials = format!("{}:{}", username, catalog.password.as_deref().unwrap_or(""));
+        request = request.set(
+            "Authorization",
+            &format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            ),
+        );
+    }
+
+    request.call().map_err(|e| match e {
+        ureq::Error::Status(401, _) => String::from("Invalid OPDS catalog username or password"),
+        ureq::Error::Status(code, _) => format!("OPDS catalog returned {}", code),
+        ureq::Error::Transport(_) => String::from("Cannot connect to OPDS catalog"),
+    })
+}
+
+fn fetch_feed(catalog: &models::OpdsCatalog, url: &str) -> Result<(Url, ParsedFeed), String> {
+    let response = request(catalog, url)?;
+    let base = Url::parse(response.get_url()).map_err(|_| String::from("Invalid URL"))?;
+    let body = response
+        .into_string()
+        .map_err(|_| String::from("Cannot read OPDS feed"))?;
+    let feed = parse_feed(&body, &base)?;
+
+    Ok((base, feed))
+}
+
+fn search_template(
+    catalog: &models::OpdsCatalog,
+    base: &Url,
+    links: &[FeedLink],
+) -> Result<Option<String>, String> {
+    let Some(link) = find_link(links, "search") else {
+        return Ok(None);
+    };
+    let Some(href) = resolve(base, &link.href) else {
+        return Ok(None);
+    };
+
+    if link.mime_type.as_deref() != Some("application/opensearchdescription+xml") {
+        // Templates are resolved after being expanded, so that their braces are not escaped
+        return Ok(Some(link.href.clone()));
+    }
+
+    let description = request(catalog, &href)?
+        .into_string()
+        .map_err(|_| String::from("Cannot read OPDS search description"))?;
+    Ok(open_search_template(&description))
+}
+
+fn into_feed(url: Url, feed: ParsedFeed, searchable: bool) -> OpdsFeed {
+    OpdsFeed {
+        next_url: find_link(&feed.links, "next").and_then(|l| resolve(&url, &l.href)),
+        previous_url: find_link(&feed.links, "previous")
+            .or(find_link(&feed.links, "prev"))
+            .and_then(|l| resolve(&url, &l.href)),
+        searchable,
+        url: url.to_string(),
+        title: feed.title,
+        entries: feed.entries,
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_opds_catalogs() -> Vec<models::OpdsCatalog> {
+    let mut conn: SqliteConnection = establish_connection();
+
+    schema::opds_catalog::table
+        .select(models::OpdsCatalog::as_select())
+        .order(schema::opds_catalog::name)
+        .load(&mut conn)
+        .unwrap()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn add_opds_catalog(
+    name: String,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<models::OpdsCatalog, String> {
+    Url::parse(&url).map_err(|_| String::from("Invalid URL"))?;
+
+    let mut conn: SqliteConnection = establish_connection();
+    let catalog = models::OpdsCatalog {
+        id: Uuid::new_v4().to_string(),
+        name,
+        url,
+        username,
+        password,
+        date_added: current_timestamp(),
+    };
+    let res = diesel::insert_into(schema::opds_catalog::table)
+        .values(&catalog)
+        .execute(&mut conn);
+
+    match res {
+        Ok(_) => Ok(catalog),
+        Err(_) => Err(String::from("Cannot add OPDS catalog")),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_opds_catalog(
+    id: String,
+    name: String,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<(), String> {
+    Url::parse(&url).map_err(|_| String::from("Invalid URL"))?;
+
+    let mut conn: SqliteConnection = establish_connection();
+    let res = diesel::update(schema::opds_catalog::table.find(id))
+        .set((
+            schema::opds_catalog::name.eq(name),
+            schema::opds_catalog::url.eq(url),
+            schema::opds_catalog::username.eq(username),
+            schema::opds_catalog::password.eq(password),
+        ))
+        .execute(&mut conn);
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update OPDS catalog")),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_opds_catalog(id: String) -> Result<(), String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let res = diesel::delete(schema::opds_catalog::table.find(id)).execute(&mut conn);
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot delete OPDS catalog")),
+    }
+}
+
+// Opens the catalog's root feed, or one of the feeds that it links to
+#[tauri::command]
+#[specta::specta]
+pub fn browse_opds_catalog(catalog_id: String, url: Option<String>) -> Result<OpdsFeed, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let catalog = get_catalog(&mut conn, &catalog_id)?;
+
+    let (base, feed) = fetch_feed(&catalog, url.as_deref().unwrap_or(&catalog.url))?;
+    let searchable = find_link(&feed.links, "search").is_some();
+
+    Ok(into_feed(base, feed, searchable))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn search_opds_catalog(catalog_id: String, query: String) -> Result<OpdsFeed, String> {
+    let mut conn: SqliteConnection = establish_connection();
+    let catalog = get_catalog(&mut conn, &catalog_id)?;
+
+    let (base, root) = fetch_feed(&catalog, &catalog.url)?;
+    let template = search_template(&catalog, &base, &root.links)?
+        .ok_or(String::from("OPDS catalog does not support search"))?;
+    let url = resolve(&base, &expand_search_template(&template, &query))
+        .ok_or(String::from("Invalid URL"))?;
+
+    let (base, feed) = fetch_feed(&catalog, &url)?;
+    Ok(into_feed(base, feed, true))
+}
+
+// Downloads an EPUB acquisition link into the data directory and imports it
+#[tauri::command]
+#[specta::specta]
+pub async fn download_opds_book(catalog_id: String, url: String) -> Result<models::Book, String> {
+    let catalog = {
+        let mut conn: SqliteConnection = establish_connection();
+        get_catalog(&mut conn, &catalog_id)?
+    };
+
+    let response = request(&catalog, &url)?;
+    let content_type = response.content_type().to_string();
+    let mut data: Vec<u8> = vec![];
+    // One byte more than the limit is read to tell a book of exactly the limit from a
+    // larger one
+    response
+        .into_reader()
+        .take(MAX_BOOK_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(|_| String::from("Cannot download book"))?;
+    if data.len() as u64 > MAX_BOOK_SIZE {
+        return Err(String::from("Book is too large"));
+    }
+
+    // Some servers send EPUBs as generic binary data, so the zip signature is checked too
+    if content_type != EPUB_MIME_TYPE && !data.starts_with(b"PK\x03\x04") {
+        return Err(String::from("Book is not an EPUB"));
+    }
+
+    fs::create_dir_all(DOWNLOADED_BOOKS_DIR).map_err(|e| e.to_string())?;
+    let path = Path::new(DOWNLOADED_BOOKS_DIR).join(format!("{}.epub", Uuid::new_v4()));
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, &data).map_err(|_| String::from("Cannot save book"))?;
+    fs::rename(&partial_path, &path).map_err(|_| String::from("Cannot save book"))?;
+
+    let path = path.to_string_lossy().to_string();
+    let res = db::add_book_from_file(path.clone()).await;
+    if res.is_err() {
+        let _ = fs::remove_file(&path);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        expand_search_template, find_link, into_feed, parse_feed, OpdsAcquisition, OpdsEntry,
+    };
+    use std::fs;
+    use std::path::Path;
+    use url::Url;
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    fn base() -> Url {
+        Url::parse("https://books.example.org/opds/new").unwrap()
+    }
+
+    fn navigation_entry(title: &str, url: &str) -> OpdsEntry {
+        OpdsEntry {
+            id: None,
+            title: String::from(title),
+            authors: vec![],
+            summary: None,
+            cover_url: None,
+            navigation_url: Some(String::from(url)),
+            acquisitions: vec![],
+        }
+    }
+
+    fn epub_acquisition(url: &str) -> OpdsAcquisition {
+        OpdsAcquisition {
+            url: String::from(url),
+            mime_type: Some(String::from("application/epub+zip")),
+        }
+    }
+
+    #[test]
+    fn it_parses_an_atom_feed() {
+        let feed = parse_feed(&fixture("opds-atom.xml"), &base()).unwrap();
+        let search = find_link(&feed.links, "search").map(|l| l.href.clone());
+        let feed = into_feed(base(), feed, false);
+
+        assert_eq!(feed.title, "Mikomi & Friends");
+        assert_eq!(
+            feed.next_url.as_deref(),
+            Some("https://books.example.org/opds/new?page=2")
+        );
+        assert_eq!(feed.previous_url, None);
+        assert_eq!(search.as_deref(), Some("/opds/search.xml"));
+        assert_eq!(
+            feed.entries,
+            vec![
+                OpdsEntry {
+                    id: Some(String::from("urn:mikomi:fixture:scifi")),
+                    ..navigation_entry("Science Fiction", "https://books.example.org/opds/scifi")
+                },
+                OpdsEntry {
+                    id: Some(String::from("urn:mikomi:fixture:epub2")),
+                    title: String::from("An EPUB2 Book"),
+                    authors: vec![String::from("Ada Writer"), String::from("Bo Editor")],
+                    summary: Some(String::from("A book <for> tests.")),
+                    cover_url: Some(String::from("https://books.example.org/covers/epub2.png")),
+                    navigation_url: None,
+                    acquisitions: vec![
+                        epub_acquisition("https://books.example.org/books/epub2.epub"),
+                        OpdsAcquisition {
+                            url: String::from("https://mirror.example.org/epub2.pdf"),
+                            mime_type: Some(String::from("application/pdf")),
+                        },
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_an_opds2_feed() {
+        let feed = parse_feed(&fixture("opds2.json"), &base()).unwrap();
+        let search = find_link(&feed.links, "search").map(|l| l.href.clone());
+        let feed = into_feed(base(), feed, true);
+
+        assert_eq!(feed.title, "Mikomi Catalog");
+        assert_eq!(feed.next_url, None);
+        assert_eq!(
+            feed.previous_url.as_deref(),
+            Some("https://books.example.org/opds2/new?page=1")
+        );
+        assert_eq!(search.as_deref(), Some("/opds2/search{?query}"));
+        assert_eq!(
+            feed.entries,
+            vec![
+                navigation_entry("Science Fiction", "https://books.example.org/opds2/scifi"),
+                OpdsEntry {
+                    id: Some(String::from("urn:mikomi:fixture:epub3")),
+                    title: String::from("An EPUB3 Book"),
+                    authors: vec![String::from("Ada Writer"), String::from("Cy Translator")],
+                    summary: Some(String::from("A book for tests.")),
+                    cover_url: Some(String::from(
+                        "https://books.example.org/opds/covers/epub3.jpg"
+                    )),
+                    navigation_url: None,
+                    acquisitions: vec![epub_acquisition(
+                        "https://books.example.org/books/epub3.epub"
+                    )],
+                },
+                OpdsEntry {
+                    id: None,
+                    title: String::from("A Featured Book"),
+                    authors: vec![String::from("Bo Editor")],
+                    summary: None,
+                    cover_url: None,
+                    navigation_url: None,
+                    acquisitions: vec![epub_acquisition("https://lending.example.org/featured")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_feeds() {
+        assert!(parse_feed("{ not json", &base()).is_err());
+        assert!(parse_feed("<feed><title>Broken</feed>", &base()).is_err());
+    }
+
+    #[test]
+    fn it_expands_search_templates() {
+        let query = "Ada & Bo/2";
+        let encoded = "Ada%20%26%20Bo%2F2";
+
+        assert_eq!(
+            expand_search_template("/search?q={searchTerms}&page={startPage?}", query),
+            format!("/search?q={encoded}&page=")
+        );
+        assert_eq!(
+            expand_search_template("/search?q={searchTerms?}", query),
+            format!("/search?q={encoded}")
+        );
+        assert_eq!(
+            expand_search_template("/opds2/search{?query}", query),
+            format!("/opds2/search?query={encoded}")
+        );
+        assert_eq!(
+            expand_search_template("/search{?title,author}", query),
+            format!("/search?title={encoded}")
+        );
+        assert_eq!(
+            expand_search_template("/search?lang=en{&query}", query),
+            format!("/search?lang=en&query={encoded}")
+        );
+        assert_eq!(
+            expand_search_template("/search/{query}", query),
+            format!("/search/{encoded}")
+        );
+        assert_eq!(
+            expand_search_template("/search{?query", query),
+            "/search{?query"
+        );
+    }
+}
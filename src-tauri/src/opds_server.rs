@@ -0,0 +1,876 @@
+use crate::db::establish_connection;
+use crate::models;
+use crate::schema;
+use base64::Engine;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use mikomi_core::collections::get_collection_books;
+use mikomi_core::current_timestamp;
+use mikomi_core::settings::{get_app_setting, set_app_setting};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+use url::Url;
+
+pub const OPDS_SERVER_SETTINGS_KEY: &str = "opds_server";
+const DEFAULT_OPDS_SERVER_PORT: i32 = 8585;
+const OPDS_PAGE_SIZE: i64 = 50;
+const BIND_RETRIES: usize = 20;
+const BIND_RETRY_DELAY: Duration = Duration::from_millis(50);
+const NAVIGATION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+const ACQUISITION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const OPEN_SEARCH_TYPE: &str = "application/opensearchdescription+xml";
+const EPUB_MIME_TYPE: &str = "application/epub+zip";
+// Everything but the unreserved characters of RFC 3986
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Serialize, Deserialize)]
+struct OpdsServerSettings {
+    enabled: bool,
+    port: i32,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl Default for OpdsServerSettings {
+    fn default() -> Self {
+        OpdsServerSettings {
+            enabled: false,
+            port: DEFAULT_OPDS_SERVER_PORT,
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Serialize, Type)]
+pub struct OpdsServerStatus {
+    pub running: bool,
+    pub port: i32,
+    pub username: Option<String>,
+}
+
+struct RunningServer {
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+}
+
+static RUNNING_SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+
+fn read_settings(conn: &mut SqliteConnection) -> OpdsServerSettings {
+    get_app_setting(conn, OPDS_SERVER_SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(conn: &mut SqliteConnection, settings: &OpdsServerSettings) -> Result<(), String> {
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    let res = set_app_setting(conn, OPDS_SERVER_SETTINGS_KEY, Some(&json));
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update OPDS server settings")),
+    }
+}
+
+// Formats a unix timestamp as an RFC 3339 date, which is what Atom requires
+fn rfc3339(timestamp: i32) -> String {
+    let days = (timestamp as i64).div_euclid(86400);
+    let seconds = (timestamp as i64).rem_euclid(86400);
+
+    // Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+fn path_segment(value: &str) -> String {
+    utf8_percent_encode(value, PATH_SEGMENT).to_string()
+}
+
+fn cover_mime_type(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if data.starts_with(b"\xFF\xD8") {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        "image/webp"
+    } else if data.starts_with(b"<") {
+        "image/svg+xml"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+struct Feed {
+    id: String,
+    title: String,
+    href: String,
+    kind: &'static str,
+    links: Vec<String>,
+    entries: Vec<String>,
+}
+
+impl Feed {
+    fn new(id: &str, title: &str, href: &str, kind: &'static str) -> Feed {
+        Feed {
+            id: id.to_string(),
+            title: title.to_string(),
+            href: href.to_string(),
+            kind,
+            links: vec![],
+            entries: vec![],
+        }
+    }
+
+    fn link(&mut self, rel: &str, href: &str, mime_type: &str) {
+        self.links.push(format!(
+            r#"<link rel="{}" href="{}" type="{}"/>"#,
+            escape(rel),
+            escape(href),
+            escape(mime_type)
+        ));
+    }
+
+    fn navigation_entry(&mut self, id: &str, title: &str, href: &str, kind: &str) {
+        self.entries.push(format!(
+            r#"<entry><title>{}</title><id>{}</id><updated>{}</updated><link rel="subsection" href="{}" type="{}"/></entry>"#,
+            escape(title),
+            escape(id),
+            rfc3339(current_timestamp()),
+            escape(href),
+            kind
+        ));
+    }
+
+    fn book_entry(&mut self, book: &models::Book, authors: &[String]) {
+        let authors: String = authors
+            .iter()
+            .map(|a| format!("<author><name>{}</name></author>", escape(a)))
+            .collect();
+        let language = book
+            .language
+            .as_deref()
+            .map(|l| format!("<dc:language>{}</dc:language>", escape(l)))
+            .unwrap_or_default();
+        let summary = book
+            .description
+            .as_deref()
+            .map(|d| format!(r#"<summary type="text">{}</summary>"#, escape(d)))
+            .unwrap_or_default();
+        let base = format!("/opds/books/{}", path_segment(&book.id));
+
+        self.entries.push(format!(
+            concat!(
+                r#"<entry><title>{}</title><id>urn:uuid:{}</id><updated>{}</updated>{}{}{}"#,
+                r#"<link rel="http://opds-spec.org/image" href="{base}/cover"/>"#,
+                r#"<link rel="http://opds-spec.org/image/thumbnail" href="{base}/cover"/>"#,
+                r#"<link rel="http://opds-spec.org/acquisition" href="{base}/file" type="{}"/></entry>"#
+            ),
+            escape(&book.title),
+            escape(&book.id),
+            rfc3339(book.date_added),
+            authors,
+            language,
+            summary,
+            EPUB_MIME_TYPE,
+            base = escape(&base),
+        ));
+    }
+
+    fn to_xml(&self) -> String {
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/terms/" xmlns:opds="http://opds-spec.org/2010/catalog">"#,
+                r#"<id>{}</id><title>{}</title><updated>{}</updated>"#,
+                r#"<link rel="self" href="{}" type="{}"/>"#,
+                r#"<link rel="start" href="/opds" type="{}"/>"#,
+                r#"<link rel="search" href="/opds/search.xml" type="{}"/>"#,
+                r#"<link rel="search" href="/opds/search?q={{searchTerms}}" type="{}"/>"#,
+                "{}{}</feed>"
+            ),
+            escape(&self.id),
+            escape(&self.title),
+            rfc3339(current_timestamp()),
+            escape(&self.href),
+            self.kind,
+            NAVIGATION_TYPE,
+            OPEN_SEARCH_TYPE,
+            ACQUISITION_TYPE,
+            self.links.concat(),
+            self.entries.concat(),
+        )
+    }
+}
+
+fn book_authors(
+    conn: &mut SqliteConnection,
+    books: &[models::Book],
+) -> QueryResult<HashMap<String, Vec<String>>> {
+    let links: Vec<(String, String)> = schema::book_author_link::table
+        .inner_join(schema::author::table)
+        .filter(schema::book_author_link::book_id.eq_any(books.iter().map(|b| b.id.clone())))
+        .select((schema::book_author_link::book_id, schema::author::name))
+        .order(schema::book_author_link::primary_creator.desc())
+        .load(conn)?;
+
+    let mut authors: HashMap<String, Vec<String>> = HashMap::new();
+    for (book_id, name) in links {
+        authors.entry(book_id).or_default().push(name);
+    }
+    Ok(authors)
+}
+
+// Replaces the page in the query of a feed's URL, keeping its other parameters
+fn page_href(href: &str, page: i64) -> String {
+    let Ok(mut url) = Url::parse(&format!("http://localhost{}", href)) else {
+        return href.to_string();
+    };
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(key, _)| key != "page")
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", &page.to_string());
+
+    format!("{}?{}", url.path(), url.query().unwrap_or_default())
+}
+
+// Lists one page of the books, adding the links to the neighbouring pages
+fn acquisition_feed(
+    conn: &mut SqliteConnection,
+    mut feed: Feed,
+    books: Vec<models::Book>,
+    page: i64,
+) -> QueryResult<Feed> {
+    let page_count = (books.len() as i64 + OPDS_PAGE_SIZE - 1) / OPDS_PAGE_SIZE;
+    // Pages past the end show the last page, which also keeps the offset from overflowing
+    let page = page.clamp(1, page_count.max(1));
+    let books: Vec<models::Book> = books
+        .into_iter()
+        .skip(((page - 1) * OPDS_PAGE_SIZE) as usize)
+        .take(OPDS_PAGE_SIZE as usize)
+        .collect();
+
+    if page > 1 {
+        let href = page_href(&feed.href, page - 1);
+        feed.link("previous", &href, ACQUISITION_TYPE);
+    }
+    if page < page_count {
+        let href = page_href(&feed.href, page + 1);
+        feed.link("next", &href, ACQUISITION_TYPE);
+    }
+
+    let authors = book_authors(conn, &books)?;
+    for book in &books {
+        feed.book_entry(book, authors.get(&book.id).map_or(&[], |a| a.as_slice()));
+    }
+
+    Ok(feed)
+}
+
+fn library_books() -> schema::book::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+    schema::book::table
+        .filter(schema::book::deleted_at.is_null())
+        .order(schema::book::title)
+        .into_boxed()
+}
+
+fn search_books(conn: &mut SqliteConnection, query: &str) -> QueryResult<Vec<models::Book>> {
+    let pattern = format!(
+        "%{}%",
+        query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let author_book_ids = schema::book_author_link::table
+        .inner_join(schema::author::table)
+        .filter(schema::author::name.like(pattern.clone()).escape('\\'))
+        .select(schema::book_author_link::book_id);
+
+    library_books()
+        .filter(
+            schema::book::title
+                .like(pattern)
+                .escape('\\')
+                .or(schema::book::id.eq_any(author_book_ids)),
+        )
+        .select(models::Book::as_select())
+        .load(conn)
+}
+
+enum OpdsResponse {
+    Feed(Feed),
+    Xml(String, &'static str),
+    File(File, Option<String>),
+    Data(Vec<u8>, &'static str),
+    NotFound,
+}
+
+fn handle_path(
+    conn: &mut SqliteConnection,
+    segments: &[String],
+    query: &HashMap<String, String>,
+    href: &str,
+) -> QueryResult<OpdsResponse> {
+    let page: i64 = query
+        .get("page")
+        .and_then(|p| p.parse().ok())
+        .filter(|p| *p > 0)
+        .unwrap_or(1);
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+    let response = match segments.as_slice() {
+        ["opds"] => {
+            let mut feed = Feed::new("urn:mikomi:root", "Mikomi", href, NAVIGATION_TYPE);
+            feed.navigation_entry(
+                "urn:mikomi:books",
+                "All books",
+                "/opds/books",
+                ACQUISITION_TYPE,
+            );
+            feed.navigation_entry(
+                "urn:mikomi:authors",
+                "Authors",
+                "/opds/authors",
+                NAVIGATION_TYPE,
+            );
+            feed.navigation_entry(
+                "urn:mikomi:collections",
+                "Collections",
+                "/opds/collections",
+                NAVIGATION_TYPE,
+            );
+            feed.navigation_entry(
+                "urn:mikomi:languages",
+                "Languages",
+                "/opds/languages",
+                NAVIGATION_TYPE,
+            );
+            OpdsResponse::Feed(feed)
+        }
+        ["opds", "search.xml"] => OpdsResponse::Xml(
+            format!(
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">"#,
+                    r#"<ShortName>Mikomi</ShortName><Description>Search the library</Description>"#,
+                    r#"<Url type="{}" template="/opds/search?q={{searchTerms}}"/>"#,
+                    r#"</OpenSearchDescription>"#
+                ),
+                ACQUISITION_TYPE
+            ),
+            OPEN_SEARCH_TYPE,
+        ),
+        ["opds", "search"] => {
+            let q = query.get("q").map(|q| q.as_str()).unwrap_or_default();
+            let books = search_books(conn, q)?;
+            let feed = Feed::new(
+                "urn:mikomi:search",
+                &format!("Search: {}", q),
+                href,
+                ACQUISITION_TYPE,
+            );
+            OpdsResponse::Feed(acquisition_feed(conn, feed, books, page)?)
+        }
+        ["opds", "books"] => {
+            let books = library_books()
+                .select(models::Book::as_select())
+                .load(conn)?;
+            let feed = Feed::new("urn:mikomi:books", "All books", href, ACQUISITION_TYPE);
+            OpdsResponse::Feed(acquisition_feed(conn, feed, books, page)?)
+        }
+        ["opds", "books", id, file @ ("file" | "cover")] => {
+            let book: Option<models::Book> = library_books()
+                .filter(schema::book::id.eq(*id))
+                .select(models::Book::as_select())
+                .first(conn)
+                .optional()?;
+            let Some(book) = book else {
+                return Ok(OpdsResponse::NotFound);
+            };
+
+            if *file == "file" {
+                let file_name = Path::new(&book.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string());
+                match File::open(&book.path) {
+                    Ok(f) => OpdsResponse::File(f, file_name),
+                    Err(_) => OpdsResponse::NotFound,
+                }
+            } else {
+                match fs::read(Path::new("mikomi-data/covers").join(&book.id)) {
+                    Ok(data) => {
+                        let mime_type = cover_mime_type(&data);
+                        OpdsResponse::Data(data, mime_type)
+                    }
+                    Err(_) => OpdsResponse::NotFound,
+                }
+            }
+        }
+        ["opds", "authors"] => {
+            let authors: Vec<models::Author> = schema::author::table
+                .filter(
+                    schema::author::id.eq_any(
+                        schema::book_author_link::table
+                            .inner_join(schema::book::table)
+                            .filter(schema::book::deleted_at.is_null())
+                            .select(schema::book_author_link::author_id),
+                    ),
+                )
+                .select(models::Author::as_select())
+                .order(schema::author::name)
+                .load(conn)?;
+            let mut feed = Feed::new("urn:mikomi:authors", "Authors", href, NAVIGATION_TYPE);
+            for author in authors {
+                feed.navigation_entry(
+                    &format!("urn:mikomi:author:{}", author.id),
+                    &author.name,
+                    &format!("/opds/authors/{}", path_segment(&author.id)),
+                    ACQUISITION_TYPE,
+                );
+            }
+            OpdsResponse::Feed(feed)
+        }
+        ["opds", "authors", id] => {
+            let author: Option<models::Author> = schema::author::table
+                .find(*id)
+                .select(models::Author::as_select())
+                .first(conn)
+                .optional()?;
+            let Some(author) = author else {
+                return Ok(OpdsResponse::NotFound);
+            };
+            let books = library_books()
+                .filter(
+                    schema::book::id.eq_any(
+                        schema::book_author_link::table
+                            .filter(schema::book_author_link::author_id.eq(*id))
+                            .select(schema::book_author_link::book_id),
+                    ),
+                )
+                .select(models::Book::as_select())
+                .load(conn)?;
+            let feed = Feed::new(
+                &format!("urn:mikomi:author:{}", author.id),
+                &author.name,
+                href,
+                ACQUISITION_TYPE,
+            );
+            OpdsResponse::Feed(acquisition_feed(conn, feed, books, page)?)
+        }
+        ["opds", "collections"] => {
+            let collections: Vec<models::Collection> = schema::collection::table
+                .select(models::Collection::as_select())
+                .order(schema::collection::name)
+                .load(conn)?;
+            let mut feed = Feed::new(
+                "urn:mikomi:collections",
+                "Collections",
+                href,
+                NAVIGATION_TYPE,
+            );
+            for collection in collections {
+                feed.navigation_entry(
+                    &format!("urn:mikomi:collection:{}", collection.id),
+                    &collection.name,
+                    &format!("/opds/collections/{}", path_segment(&collection.id)),
+                    ACQUISITION_TYPE,
+                );
+            }
+            OpdsResponse::Feed(feed)
+        }
+        ["opds", "collections", id] => {
+            let collection: Option<models::Collection> = schema::collection::table
+                .find(*id)
+                .select(models::Collection::as_select())
+                .first(conn)
+                .optional()?;
+            let Some(collection) = collection else {
+                return Ok(OpdsResponse::NotFound);
+            };
+            let books = get_collection_books(conn, &collection)?;
+            let feed = Feed::new(
+                &format!("urn:mikomi:collection:{}", collection.id),
+                &collection.name,
+                href,
+                ACQUISITION_TYPE,
+            );
+            OpdsResponse::Feed(acquisition_feed(conn, feed, books, page)?)
+        }
+        ["opds", "languages"] => {
+            let languages: Vec<String> = schema::book::table
+                .filter(schema::book::deleted_at.is_null())
+                .filter(schema::book::language.is_not_null())
+                .select(schema::book::language.assume_not_null())
+                .distinct()
+                .order(schema::book::language)
+                .load(conn)?;
+            let mut feed = Feed::new("urn:mikomi:languages", "Languages", href, NAVIGATION_TYPE);
+            for language in languages {
+                feed.navigation_entry(
+                    &format!("urn:mikomi:language:{}", language),
+                    &language,
+                    &format!("/opds/languages/{}", path_segment(&language)),
+                    ACQUISITION_TYPE,
+                );
+            }
+            OpdsResponse::Feed(feed)
+        }
+        ["opds", "languages", language] => {
+            let books = library_books()
+                .filter(schema::book::language.eq(*language))
+                .select(models::Book::as_select())
+                .load(conn)?;
+            let feed = Feed::new(
+                &format!("urn:mikomi:language:{}", language),
+                language,
+                href,
+                ACQUISITION_TYPE,
+            );
+            OpdsResponse::Feed(acquisition_feed(conn, feed, books, page)?)
+        }
+        _ => OpdsResponse::NotFound,
+    };
+
+    Ok(response)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn is_authorized(request: &Request, username: &Option<String>, password: &Option<String>) -> bool {
+    let (Some(username), Some(password)) = (username, password) else {
+        return false;
+    };
+    let expected = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password))
+    );
+
+    request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == expected)
+}
+
+fn handle_request(request: Request, username: &Option<String>, password: &Option<String>) {
+    if !is_authorized(&request, username, password) {
+        let _ = request.respond(
+            Response::from_string("Unauthorized")
+                .with_status_code(StatusCode(401))
+                .with_header(header("WWW-Authenticate", r#"Basic realm="Mikomi""#)),
+        );
+        return;
+    }
+
+    let url = match Url::parse(&format!("http://localhost{}", request.url())) {
+        Ok(v) => v,
+        Err(_) => {
+            let _ = request.respond(Response::empty(StatusCode(400)));
+            return;
+        }
+    };
+    let segments: Vec<String> = url
+        .path_segments()
+        .map(|s| {
+            s.filter(|s| !s.is_empty())
+                .map(|s| percent_decode_str(s).decode_utf8_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let href = request.url().to_string();
+
+    let mut conn = establish_connection();
+    let res = handle_path(&mut conn, &segments, &query, &href);
+    drop(conn);
+
+    let _ = match res {
+        Ok(OpdsResponse::Feed(feed)) => request.respond(
+            Response::from_string(feed.to_xml()).with_header(header("Content-Type", feed.kind)),
+        ),
+        Ok(OpdsResponse::Xml(xml, mime_type)) => request
+            .respond(Response::from_string(xml).with_header(header("Content-Type", mime_type))),
+        Ok(OpdsResponse::File(file, file_name)) => {
+            let mut response =
+                Response::from_file(file).with_header(header("Content-Type", EPUB_MIME_TYPE));
+            if let Some(file_name) = file_name {
+                response = response.with_header(header(
+                    "Content-Disposition",
+                    &format!("attachment; filename*=UTF-8''{}", path_segment(&file_name)),
+                ));
+            }
+            request.respond(response)
+        }
+        Ok(OpdsResponse::Data(data, mime_type)) => request
+            .respond(Response::from_data(data).with_header(header("Content-Type", mime_type))),
+        Ok(OpdsResponse::NotFound) => {
+            request.respond(Response::from_string("Not found").with_status_code(StatusCode(404)))
+        }
+        Err(_) => request.respond(Response::empty(StatusCode(500))),
+    };
+}
+
+fn stop_server(running: RunningServer) {
+    running.server.unblock();
+    let _ = running.thread.join();
+}
+
+pub fn stop_running_server() {
+    let running = RUNNING_SERVER.lock().unwrap().take();
+    if let Some(running) = running {
+        stop_server(running);
+    }
+}
+
+fn running_server_port() -> Option<u16> {
+    RUNNING_SERVER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|running| running.server.server_addr().to_ip())
+        .map(|addr| addr.port())
+}
+
+// The new server is bound before the running one is stopped, so that a port that cannot be
+// used leaves the running server as it was. The running server's own port only becomes
+// free once it stops, and a little after, since its socket is closed by a thread of its own.
+fn bind_server(port: u16) -> Result<Arc<Server>, String> {
+    let mut server = Server::http(("0.0.0.0", port));
+    if server.is_err() && running_server_port() == Some(port) {
+        stop_running_server();
+        for _ in 0..BIND_RETRIES {
+            server = Server::http(("0.0.0.0", port));
+            if server.is_ok() {
+                break;
+            }
+            thread::sleep(BIND_RETRY_DELAY);
+        }
+    }
+
+    server
+        .map(Arc::new)
+        .map_err(|_| format!("Cannot listen on port {}", port))
+}
+
+fn start_server(settings: &OpdsServerSettings) -> Result<(), String> {
+    // The server listens on every network interface so that readers on other devices can reach
+    // it, so it never serves the library without a password
+    if settings.username.is_none() || settings.password.is_none() {
+        return Err(String::from(
+            "A username and password are required to share the library",
+        ));
+    }
+    let port = u16::try_from(settings.port).map_err(|_| String::from("Invalid port"))?;
+    let server = bind_server(port)?;
+
+    let username = settings.username.clone();
+    let password = settings.password.clone();
+    let thread = {
+        let server = server.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let username = username.clone();
+                let password = password.clone();
+                // Downloads can take a while, so they should not hold up the other readers
+                thread::spawn(move || handle_request(request, &username, &password));
+            }
+        })
+    };
+
+    let previous = RUNNING_SERVER
+        .lock()
+        .unwrap()
+        .replace(RunningServer { server, thread });
+    if let Some(previous) = previous {
+        stop_server(previous);
+    }
+    Ok(())
+}
+
+pub fn start_opds_server_if_enabled() -> Result<(), String> {
+    let mut conn = establish_connection();
+    let settings = read_settings(&mut conn);
+    drop(conn);
+
+    if !settings.enabled {
+        return Ok(());
+    }
+    start_server(&settings)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_opds_server_status() -> OpdsServerStatus {
+    let mut conn = establish_connection();
+    let settings = read_settings(&mut conn);
+
+    OpdsServerStatus {
+        running: RUNNING_SERVER.lock().unwrap().is_some(),
+        port: settings.port,
+        username: settings.username,
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn start_opds_server(
+    port: i32,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<(), String> {
+    let settings = OpdsServerSettings {
+        enabled: true,
+        port,
+        username: username.filter(|u| !u.is_empty()),
+        password: password.filter(|p| !p.is_empty()),
+    };
+    start_server(&settings)?;
+
+    let mut conn = establish_connection();
+    save_settings(&mut conn, &settings)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_opds_server() -> Result<(), String> {
+    stop_running_server();
+
+    let mut conn = establish_connection();
+    let settings = OpdsServerSettings {
+        enabled: false,
+        ..read_settings(&mut conn)
+    };
+    save_settings(&mut conn, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        acquisition_feed, running_server_port, start_server, stop_running_server, Feed,
+        OpdsServerSettings, ACQUISITION_TYPE,
+    };
+    use crate::models;
+    use mikomi_core::{books, current_timestamp, establish_connection, run_migrations};
+    use std::net::TcpListener;
+
+    fn book(id: &str) -> models::Book {
+        models::Book {
+            id: id.to_string(),
+            title: format!("Book {id}"),
+            path: format!("/library/{id}.epub"),
+            last_read: None,
+            date_added: current_timestamp(),
+            reading_status: models::ReadingStatus::PlanToRead,
+            language: None,
+            last_modified: None,
+            identifier: None,
+            published_date: None,
+            description: None,
+            publisher: None,
+            page_progression_direction: None,
+            deleted_at: None,
+        }
+    }
+
+    fn settings(port: u16) -> OpdsServerSettings {
+        OpdsServerSettings {
+            enabled: true,
+            port: port as i32,
+            username: Some(String::from("reader")),
+            password: Some(String::from("secret")),
+        }
+    }
+
+    fn free_port() -> u16 {
+        TcpListener::bind("0.0.0.0:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn pages_past_the_end_show_the_last_page() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        run_migrations(&mut conn).unwrap();
+        let books: Vec<models::Book> = (0..60).map(|i| book(&i.to_string())).collect();
+        for book in &books {
+            books::add_book(&mut conn, book, &[]).unwrap();
+        }
+
+        let feed = Feed::new("all", "All books", "/opds/books", ACQUISITION_TYPE);
+        let feed = acquisition_feed(&mut conn, feed, books, i64::MAX).unwrap();
+
+        assert_eq!(feed.entries.len(), 10);
+        assert!(feed.links.iter().any(|l| l.contains("page=1")));
+        assert!(!feed.links.iter().any(|l| l.contains(r#"rel="next""#)));
+    }
+
+    #[test]
+    fn restarting_keeps_the_running_server_when_the_port_is_taken() {
+        let port = free_port();
+        start_server(&settings(port)).unwrap();
+
+        let taken = TcpListener::bind("0.0.0.0:0").unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        assert!(start_server(&settings(taken_port)).is_err());
+        assert_eq!(running_server_port(), Some(port));
+
+        // Restarting on its own port rebinds it
+        start_server(&settings(port)).unwrap();
+        assert_eq!(running_server_port(), Some(port));
+
+        stop_running_server();
+        assert_eq!(running_server_port(), None);
+    }
+
+    #[test]
+    fn starting_without_credentials_fails() {
+        let settings = OpdsServerSettings {
+            password: None,
+            ..settings(free_port())
+        };
+
+        assert!(start_server(&settings).is_err());
+    }
+}
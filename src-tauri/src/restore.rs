@@ -0,0 +1,33 @@
+use crate::{api_server, backup, opds_server};
+use std::path::Path;
+
+// Stops everything that uses the database in the background, so that restoring never swaps the
+// library out from under a request or a snapshot, runs the restore, and then starts the servers
+// again with the settings of whichever library is now in place
+fn with_background_tasks_stopped(
+    restore: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    let scheduler = backup::pause_snapshot_scheduler();
+    api_server::stop_running_server();
+    opds_server::stop_running_server();
+
+    let res = restore();
+
+    drop(scheduler);
+    let _ = opds_server::start_opds_server_if_enabled();
+    let _ = api_server::start_api_server_if_enabled();
+
+    res
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn restore_backup(path: String) -> Result<(), String> {
+    with_background_tasks_stopped(|| backup::restore_backup_archive(Path::new(&path)).map(|_| ()))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn restore_snapshot(file_name: String) -> Result<(), String> {
+    with_background_tasks_stopped(|| backup::restore_snapshot_file(&file_name))
+}
@@ -0,0 +1,495 @@
+use crate::db::establish_connection;
+use crate::models;
+use crate::schema;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use mikomi_core::annotations::delete_highlight_notes;
+use mikomi_core::current_timestamp;
+use mikomi_core::settings::{get_app_setting, set_app_setting, set_book_progress};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use specta::Type;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub const SYNC_FOLDER_SETTING_KEY: &str = "sync_folder";
+pub const SYNC_DEVICE_ID_SETTING_KEY: &str = "sync_device_id";
+const SYNC_LOG_FORMAT_VERSION: i32 = 1;
+const SYNC_LOG_EXTENSION: &str = "json";
+const PROGRESS_RECORD_KEY: &str = "progress";
+const BOOKMARK_RECORD_PREFIX: &str = "bookmark:";
+const HIGHLIGHT_RECORD_PREFIX: &str = "highlight:";
+
+// The latest known state of a single record of a book. A `None` value is a tombstone that
+// records the deletion of the record so that it is not brought back by another device.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+struct SyncEntry {
+    value: Option<Value>,
+    modified: i32,
+    device_id: String,
+}
+
+// Every device only ever writes its own change log file in a book's directory, so that the
+// files do not conflict when the folder is shared by a file syncing service
+#[derive(Serialize, Deserialize)]
+struct SyncLog {
+    format_version: i32,
+    device_id: String,
+    records: BTreeMap<String, SyncEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProgressRecord {
+    percentage: Option<i32>,
+    last_element: Option<String>,
+    last_page: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookmarkRecord {
+    display_text: String,
+    date_added: i32,
+    css_selector: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HighlightRecord {
+    date_added: i32,
+    note: String,
+    start_container: String,
+    start_offset: i32,
+    end_container: String,
+    end_offset: i32,
+    color: String,
+}
+
+#[derive(Serialize, Type)]
+pub struct SyncSettings {
+    pub folder: Option<String>,
+    pub device_id: String,
+}
+
+#[derive(Serialize, Type)]
+pub struct SyncSummary {
+    pub books_synced: i32,
+    pub books_skipped: i32,
+    pub records_received: i32,
+}
+
+pub fn get_device_id(conn: &mut SqliteConnection) -> QueryResult<String> {
+    if let Some(device_id) = get_app_setting(conn, SYNC_DEVICE_ID_SETTING_KEY)? {
+        return Ok(device_id);
+    }
+
+    let device_id = Uuid::new_v4().to_string();
+    set_app_setting(conn, SYNC_DEVICE_ID_SETTING_KEY, Some(&device_id))?;
+    Ok(device_id)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Local ids differ between machines, so books are matched by their identifier, or by the
+// contents of their file when they do not have one
+fn sync_key(book: &models::Book) -> Option<String> {
+    if let Some(identifier) = book.identifier.as_deref().map(str::trim) {
+        if !identifier.is_empty() {
+            return Some(format!("id-{}", sha256_hex(identifier.as_bytes())));
+        }
+    }
+
+    let mut file = File::open(&book.path).ok()?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).ok()?;
+    Some(format!("file-{:x}", hasher.finalize()))
+}
+
+fn to_value<T: Serialize>(record: T) -> QueryResult<Value> {
+    serde_json::to_value(record).map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))
+}
+
+fn from_value<T: for<'de> Deserialize<'de>>(value: &Value) -> QueryResult<T> {
+    serde_json::from_value(value.clone())
+        .map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))
+}
+
+fn read_local_records(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+) -> QueryResult<BTreeMap<String, Value>> {
+    let mut records: BTreeMap<String, Value> = BTreeMap::new();
+
+    let settings: Option<models::BookSettings> = schema::book_settings::table
+        .filter(schema::book_settings::book_id.eq(book_id))
+        .select(models::BookSettings::as_select())
+        .first(conn)
+        .optional()?;
+    if let Some(settings) = settings {
+        if settings.percentage.is_some() || settings.last_element.is_some() {
+            records.insert(
+                String::from(PROGRESS_RECORD_KEY),
+                to_value(ProgressRecord {
+                    percentage: settings.percentage,
+                    last_element: settings.last_element,
+                    last_page: settings.last_page,
+                })?,
+            );
+        }
+    }
+
+    let bookmarks: Vec<models::Bookmark> = schema::bookmark::table
+        .filter(schema::bookmark::book_id.eq(book_id))
+        .select(models::Bookmark::as_select())
+        .load(conn)?;
+    for bookmark in bookmarks {
+        records.insert(
+            format!("{}{}", BOOKMARK_RECORD_PREFIX, bookmark.id),
+            to_value(BookmarkRecord {
+                display_text: bookmark.display_text,
+                date_added: bookmark.date_added,
+                css_selector: bookmark.css_selector,
+            })?,
+        );
+    }
+
+    let highlights: Vec<models::Highlight> = schema::highlight::table
+        .filter(schema::highlight::book_id.eq(book_id))
+        .select(models::Highlight::as_select())
+        .load(conn)?;
+    for highlight in highlights {
+        records.insert(
+            format!("{}{}", HIGHLIGHT_RECORD_PREFIX, highlight.id),
+            to_value(HighlightRecord {
+                date_added: highlight.date_added,
+                note: highlight.note,
+                start_container: highlight.start_container,
+                start_offset: highlight.start_offset,
+                end_container: highlight.end_container,
+                end_offset: highlight.end_offset,
+                color: highlight.color,
+            })?,
+        );
+    }
+
+    Ok(records)
+}
+
+fn apply_record(
+    conn: &mut SqliteConnection,
+    book_id: &str,
+    record_key: &str,
+    value: Option<&Value>,
+) -> QueryResult<()> {
+    if record_key == PROGRESS_RECORD_KEY {
+        // Progress is never deleted, only overwritten
+        let Some(value) = value else {
+            return Ok(());
+        };
+        let progress: ProgressRecord = from_value(value)?;
+        set_book_progress(
+            conn,
+            book_id,
+            progress.percentage,
+            progress.last_element.as_deref(),
+            progress.last_page,
+        )?;
+    } else if let Some(id) = record_key.strip_prefix(BOOKMARK_RECORD_PREFIX) {
+        match value {
+            Some(value) => {
+                let bookmark: BookmarkRecord = from_value(value)?;
+                diesel::replace_into(schema::bookmark::table)
+                    .values(models::Bookmark {
+                        id: id.to_string(),
+                        book_id: book_id.to_string(),
+                        display_text: bookmark.display_text,
+                        date_added: bookmark.date_added,
+                        css_selector: bookmark.css_selector,
+                    })
+                    .execute(conn)?;
+            }
+            None => {
+                diesel::delete(schema::bookmark::table.find(id)).execute(conn)?;
+            }
+        }
+    } else if let Some(id) = record_key.strip_prefix(HIGHLIGHT_RECORD_PREFIX) {
+        match value {
+            Some(value) => {
+                let highlight: HighlightRecord = from_value(value)?;
+                // Highlight color ids are generated on each machine, so the color is matched
+                // by its value instead
+                let highlight_color_id: Option<String> = schema::highlight_color::table
+                    .filter(schema::highlight_color::color.eq(&highlight.color))
+                    .select(schema::highlight_color::id)
+                    .first(conn)
+                    .optional()?;
+                diesel::replace_into(schema::highlight::table)
+                    .values(models::Highlight {
+                        id: id.to_string(),
+                        book_id: book_id.to_string(),
+                        date_added: highlight.date_added,
+                        note: highlight.note,
+                        start_container: highlight.start_container,
+                        start_offset: highlight.start_offset,
+                        end_container: highlight.end_container,
+                        end_offset: highlight.end_offset,
+                        color: highlight.color,
+                        highlight_color_id,
+                    })
+                    .execute(conn)?;
+            }
+            None => {
+                delete_highlight_notes(conn, vec![id.to_string()])?;
+                diesel::delete(schema::highlight::table.find(id)).execute(conn)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_remote_logs(book_dir: &Path, device_id: &str) -> Vec<SyncLog> {
+    let entries = match fs::read_dir(book_dir) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|e| e == SYNC_LOG_EXTENSION)
+                && path.file_stem().is_some_and(|s| s != device_id)
+        })
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|contents| serde_json::from_str::<SyncLog>(&contents).ok())
+        .filter(|log| log.format_version <= SYNC_LOG_FORMAT_VERSION && log.device_id != device_id)
+        .collect()
+}
+
+fn write_log(book_dir: &Path, log: &SyncLog) -> Result<(), String> {
+    fs::create_dir_all(book_dir).map_err(|e| e.to_string())?;
+
+    let path = book_dir.join(format!("{}.{}", log.device_id, SYNC_LOG_EXTENSION));
+    let partial_path = path.with_extension("partial");
+    let contents = serde_json::to_string_pretty(log).map_err(|e| e.to_string())?;
+    fs::write(&partial_path, contents).map_err(|e| e.to_string())?;
+    fs::rename(&partial_path, &path).map_err(|e| e.to_string())
+}
+
+// Returns the number of records that were changed by other devices
+fn sync_book(
+    conn: &mut SqliteConnection,
+    folder: &Path,
+    device_id: &str,
+    book: &models::Book,
+    key: &str,
+) -> Result<i32, String> {
+    let book_dir = folder.join(key);
+    let remote_logs = read_remote_logs(&book_dir, device_id);
+    let now = current_timestamp();
+
+    let res = conn.transaction(|conn| {
+        let local_records = read_local_records(conn, &book.id)?;
+        let known_records: HashMap<String, models::SyncRecord> = schema::sync_record::table
+            .filter(schema::sync_record::book_id.eq(&book.id))
+            .select(models::SyncRecord::as_select())
+            .load(conn)?
+            .into_iter()
+            .map(|r| (r.record_key.clone(), r))
+            .collect();
+
+        // Records that changed since the last sync are stamped with the current time, and
+        // records that disappeared since then become tombstones
+        let mut merged: BTreeMap<String, SyncEntry> = BTreeMap::new();
+        for (record_key, value) in &local_records {
+            let known = known_records.get(record_key).filter(|known| {
+                known
+                    .value
+                    .as_deref()
+                    .and_then(|v| serde_json::from_str::<Value>(v).ok())
+                    == Some(value.clone())
+            });
+            let entry = match known {
+                Some(known) => SyncEntry {
+                    value: Some(value.clone()),
+                    modified: known.modified,
+                    device_id: known.device_id.clone(),
+                },
+                None => SyncEntry {
+                    value: Some(value.clone()),
+                    modified: now,
+                    device_id: device_id.to_string(),
+                },
+            };
+            merged.insert(record_key.clone(), entry);
+        }
+        for (record_key, known) in &known_records {
+            if local_records.contains_key(record_key) {
+                continue;
+            }
+            let entry = match known.value {
+                Some(_) => SyncEntry {
+                    value: None,
+                    modified: now,
+                    device_id: device_id.to_string(),
+                },
+                None => SyncEntry {
+                    value: None,
+                    modified: known.modified,
+                    device_id: known.device_id.clone(),
+                },
+            };
+            merged.insert(record_key.clone(), entry);
+        }
+
+        // Last writer wins, with the device id breaking ties so that every device picks the
+        // same entry
+        for log in &remote_logs {
+            for (record_key, entry) in &log.records {
+                let newer = match merged.get(record_key) {
+                    Some(current) => {
+                        (entry.modified, &entry.device_id) > (current.modified, &current.device_id)
+                    }
+                    None => true,
+                };
+                if newer {
+                    merged.insert(record_key.clone(), entry.clone());
+                }
+            }
+        }
+
+        let mut records_received = 0;
+        for (record_key, entry) in &merged {
+            if local_records.get(record_key) != entry.value.as_ref() {
+                apply_record(conn, &book.id, record_key, entry.value.as_ref())?;
+                records_received += 1;
+            }
+        }
+
+        diesel::delete(
+            schema::sync_record::table.filter(schema::sync_record::book_id.eq(&book.id)),
+        )
+        .execute(conn)?;
+        let sync_records: Vec<models::SyncRecord> = merged
+            .iter()
+            .map(|(record_key, entry)| models::SyncRecord {
+                book_id: book.id.clone(),
+                record_key: record_key.clone(),
+                value: entry.value.as_ref().map(|v| v.to_string()),
+                modified: entry.modified,
+                device_id: entry.device_id.clone(),
+            })
+            .collect();
+        diesel::insert_into(schema::sync_record::table)
+            .values(&sync_records)
+            .execute(conn)?;
+
+        diesel::result::QueryResult::Ok(records_received)
+    });
+
+    let records_received = res.map_err(|_| format!("Cannot sync \"{}\"", book.title))?;
+
+    let merged: BTreeMap<String, SyncEntry> = schema::sync_record::table
+        .filter(schema::sync_record::book_id.eq(&book.id))
+        .select(models::SyncRecord::as_select())
+        .load(conn)
+        .map_err(|_| format!("Cannot sync \"{}\"", book.title))?
+        .into_iter()
+        .map(|r| {
+            (
+                r.record_key,
+                SyncEntry {
+                    value: r.value.and_then(|v| serde_json::from_str(&v).ok()),
+                    modified: r.modified,
+                    device_id: r.device_id,
+                },
+            )
+        })
+        .collect();
+    if !merged.is_empty() {
+        write_log(
+            &book_dir,
+            &SyncLog {
+                format_version: SYNC_LOG_FORMAT_VERSION,
+                device_id: device_id.to_string(),
+                records: merged,
+            },
+        )?;
+    }
+
+    Ok(records_received)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_sync_settings() -> Result<SyncSettings, String> {
+    let mut conn: SqliteConnection = establish_connection();
+
+    let folder = get_app_setting(&mut conn, SYNC_FOLDER_SETTING_KEY)
+        .map_err(|_| String::from("Cannot get sync settings"))?;
+    let device_id =
+        get_device_id(&mut conn).map_err(|_| String::from("Cannot get sync settings"))?;
+
+    Ok(SyncSettings { folder, device_id })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_sync_folder(folder: Option<String>) -> Result<(), String> {
+    if let Some(folder) = &folder {
+        if !Path::new(folder).is_dir() {
+            return Err(String::from("Sync folder does not exist"));
+        }
+    }
+
+    let mut conn: SqliteConnection = establish_connection();
+    let res = set_app_setting(&mut conn, SYNC_FOLDER_SETTING_KEY, folder.as_deref());
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("Cannot update sync folder")),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn sync_now() -> Result<SyncSummary, String> {
+    let mut conn: SqliteConnection = establish_connection();
+
+    let folder: PathBuf = get_app_setting(&mut conn, SYNC_FOLDER_SETTING_KEY)
+        .map_err(|_| String::from("Cannot get sync settings"))?
+        .map(PathBuf::from)
+        .ok_or(String::from("No sync folder is set"))?;
+    if !folder.is_dir() {
+        return Err(String::from("Sync folder does not exist"));
+    }
+    let device_id =
+        get_device_id(&mut conn).map_err(|_| String::from("Cannot get sync settings"))?;
+
+    let books: Vec<models::Book> = schema::book::table
+        .filter(schema::book::deleted_at.is_null())
+        .select(models::Book::as_select())
+        .load(&mut conn)
+        .map_err(|_| String::from("Cannot get books"))?;
+
+    let mut summary = SyncSummary {
+        books_synced: 0,
+        books_skipped: 0,
+        records_received: 0,
+    };
+    for book in books {
+        let Some(key) = sync_key(&book) else {
+            summary.books_skipped += 1;
+            continue;
+        };
+        summary.records_received += sync_book(&mut conn, &folder, &device_id, &book, &key)?;
+        summary.books_synced += 1;
+    }
+
+    Ok(summary)
+}
@@ -22,6 +22,10 @@ export function getBooksBelongingToCollections(collectionId: string) {
     return invoke()<CollectionWithBooks>("get_books_belonging_to_collections", { collectionId })
 }
 
+export function getBooksBelongingToCollectionTree(collectionId: string) {
+    return invoke()<CollectionWithBooks>("get_books_belonging_to_collection_tree", { collectionId })
+}
+
 export function addBookFromFile(path: string) {
     return invoke()<Book>("add_book_from_file", { path })
 }
@@ -34,7 +38,7 @@ export function updateBook(book: Book) {
     return invoke()<null>("update_book", { book })
 }
 
-export function updateBookReadingStatus(id: string, readingStatus: string) {
+export function updateBookReadingStatus(id: string, readingStatus: ReadingStatus) {
     return invoke()<null>("update_book_reading_status", { id,readingStatus })
 }
 
@@ -42,6 +46,238 @@ export function removeBook(id: string) {
     return invoke()<null>("remove_book", { id })
 }
 
+export function getTrashedBooks() {
+    return invoke()<BookWithCover[]>("get_trashed_books")
+}
+
+export function restoreBooks(bookIds: string[]) {
+    return invoke()<null>("restore_books", { bookIds })
+}
+
+export function removeBookPermanently(id: string) {
+    return invoke()<null>("remove_book_permanently", { id })
+}
+
+export function emptyTrash() {
+    return invoke()<null>("empty_trash")
+}
+
+export function getJournalEntries() {
+    return invoke()<JournalEntrySummary[]>("get_journal_entries")
+}
+
+export function undoLast() {
+    return invoke()<string | null>("undo_last")
+}
+
+export function redo() {
+    return invoke()<string | null>("redo")
+}
+
+export function createBackup(path: string) {
+    return invoke()<null>("create_backup", { path })
+}
+
+export function restoreBackup(path: string) {
+    return invoke()<null>("restore_backup", { path })
+}
+
+export function getSnapshotSettings() {
+    return invoke()<SnapshotSettings>("get_snapshot_settings")
+}
+
+export function updateSnapshotSettings(settings: SnapshotSettings) {
+    return invoke()<null>("update_snapshot_settings", { settings })
+}
+
+export function getSnapshots() {
+    return invoke()<Snapshot[]>("get_snapshots")
+}
+
+export function createSnapshot() {
+    return invoke()<null>("create_snapshot")
+}
+
+export function getSyncSettings() {
+    return invoke()<SyncSettings>("get_sync_settings")
+}
+
+export function updateSyncFolder(folder: string | null) {
+    return invoke()<null>("update_sync_folder", { folder })
+}
+
+export function syncNow() {
+    return invoke()<SyncSummary>("sync_now")
+}
+
+export function getKoreaderSyncSettings() {
+    return invoke()<KOReaderSyncSettings | null>("get_koreader_sync_settings")
+}
+
+export function registerKoreaderSyncUser(serverUrl: string, username: string, password: string, documentMatching: DocumentMatching) {
+    return invoke()<null>("register_koreader_sync_user", { serverUrl,username,password,documentMatching })
+}
+
+export function loginKoreaderSync(serverUrl: string, username: string, password: string, documentMatching: DocumentMatching) {
+    return invoke()<null>("login_koreader_sync", { serverUrl,username,password,documentMatching })
+}
+
+export function logoutKoreaderSync() {
+    return invoke()<null>("logout_koreader_sync")
+}
+
+export function pushKoreaderProgress(bookId: string) {
+    return invoke()<null>("push_koreader_progress", { bookId })
+}
+
+export function pullKoreaderProgress(bookId: string) {
+    return invoke()<KOReaderProgress | null>("pull_koreader_progress", { bookId })
+}
+
+export function getOpdsCatalogs() {
+    return invoke()<OpdsCatalog[]>("get_opds_catalogs")
+}
+
+export function addOpdsCatalog(name: string, url: string, username: string | null, password: string | null) {
+    return invoke()<OpdsCatalog>("add_opds_catalog", { name,url,username,password })
+}
+
+export function updateOpdsCatalog(id: string, name: string, url: string, username: string | null, password: string | null) {
+    return invoke()<null>("update_opds_catalog", { id,name,url,username,password })
+}
+
+export function removeOpdsCatalog(id: string) {
+    return invoke()<null>("remove_opds_catalog", { id })
+}
+
+export function browseOpdsCatalog(catalogId: string, url: string | null) {
+    return invoke()<OpdsFeed>("browse_opds_catalog", { catalogId,url })
+}
+
+export function searchOpdsCatalog(catalogId: string, query: string) {
+    return invoke()<OpdsFeed>("search_opds_catalog", { catalogId,query })
+}
+
+export function downloadOpdsBook(catalogId: string, url: string) {
+    return invoke()<Book>("download_opds_book", { catalogId,url })
+}
+
+export function getOpdsServerStatus() {
+    return invoke()<OpdsServerStatus>("get_opds_server_status")
+}
+
+export function startOpdsServer(port: number, username: string | null, password: string | null) {
+    return invoke()<null>("start_opds_server", { port,username,password })
+}
+
+export function stopOpdsServer() {
+    return invoke()<null>("stop_opds_server")
+}
+
+export function getApiServerStatus() {
+    return invoke()<ApiServerStatus>("get_api_server_status")
+}
+
+export function startApiServer(port: number) {
+    return invoke()<null>("start_api_server", { port })
+}
+
+export function stopApiServer() {
+    return invoke()<null>("stop_api_server")
+}
+
+export function regenerateApiToken() {
+    return invoke()<string>("regenerate_api_token")
+}
+
+export function restoreSnapshot(fileName: string) {
+    return invoke()<null>("restore_snapshot", { fileName })
+}
+
+export function getTrashRetentionPeriod() {
+    return invoke()<number>("get_trash_retention_period")
+}
+
+export function updateTrashRetentionPeriod(days: number) {
+    return invoke()<null>("update_trash_retention_period", { days })
+}
+
+export function bulkUpdateReadingStatus(bookIds: string[], readingStatus: ReadingStatus) {
+    return invoke()<BulkOperationResult[]>("bulk_update_reading_status", { bookIds,readingStatus })
+}
+
+export function bulkAddBooksToCollections(bookIds: string[], collectionIds: string[]) {
+    return invoke()<BulkOperationResult[]>("bulk_add_books_to_collections", { bookIds,collectionIds })
+}
+
+export function bulkRemoveBooksFromCollections(bookIds: string[], collectionIds: string[]) {
+    return invoke()<BulkOperationResult[]>("bulk_remove_books_from_collections", { bookIds,collectionIds })
+}
+
+export function bulkUpdateBookSettings(bookIds: string[], settings: BulkBookSettings) {
+    return invoke()<BulkOperationResult[]>("bulk_update_book_settings", { bookIds,settings })
+}
+
+export function bulkUpdateBookLanguage(bookIds: string[], language: string | null) {
+    return invoke()<BulkOperationResult[]>("bulk_update_book_language", { bookIds,language })
+}
+
+export function bulkRemoveBooks(bookIds: string[]) {
+    return invoke()<BulkOperationResult[]>("bulk_remove_books", { bookIds })
+}
+
+export function startReadingSession(bookId: string, percentage: number | null) {
+    return invoke()<ReadingSession>("start_reading_session", { bookId,percentage })
+}
+
+export function heartbeatReadingSession(id: string, percentage: number | null, pagesRead: number, charactersRead: number) {
+    return invoke()<null>("heartbeat_reading_session", { id,percentage,pagesRead,charactersRead })
+}
+
+export function endReadingSession(id: string, percentage: number | null, pagesRead: number, charactersRead: number) {
+    return invoke()<null>("end_reading_session", { id,percentage,pagesRead,charactersRead })
+}
+
+export function getReadingTimePerDay(from: number, to: number) {
+    return invoke()<ReadingTimePerDay[]>("get_reading_time_per_day", { from,to })
+}
+
+export function getReadingTimePerBook() {
+    return invoke()<ReadingTimePerBook[]>("get_reading_time_per_book")
+}
+
+export function getReadingTimePerAuthor() {
+    return invoke()<ReadingTimePerAuthor[]>("get_reading_time_per_author")
+}
+
+export function getBookReadingStatistics(bookId: string) {
+    return invoke()<BookReadingStatistics>("get_book_reading_statistics", { bookId })
+}
+
+export function getReadingStatusEvents(bookId: string) {
+    return invoke()<ReadingStatusEvent[]>("get_reading_status_events", { bookId })
+}
+
+export function getReadingHistories() {
+    return invoke()<ReadingHistory[]>("get_reading_histories")
+}
+
+export function getReadingGoals() {
+    return invoke()<ReadingGoal[]>("get_reading_goals")
+}
+
+export function addReadingGoal(newReadingGoal: ReadingGoal) {
+    return invoke()<null>("add_reading_goal", { newReadingGoal })
+}
+
+export function removeReadingGoal(id: string) {
+    return invoke()<null>("remove_reading_goal", { id })
+}
+
+export function getReadingGoalProgress(year: number) {
+    return invoke()<ReadingGoalProgress>("get_reading_goal_progress", { year })
+}
+
 export function addBookmark(newBookmark: Bookmark) {
     return invoke()<null>("add_bookmark", { newBookmark })
 }
@@ -62,8 +298,56 @@ export function removeHighlight(id: string) {
     return invoke()<null>("remove_highlight", { id })
 }
 
-export function updateHighlight(id: string, note: string, color: string) {
-    return invoke()<null>("update_highlight", { id,note,color })
+export function updateHighlight(id: string, note: string, color: string, highlightColorId: string | null) {
+    return invoke()<null>("update_highlight", { id,note,color,highlightColorId })
+}
+
+export function getHighlightNotes(highlightId: string) {
+    return invoke()<HighlightNote[]>("get_highlight_notes", { highlightId })
+}
+
+export function getHighlightNoteRevisions(highlightNoteId: string) {
+    return invoke()<HighlightNoteRevision[]>("get_highlight_note_revisions", { highlightNoteId })
+}
+
+export function addHighlightNote(newHighlightNote: HighlightNote) {
+    return invoke()<null>("add_highlight_note", { newHighlightNote })
+}
+
+export function updateHighlightNote(id: string, content: string) {
+    return invoke()<null>("update_highlight_note", { id,content })
+}
+
+export function removeHighlightNote(id: string) {
+    return invoke()<null>("remove_highlight_note", { id })
+}
+
+export function getHighlights(bookId: string | null, highlightColorIds: string[]) {
+    return invoke()<Highlight[]>("get_highlights", { bookId,highlightColorIds })
+}
+
+export function getHighlightsGroupedByColor(bookId: string | null) {
+    return invoke()<HighlightColorWithHighlights[]>("get_highlights_grouped_by_color", { bookId })
+}
+
+export function getHighlightColors() {
+    return invoke()<HighlightColor[]>("get_highlight_colors")
+}
+
+export function addHighlightColor(newHighlightColor: HighlightColor) {
+    return invoke()<null>("add_highlight_color", { newHighlightColor })
+}
+
+export function updateHighlightColor(id: string, name: string, color: string) {
+    return invoke()<null>("update_highlight_color", { id,name,color })
+}
+
+export function reorderHighlightColors(highlightColors: HighlightColorIdWithSortOrder[]) {
+    return invoke()<null>("reorder_highlight_colors", { highlightColors })
+}
+
+export function removeHighlightColor(id: string) {
+    return invoke()<null>("remove_highlight_color", { id })
 }
 
 export function addBookSettings(newBookSettings: BookSettings) {
@@ -78,6 +362,70 @@ export function updateBookSettings(bookId: string, newBookSettings: BookSettings
     return invoke()<null>("update_book_settings", { bookId,newBookSettings })
 }
 
+export function getReaderSettingsDefaults() {
+    return invoke()<ReaderSettingsDefault[]>("get_reader_settings_defaults")
+}
+
+export function addReaderSettingsDefault(newReaderSettingsDefault: ReaderSettingsDefault) {
+    return invoke()<null>("add_reader_settings_default", { newReaderSettingsDefault })
+}
+
+export function removeReaderSettingsDefault(id: string) {
+    return invoke()<null>("remove_reader_settings_default", { id })
+}
+
+export function getEffectiveSettings(bookId: string) {
+    return invoke()<EffectiveBookSettings>("get_effective_settings", { bookId })
+}
+
+export function resetBookSettingsToDefaults(bookId: string) {
+    return invoke()<null>("reset_book_settings_to_defaults", { bookId })
+}
+
+export function getGlobalUserCss() {
+    return invoke()<string | null>("get_global_user_css")
+}
+
+export function updateGlobalUserCss(userCss: string | null) {
+    return invoke()<null>("update_global_user_css", { userCss })
+}
+
+export function getBookUserCss(bookId: string) {
+    return invoke()<string | null>("get_book_user_css", { bookId })
+}
+
+export function updateBookUserCss(bookId: string, userCss: string | null) {
+    return invoke()<null>("update_book_user_css", { bookId,userCss })
+}
+
+export function getBookStylesheets(bookId: string) {
+    return invoke()<EpubStylesheet[]>("get_book_stylesheets", { bookId })
+}
+
+export function setBookStylesheetDisabled(bookId: string, path: string, disabled: boolean) {
+    return invoke()<null>("set_book_stylesheet_disabled", { bookId,path,disabled })
+}
+
+export function getReaderSettingsProfiles() {
+    return invoke()<ReaderSettingsProfile[]>("get_reader_settings_profiles")
+}
+
+export function addReaderSettingsProfile(newReaderSettingsProfile: ReaderSettingsProfile) {
+    return invoke()<null>("add_reader_settings_profile", { newReaderSettingsProfile })
+}
+
+export function updateReaderSettingsProfile(readerSettingsProfile: ReaderSettingsProfile) {
+    return invoke()<null>("update_reader_settings_profile", { readerSettingsProfile })
+}
+
+export function removeReaderSettingsProfile(id: string) {
+    return invoke()<null>("remove_reader_settings_profile", { id })
+}
+
+export function applyReaderSettingsProfile(id: string, target: ReaderSettingsProfileTarget) {
+    return invoke()<BulkOperationResult[]>("apply_reader_settings_profile", { id,target })
+}
+
 export function getReaderThemes() {
     return invoke()<ReaderTheme[]>("get_reader_themes")
 }
@@ -90,8 +438,32 @@ export function removeReaderTheme(id: string) {
     return invoke()<null>("remove_reader_theme", { id })
 }
 
-export function updateReaderTheme(id: string, name: string) {
-    return invoke()<null>("update_reader_theme", { id,name })
+export function updateReaderTheme(readerTheme: ReaderTheme) {
+    return invoke()<null>("update_reader_theme", { readerTheme })
+}
+
+export function setBookReaderTheme(bookId: string, readerThemeId: string | null) {
+    return invoke()<null>("set_book_reader_theme", { bookId,readerThemeId })
+}
+
+export function exportReaderThemes(ids: string[], path: string) {
+    return invoke()<null>("export_reader_themes", { ids,path })
+}
+
+export function importReaderThemes(path: string) {
+    return invoke()<ReaderTheme[]>("import_reader_themes", { path })
+}
+
+export function getFonts() {
+    return invoke()<FontWithPath[]>("get_fonts")
+}
+
+export function addFontFromFile(path: string) {
+    return invoke()<FontWithPath>("add_font_from_file", { path })
+}
+
+export function removeFont(id: string) {
+    return invoke()<null>("remove_font", { id })
 }
 
 export function getCollections() {
@@ -118,8 +490,16 @@ export function reorderBooksInCollection(bookCollectionLinks: BookCollectionLink
     return invoke()<null>("reorder_books_in_collection", { bookCollectionLinks })
 }
 
-export function removeCollection(id: string) {
-    return invoke()<null>("remove_collection", { id })
+export function removeCollection(id: string, policy: RemoveCollectionPolicy | null) {
+    return invoke()<null>("remove_collection", { id,policy })
+}
+
+export function moveCollection(id: string, parentId: string | null, sortOrder: number | null) {
+    return invoke()<null>("move_collection", { id,parentId,sortOrder })
+}
+
+export function getCollectionTree() {
+    return invoke()<CollectionTreeNode[]>("get_collection_tree")
 }
 
 export function addBookToCollections(bookId: string, collectionIds: string[]) {
@@ -134,17 +514,60 @@ export function getLanguages() {
     return invoke()<Language[]>("get_languages")
 }
 
-export type Highlight = { id: string; book_id: string; date_added: number; note: string; start_container: string; start_offset: number; end_container: string; end_offset: number; color: string }
-export type BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: string; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null }) & { authors: Author[]; bookmarks: Bookmark[]; highlights: Highlight[]; collections: Collection[]; cover: string | null; settings: BookSettings | null }
+export type Highlight = { id: string; book_id: string; date_added: number; note: string; start_container: string; start_offset: number; end_container: string; end_offset: number; color: string; highlight_color_id: string | null }
+export type BookWithAuthorsAndCoverAndBookmarksAndHighlightsAndSettingsAndCollections = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: ReadingStatus; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null; deleted_at: number | null }) & { authors: Author[]; bookmarks: Bookmark[]; highlights: Highlight[]; collections: Collection[]; cover: string | null; settings: BookSettings | null }
 export type Language = { name: string }
 export type Bookmark = { id: string; book_id: string; display_text: string; date_added: number; css_selector: string }
-export type BookSettings = { id: string; book_id: string; width: number | null; height: number | null; percentage: number | null; last_element: string | null; last_page: number | null; font_size: number; line_height: string; margins: number; text_align: string; column_count: number; writing_mode: string; font_family: string; background_color: string; color: string; link_color: string; primary_color: string; image_blend_mode: string }
-export type Book = { id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: string; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null }
-export type ReaderTheme = { id: string; name: string; background_color: string; color: string; link_color: string; primary_color: string; image_blend_mode: string }
+export type BookSettings = { id: string; book_id: string; width: number | null; height: number | null; percentage: number | null; last_element: string | null; last_page: number | null; font_size: number | null; line_height: string | null; margins: number | null; text_align: string | null; column_count: number | null; writing_mode: string | null; font_family: string | null; background_color: string | null; color: string | null; link_color: string | null; primary_color: string | null; image_blend_mode: string | null; reader_theme_id: string | null; user_css: string | null }
+export type EffectiveBookSettings = { book_id: string; width: number | null; height: number | null; percentage: number | null; last_element: string | null; last_page: number | null; font_size: number; line_height: string; margins: number; text_align: string; column_count: number; writing_mode: string; font_family: string; background_color: string; color: string; link_color: string; primary_color: string; image_blend_mode: string; reader_theme_id: string | null }
+export type Book = { id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: ReadingStatus; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null; deleted_at: number | null }
+export type ReaderTheme = { id: string; name: string; background_color: string; color: string; link_color: string; primary_color: string; image_blend_mode: string; built_in?: boolean }
 export type BookCollectionLink = { book_id: string; collection_id: string; sort_order: number | null }
-export type Collection = { id: string; name: string; sort_order: number | null }
+export type Collection = { id: string; name: string; sort_order: number | null; smart_filter: SmartCollectionFilter | null; parent_id: string | null }
 export type Author = { id: string; name: string }
-export type BookWithAuthorsAndCoverAndSettingsAndCollections = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: string; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null }) & { authors: Author[]; cover: string | null; settings: BookSettings | null; collections: Collection[] }
+export type BookWithAuthorsAndCoverAndSettingsAndCollections = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: ReadingStatus; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null; deleted_at: number | null }) & { authors: Author[]; cover: string | null; settings: BookSettings | null; collections: Collection[] }
 export type CollectionIdWithSortOrder = { id: string; sort_order: number }
-export type BookWithCover = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: string; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null }) & { cover: string | null }
+export type BookWithCover = ({ id: string; title: string; path: string; last_read: number | null; date_added: number; reading_status: ReadingStatus; language: string | null; last_modified: string | null; identifier: string | null; published_date: string | null; description: string | null; publisher: string | null; page_progression_direction: string | null; deleted_at: number | null }) & { cover: string | null }
 export type CollectionWithBooks = { collection: Collection; books: BookWithCover[] }
+export type ReadingStatus = "Reading" | "Plan to read" | "Finished"
+export type JournalEntrySummary = { id: string; description: string; undone: boolean; date_added: number }
+export type SnapshotSettings = { on_startup: boolean; on_exit: boolean; interval_hours: number | null; keep: number }
+export type Snapshot = { file_name: string; date_added: number; size: string }
+export type SyncSettings = { folder: string | null; device_id: string }
+export type SyncSummary = { books_synced: number; books_skipped: number; records_received: number }
+export type KOReaderSyncSettings = { server_url: string; username: string; document_matching: DocumentMatching }
+export type DocumentMatching = "binary" | "file_name"
+export type KOReaderProgress = { percentage: number; device: string | null; timestamp: number | null }
+export type OpdsCatalog = { id: string; name: string; url: string; username: string | null; date_added: number }
+export type OpdsFeed = { url: string; title: string; entries: OpdsEntry[]; next_url: string | null; previous_url: string | null; searchable: boolean }
+export type OpdsServerStatus = { running: boolean; port: number; username: string | null }
+export type ApiServerStatus = { running: boolean; port: number; token: string }
+export type BulkOperationResult = { book_id: string; success: boolean; error: string | null }
+export type BulkBookSettings = { font_size: number | null; line_height: string | null; margins: number | null; text_align: string | null; column_count: number | null; writing_mode: string | null; font_family: string | null; reader_theme_id: string | null }
+export type ReadingSession = { id: string; book_id: string; start_time: number; end_time: number; start_percentage: number | null; end_percentage: number | null; pages_read: number; characters_read: number }
+export type ReadingTimePerDay = { day: string; seconds_read: number; pages_read: number; characters_read: number }
+export type ReadingTimePerBook = { book_id: string; title: string; seconds_read: number; pages_read: number; characters_read: number; session_count: number }
+export type ReadingTimePerAuthor = { author_id: string; name: string; seconds_read: number; pages_read: number; characters_read: number; book_count: number }
+export type BookReadingStatistics = { book_id: string; seconds_read: number; pages_read: number; characters_read: number; session_count: number; percentage: number | null; percentage_per_hour: number | null; pages_per_hour: number | null; characters_per_minute: number | null; estimated_seconds_left: number | null }
+export type ReadingStatusEvent = { id: string; book_id: string; reading_status: ReadingStatus; previous_reading_status: ReadingStatus | null; date_added: number }
+export type ReadingHistory = { book_id: string; date_started: number | null; date_finished: number | null; reread_count: number }
+export type ReadingGoal = { id: string; goal_type: ReadingGoalType; target: number; year: number | null }
+export type ReadingGoalProgress = { year: number; books_per_year_goal: number | null; books_completed: number; books_completed_per_month: BooksCompletedInMonth[]; minutes_per_day_goal: number | null; minutes_read_today: number; current_streak: number; longest_streak: number }
+export type HighlightNote = { id: string; highlight_id: string; parent_id: string | null; content: string; date_added: number; date_modified: number | null }
+export type HighlightNoteRevision = { id: string; highlight_note_id: string; content: string; date_added: number }
+export type HighlightColorWithHighlights = { highlight_color: HighlightColor | null; highlights: Highlight[] }
+export type HighlightColor = { id: string; name: string; color: string; sort_order: number | null }
+export type HighlightColorIdWithSortOrder = { id: string; sort_order: number }
+export type ReaderSettingsDefault = { id: string; language: string | null; collection_id: string | null; font_size: number | null; line_height: string | null; margins: number | null; text_align: string | null; column_count: number | null; writing_mode: string | null; font_family: string | null; background_color: string | null; color: string | null; link_color: string | null; primary_color: string | null; image_blend_mode: string | null; reader_theme_id: string | null }
+export type EpubStylesheet = { path: string; disabled: boolean }
+export type ReaderSettingsProfile = { id: string; name: string; font_size: number; line_height: string; margins: number; text_align: string; column_count: number; writing_mode: string; font_family: string }
+export type ReaderSettingsProfileTarget = { Book: string } | { Collection: string } | { Books: string[] }
+export type FontWithPath = ({ id: string; family: string; style: string; weight: number; format: string; date_added: number }) & { path: string }
+export type RemoveCollectionPolicy = "Cascade" | "Reparent"
+export type CollectionTreeNode = { collection: Collection; book_count: number; total_book_count: number; children: CollectionTreeNode[] }
+export type OpdsEntry = { id: string | null; title: string; authors: string[]; summary: string | null; cover_url: string | null; navigation_url: string | null; acquisitions: OpdsAcquisition[] }
+export type ReadingGoalType = "Books per year" | "Minutes per day"
+export type BooksCompletedInMonth = { month: string; books_completed: number }
+export type SmartCollectionFilter = { match_all: boolean; rules: SmartCollectionRule[] }
+export type OpdsAcquisition = { url: string; mime_type: string | null }
+export type SmartCollectionRule = { type: "language"; languages: string[] } | { type: "reading_status"; reading_statuses: ReadingStatus[] } | { type: "author"; author_ids: string[] } | { type: "publisher"; publishers: string[] } | { type: "title_contains"; text: string } | { type: "added_within_days"; days: number } | { type: "last_read_within_days"; days: number }
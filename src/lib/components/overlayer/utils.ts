@@ -41,6 +41,7 @@ export async function addHighlightToDBAndStore(highlight: Highlight, bookId: str
 		id: highlight.id,
 		book_id: bookId,
 		color: highlight.color,
+		highlight_color_id: highlight.highlightColorId,
 		date_added: highlight.dateAdded,
 		start_container: getSelector(highlight.range.startContainer as Element | Text),
 		start_offset: highlight.range.startOffset,
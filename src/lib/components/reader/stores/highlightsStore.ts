@@ -9,6 +9,7 @@ export type Highlight = {
 	dateAdded: number;
 	range: Range;
 	color: string;
+	highlightColorId: string | null;
 	rects: DOMRect[];
 };
 
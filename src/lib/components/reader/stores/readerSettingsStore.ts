@@ -1,7 +1,7 @@
-import { writable } from 'svelte/store';
+import { get, writable } from 'svelte/store';
 import type { EnglishFont, LineHeight, TextAlign } from '../settings/settings';
 import type { Orientation } from '../utils';
-import { addBookSettings } from '$lib/bindings';
+import { addBookSettings, setBookReaderTheme, type BookSettings } from '$lib/bindings';
 
 export type ReaderSettings = {
 	fontSize: number;
@@ -27,6 +27,8 @@ export const mixBlendModeArray = [
 export type MixBlendMode = (typeof mixBlendModeArray)[number];
 
 export type ReaderThemeSettings = {
+	// The saved or built-in theme the colors come from, if they have not been edited since
+	id?: string;
 	name: string;
 	backgroundColor: string;
 	color: string;
@@ -38,6 +40,7 @@ export type ReaderThemeSettings = {
 export const readerThemeStore = writable<ReaderThemeSettings>();
 
 export const lightTheme = {
+	id: 'light',
 	name: 'Light',
 	backgroundColor: '#ffffff',
 	color: '#333333',
@@ -47,6 +50,7 @@ export const lightTheme = {
 } satisfies ReaderThemeSettings;
 
 export const darkTheme = {
+	id: 'dark',
 	name: 'Dark',
 	backgroundColor: '#202124',
 	color: '#f8f9fa',
@@ -59,6 +63,7 @@ export const presetReaderThemes: ReaderThemeSettings[] = [
 	lightTheme,
 	darkTheme,
 	{
+		id: 'dark-contrast',
 		name: 'Dark contrast',
 		backgroundColor: '#121212',
 		color: '#ffffff',
@@ -67,6 +72,7 @@ export const presetReaderThemes: ReaderThemeSettings[] = [
 		primaryColor: '#6c73d5'
 	},
 	{
+		id: 'sepia',
 		name: 'Sepia',
 		backgroundColor: '#fbf0d9',
 		color: '#5f4b32',
@@ -75,6 +81,7 @@ export const presetReaderThemes: ReaderThemeSettings[] = [
 		primaryColor: '#b77d7d'
 	},
 	{
+		id: 'sepia-contrast',
 		name: 'Sepia contrast',
 		backgroundColor: '#fbf0d9',
 		color: '#000000',
@@ -83,6 +90,7 @@ export const presetReaderThemes: ReaderThemeSettings[] = [
 		primaryColor: '#b77d7d'
 	},
 	{
+		id: 'green',
 		name: 'Green',
 		backgroundColor: '#c5e7ce',
 		color: '#3a4a43',
@@ -94,35 +102,126 @@ export const presetReaderThemes: ReaderThemeSettings[] = [
 
 export const savedReaderThemes = writable<(ReaderThemeSettings & { id: string })[]>([]);
 
-export async function addBookSettingsFromSettingsAndTheme(
+// The settings the book was opened with. Only what the reader changes after that is written to
+// the book, so the other settings keep following the reader defaults.
+let openedSettings: ReaderSettings | undefined;
+let openedTheme: ReaderThemeSettings | undefined;
+
+export function setOpenedSettingsAndTheme(settings: ReaderSettings, theme: ReaderThemeSettings) {
+	openedSettings = { ...settings };
+	openedTheme = { ...theme };
+}
+
+function emptyBookSettings(bookId: string): BookSettings {
+	return {
+		background_color: null,
+		book_id: bookId,
+		color: null,
+		column_count: null,
+		font_family: null,
+		font_size: null,
+		height: null,
+		id: crypto.randomUUID(),
+		image_blend_mode: null,
+		line_height: null,
+		link_color: null,
+		primary_color: null,
+		margins: null,
+		text_align: null,
+		width: null,
+		writing_mode: null,
+		last_element: null,
+		last_page: null,
+		percentage: null,
+		reader_theme_id: null,
+		user_css: null
+	};
+}
+
+function settingsColumns(settings: ReaderSettings) {
+	return {
+		column_count: settings.columnCount,
+		font_family: settings.fontFamily,
+		font_size: settings.fontSize,
+		line_height: String(settings.lineHeight),
+		margins: settings.margins,
+		text_align: settings.textAlign,
+		writing_mode: settings.writingMode
+	};
+}
+
+function themeColumns(theme: ReaderThemeSettings) {
+	return {
+		background_color: theme.backgroundColor,
+		color: theme.color,
+		image_blend_mode: theme.imageMixBlendMode,
+		link_color: theme.linkColor,
+		primary_color: theme.primaryColor
+	};
+}
+
+function changedColumns(
+	columns: Record<string, string | number>,
+	openedColumns?: Record<string, string | number>
+): Partial<BookSettings> {
+	return Object.fromEntries(
+		Object.entries(columns).filter(([key, value]) => openedColumns?.[key] !== value)
+	);
+}
+
+// Returns the id of the theme only if the colors still match it, as they can be edited after
+// picking a theme
+function unchangedReaderThemeId(theme: ReaderThemeSettings) {
+	const knownTheme = [...presetReaderThemes, ...get(savedReaderThemes)].find(
+		(t) => t.id === theme.id
+	);
+	if (!knownTheme) return undefined;
+
+	const columns = themeColumns(theme);
+	const knownColumns = themeColumns(knownTheme);
+	const changed = Object.keys(changedColumns(columns, knownColumns)).length > 0;
+	return changed ? undefined : knownTheme.id;
+}
+
+export async function saveChangedBookSettings(
+	bookId: string,
+	settings: ReaderSettings,
+	theme: ReaderThemeSettings
+) {
+	const changedSettings = changedColumns(
+		settingsColumns(settings),
+		openedSettings && settingsColumns(openedSettings)
+	);
+	let changedTheme = changedColumns(themeColumns(theme), openedTheme && themeColumns(openedTheme));
+	if (Object.keys(changedSettings).length === 0 && Object.keys(changedTheme).length === 0) return;
+
+	// A picked theme is saved by reference, so the book follows later edits of the theme
+	const readerThemeId = unchangedReaderThemeId(theme);
+	if (Object.keys(changedTheme).length > 0 && readerThemeId) {
+		await setBookReaderTheme(bookId, readerThemeId);
+		changedTheme = {};
+	}
+
+	if (Object.keys(changedSettings).length > 0 || Object.keys(changedTheme).length > 0) {
+		await addBookSettings({ ...emptyBookSettings(bookId), ...changedSettings, ...changedTheme });
+	}
+	setOpenedSettingsAndTheme(settings, theme);
+}
+
+export async function saveBookPosition(
 	bookId: string,
 	height: number,
 	width: number,
-	settings: ReaderSettings,
-	theme: ReaderThemeSettings,
-	percentage?: number,
+	percentage: number,
 	lastElement?: string,
 	lastPage?: number
 ) {
 	await addBookSettings({
-		background_color: theme.backgroundColor,
-		book_id: bookId,
-		color: theme.color,
-		column_count: settings.columnCount,
-		font_family: settings.fontFamily,
-		font_size: settings.fontSize,
+		...emptyBookSettings(bookId),
 		height,
-		id: crypto.randomUUID(),
-		image_blend_mode: theme.imageMixBlendMode,
-		line_height: String(settings.lineHeight),
-		link_color: theme.linkColor,
-		primary_color: theme.primaryColor,
-		margins: settings.margins,
-		text_align: settings.textAlign,
 		width,
-		writing_mode: settings.writingMode,
+		percentage,
 		last_element: lastElement ?? null,
-		last_page: lastPage ?? null,
-		percentage: percentage ?? null
+		last_page: lastPage ?? null
 	});
 }
@@ -1,11 +1,11 @@
 import type { PageLoad } from './$types';
-import { getBook, getReaderThemes } from '$lib/bindings';
+import { getBook, getEffectiveSettings, getReaderThemes } from '$lib/bindings';
 import {
 	readerSettingsStore,
 	readerThemeStore,
 	type MixBlendMode,
 	type ReaderThemeSettings,
-	addBookSettingsFromSettingsAndTheme,
+	setOpenedSettingsAndTheme,
 	type ReaderSettings,
 	savedReaderThemes,
 	lightTheme,
@@ -29,72 +29,68 @@ export const load = (async ({ params }) => {
 	}
 	console.log(book);
 
-	const savedThemes = (await getReaderThemes()).map((theme) => {
-		return {
-			backgroundColor: theme.background_color,
-			color: theme.color,
-			imageMixBlendMode: theme.image_blend_mode as MixBlendMode,
-			linkColor: theme.link_color,
-			name: theme.name,
-			primaryColor: theme.primary_color,
-			id: theme.id
-		} satisfies ReaderThemeSettings & { id: string };
-	});
-	savedReaderThemes.set(savedThemes);
+	// The settings of the book are only overrides, so the layout comes from the merged defaults
+	const settings = await getEffectiveSettings(params.id);
 
-	if (book.settings) {
-		readerSettingsStore.set({
-			columnCount: book.settings.column_count as 1 | 2,
-			fontSize: book.settings.font_size,
-			lineHeight: stringToLineHeight(book.settings.line_height),
-			textAlign: book.settings.text_align as TextAlign,
-			fontFamily: book.settings.font_family as EnglishFont,
-			writingMode: book.settings.writing_mode as Orientation,
-			margins: book.settings.margins
+	const readerThemes = await getReaderThemes();
+	// The built-in themes are listed as presets, so only the themes of the user are shown as saved
+	const savedThemes = readerThemes
+		.filter((theme) => !theme.built_in)
+		.map((theme) => {
+			return {
+				backgroundColor: theme.background_color,
+				color: theme.color,
+				imageMixBlendMode: theme.image_blend_mode as MixBlendMode,
+				linkColor: theme.link_color,
+				name: theme.name,
+				primaryColor: theme.primary_color,
+				id: theme.id
+			} satisfies ReaderThemeSettings & { id: string };
 		});
+	savedReaderThemes.set(savedThemes);
 
-		readerThemeStore.set({
-			name: 'Custom',
-			backgroundColor: book.settings.background_color,
-			color: book.settings.color,
-			linkColor: book.settings.link_color,
-			imageMixBlendMode: book.settings.image_blend_mode as MixBlendMode,
-			primaryColor: book.settings.primary_color
-		});
-	} else {
-		let writingMode: Orientation = 'horizontal';
-		if (book.page_progression_direction === 'rtl') {
-			writingMode = 'vertical';
-		}
+	// A right-to-left book reads vertically unless the book itself says otherwise
+	let writingMode = settings.writing_mode as Orientation;
+	if (!book.settings?.writing_mode && book.page_progression_direction === 'rtl') {
+		writingMode = 'vertical';
+	}
 
-		const defaultSettings = {
-			columnCount: 1,
-			fontSize: 16,
-			lineHeight: 'normal',
-			textAlign: 'initial',
-			fontFamily: 'initial',
-			writingMode,
-			margins: 0
-		} as ReaderSettings;
-		readerSettingsStore.set(defaultSettings);
+	const readerSettings = {
+		columnCount: settings.column_count as 1 | 2,
+		fontSize: settings.font_size,
+		lineHeight: stringToLineHeight(settings.line_height),
+		textAlign: settings.text_align as TextAlign,
+		fontFamily: settings.font_family as EnglishFont,
+		writingMode,
+		margins: settings.margins
+	} as ReaderSettings;
+	readerSettingsStore.set(readerSettings);
 
+	const readerThemeId = book.settings?.reader_theme_id ?? undefined;
+	let readerTheme: ReaderThemeSettings;
+	if (readerThemeId || book.settings?.background_color) {
+		readerTheme = {
+			id: readerThemeId,
+			name: readerThemes.find((theme) => theme.id === readerThemeId)?.name ?? 'Custom',
+			backgroundColor: settings.background_color,
+			color: settings.color,
+			linkColor: settings.link_color,
+			imageMixBlendMode: settings.image_blend_mode as MixBlendMode,
+			primaryColor: settings.primary_color
+		};
+	} else {
 		const lastUsedThemeStr = localStorage.getItem('last-reader-theme');
-		let defaultTheme;
 		if (lastUsedThemeStr) {
-			const lastUsedTheme = JSON.parse(lastUsedThemeStr) as ReaderThemeSettings;
-			readerThemeStore.set(lastUsedTheme);
-			defaultTheme = lastUsedTheme;
+			readerTheme = JSON.parse(lastUsedThemeStr) as ReaderThemeSettings;
+		} else if (localStorage.getItem('theme') === 'dark') {
+			readerTheme = darkTheme;
 		} else {
-			if (localStorage.getItem('theme') === 'dark') {
-				defaultTheme = darkTheme;
-			} else {
-				defaultTheme = lightTheme;
-			}
-
-			readerThemeStore.set(defaultTheme);
+			readerTheme = lightTheme;
 		}
-		await addBookSettingsFromSettingsAndTheme(params.id, 860, 512, defaultSettings, defaultTheme);
 	}
+	readerThemeStore.set(readerTheme);
+
+	setOpenedSettingsAndTheme(readerSettings, readerTheme);
 
 	return { book };
 }) satisfies PageLoad;